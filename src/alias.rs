@@ -0,0 +1,156 @@
+use crate::format::{EncodedInstruction, Mnemonic, Operands};
+use crate::operand::{CsrAddr, FenceSet, Imm, Operand, Reg};
+
+const ZERO: Operand = Operand::Reg(Reg(0));
+const RA: Operand = Operand::Reg(Reg(1));
+const IMM_0: Operand = Operand::Imm(Imm(0));
+const IMM_1: Operand = Operand::Imm(Imm(1));
+const IMM_NEG_1: Operand = Operand::Imm(Imm(-1));
+const IORW: Operand = Operand::FenceSet(FenceSet(0b1111));
+const FFLAGS: Operand = Operand::Csr(CsrAddr(0x001));
+const FRM: Operand = Operand::Csr(CsrAddr(0x002));
+const FCSR: Operand = Operand::Csr(CsrAddr(0x003));
+const CYCLE: Operand = Operand::Csr(CsrAddr(0xc00));
+const TIME: Operand = Operand::Csr(CsrAddr(0xc01));
+const INSTRET: Operand = Operand::Csr(CsrAddr(0xc02));
+const CYCLEH: Operand = Operand::Csr(CsrAddr(0xc80));
+const TIMEH: Operand = Operand::Csr(CsrAddr(0xc81));
+const INSTRETH: Operand = Operand::Csr(CsrAddr(0xc82));
+
+// the pseudo-instruction objdump shows for a decoded instruction, e.g. `ret` for
+// `jalr x0, 0(x1)`. The alias keeps the instruction word, its operands are a subset of
+// the original ones in the same order, and `encode` reads it back to the same word.
+// Compressed instructions keep their `c.` mnemonic except c.unimp, which objdump shows as
+// unimp as well; that text reads back as the 32-bit csrrw
+pub fn alias(d: &EncodedInstruction) -> Option<EncodedInstruction> {
+    if let Some(alias) = sign_injection_alias(d) {
+        return Some(alias);
+    }
+    let (mnemonic, operands) = match (d.mnemonic.as_str(), &d.operands[..]) {
+        ("addi", [ZERO, ZERO, IMM_0]) => ("nop", Operands::new()),
+        ("addi", [rd, ZERO, imm]) => ("li", Operands::from([*rd, *imm])),
+        ("addi", [rd, rs, IMM_0]) => ("mv", Operands::from([*rd, *rs])),
+        ("xori", [rd, rs, IMM_NEG_1]) => ("not", Operands::from([*rd, *rs])),
+        ("sub", [rd, ZERO, rs]) => ("neg", Operands::from([*rd, *rs])),
+        ("subw", [rd, ZERO, rs]) => ("negw", Operands::from([*rd, *rs])),
+        ("addiw", [rd, rs, IMM_0]) => ("sext.w", Operands::from([*rd, *rs])),
+        ("add.uw", [rd, rs, ZERO]) => ("zext.w", Operands::from([*rd, *rs])),
+        ("sltiu", [rd, rs, IMM_1]) => ("seqz", Operands::from([*rd, *rs])),
+        ("sltu", [rd, ZERO, rs]) => ("snez", Operands::from([*rd, *rs])),
+        ("slt", [rd, rs, ZERO]) => ("sltz", Operands::from([*rd, *rs])),
+        ("slt", [rd, ZERO, rs]) => ("sgtz", Operands::from([*rd, *rs])),
+        ("beq", [rs, ZERO, off]) => ("beqz", Operands::from([*rs, *off])),
+        ("bne", [rs, ZERO, off]) => ("bnez", Operands::from([*rs, *off])),
+        ("bge", [ZERO, rs, off]) => ("blez", Operands::from([*rs, *off])),
+        ("bge", [rs, ZERO, off]) => ("bgez", Operands::from([*rs, *off])),
+        ("blt", [rs, ZERO, off]) => ("bltz", Operands::from([*rs, *off])),
+        ("blt", [ZERO, rs, off]) => ("bgtz", Operands::from([*rs, *off])),
+        ("jal", [ZERO, off]) => ("j", Operands::from([*off])),
+        ("jal", [RA, off]) => ("jal", Operands::from([*off])),
+        ("jalr", [ZERO, IMM_0, RA]) => ("ret", Operands::new()),
+        ("jalr", [ZERO, IMM_0, rs]) => ("jr", Operands::from([*rs])),
+        ("jalr", [RA, IMM_0, rs]) => ("jalr", Operands::from([*rs])),
+        ("csrrs", [rd, csr, ZERO]) => match read_alias(*csr) {
+            Some(name) => (name, Operands::from([*rd])),
+            None => ("csrr", Operands::from([*rd, *csr])),
+        },
+        ("csrrw", [ZERO, CYCLE, ZERO]) | ("c.unimp", []) => ("unimp", Operands::new()),
+        ("csrrw", [ZERO, csr @ (FCSR | FRM | FFLAGS), rs]) => {
+            (write_alias(*csr), Operands::from([*rs]))
+        }
+        ("csrrw", [rd, csr @ (FCSR | FRM | FFLAGS), rs]) => {
+            (write_alias(*csr), Operands::from([*rd, *rs]))
+        }
+        ("csrrw", [ZERO, csr, rs]) => ("csrw", Operands::from([*csr, *rs])),
+        ("csrrs", [ZERO, csr, rs]) => ("csrs", Operands::from([*csr, *rs])),
+        ("csrrc", [ZERO, csr, rs]) => ("csrc", Operands::from([*csr, *rs])),
+        ("csrrwi", [ZERO, csr, imm]) => ("csrwi", Operands::from([*csr, *imm])),
+        ("csrrsi", [ZERO, csr, imm]) => ("csrsi", Operands::from([*csr, *imm])),
+        ("csrrci", [ZERO, csr, imm]) => ("csrci", Operands::from([*csr, *imm])),
+        ("fence", [IORW, IORW]) => ("fence", Operands::new()),
+        // the address-translation fences leave out x0 operands from the right
+        (
+            m @ ("sfence.vma" | "sinval.vma" | "hfence.vvma" | "hfence.gvma" | "hinval.vvma"
+            | "hinval.gvma"),
+            [rs1, ZERO],
+        ) => match *rs1 {
+            ZERO => (m, Operands::new()),
+            _ => (m, Operands::from([*rs1])),
+        },
+        // the vector aliases keep a trailing `v0.t`
+        ("vrsub.vx", [vd, vs, ZERO, mask @ ..]) => ("vneg.v", masked(&[*vd, *vs], mask)),
+        ("vxor.vi", [vd, vs, IMM_NEG_1, mask @ ..]) => ("vnot.v", masked(&[*vd, *vs], mask)),
+        ("vwadd.vx", [vd, vs, ZERO, mask @ ..]) => ("vwcvt.x.x.v", masked(&[*vd, *vs], mask)),
+        ("vwaddu.vx", [vd, vs, ZERO, mask @ ..]) => ("vwcvtu.x.x.v", masked(&[*vd, *vs], mask)),
+        ("vnsrl.wx", [vd, vs, ZERO, mask @ ..]) => ("vncvt.x.x.w", masked(&[*vd, *vs], mask)),
+        ("vfsgnjn.vv", [vd, vs, vt, mask @ ..]) if vs == vt => {
+            ("vfneg.v", masked(&[*vd, *vs], mask))
+        }
+        ("vfsgnjx.vv", [vd, vs, vt, mask @ ..]) if vs == vt => {
+            ("vfabs.v", masked(&[*vd, *vs], mask))
+        }
+        ("vmxor.mm", [vd, vs, vt]) if vd == vs && vs == vt => ("vmclr.m", Operands::from([*vd])),
+        ("vmxnor.mm", [vd, vs, vt]) if vd == vs && vs == vt => ("vmset.m", Operands::from([*vd])),
+        ("vmand.mm", [vd, vs, vt]) if vs == vt => ("vmmv.m", Operands::from([*vd, *vs])),
+        ("vmnand.mm", [vd, vs, vt]) if vs == vt => ("vmnot.m", Operands::from([*vd, *vs])),
+        _ => return None,
+    };
+    Some(EncodedInstruction {
+        instr: d.instr,
+        mnemonic: Mnemonic::from(mnemonic),
+        operands,
+        xlen: d.xlen,
+    })
+}
+
+fn masked(operands: &[Operand], mask: &[Operand]) -> Operands {
+    operands.iter().chain(mask).copied().collect()
+}
+
+// counters and floating-point CSRs read with csrrs into rd
+fn read_alias(csr: Operand) -> Option<&'static str> {
+    match csr {
+        CYCLE => Some("rdcycle"),
+        TIME => Some("rdtime"),
+        INSTRET => Some("rdinstret"),
+        CYCLEH => Some("rdcycleh"),
+        TIMEH => Some("rdtimeh"),
+        INSTRETH => Some("rdinstreth"),
+        FCSR => Some("frcsr"),
+        FRM => Some("frrm"),
+        FFLAGS => Some("frflags"),
+        _ => None,
+    }
+}
+
+// floating-point CSRs swapped with csrrw, the old value goes to rd unless it is x0
+fn write_alias(csr: Operand) -> &'static str {
+    match csr {
+        FCSR => "fscsr",
+        FRM => "fsrm",
+        _ => "fsflags",
+    }
+}
+
+// fmv, fneg and fabs are sign injections of a register with itself
+fn sign_injection_alias(d: &EncodedInstruction) -> Option<EncodedInstruction> {
+    let [rd, rs, rt] = d.operands[..] else {
+        return None;
+    };
+    let (name, fmt) = d.mnemonic.split_once('.')?;
+    let name = match name {
+        "fsgnj" => "fmv",
+        "fsgnjn" => "fneg",
+        "fsgnjx" => "fabs",
+        _ => return None,
+    };
+    if rs != rt {
+        return None;
+    }
+    Some(EncodedInstruction {
+        instr: d.instr,
+        mnemonic: mnemonic!("{}.{}", name, fmt),
+        operands: Operands::from([rd, rs]),
+        xlen: d.xlen,
+    })
+}
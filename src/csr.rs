@@ -0,0 +1,139 @@
+use core::fmt;
+
+// CSR names from the privileged spec, by address
+#[rustfmt::skip]
+const CSR_NAMES: &[(u16, &str)] = &[
+    // unprivileged floating-point, vector and entropy source
+    (0x001, "fflags"), (0x002, "frm"), (0x003, "fcsr"),
+    (0x008, "vstart"), (0x009, "vxsat"), (0x00a, "vxrm"), (0x00f, "vcsr"),
+    (0x015, "seed"), (0x017, "jvt"),
+    (0xc20, "vl"), (0xc21, "vtype"), (0xc22, "vlenb"),
+    // unprivileged counters and timers
+    (0xc00, "cycle"), (0xc01, "time"), (0xc02, "instret"),
+    (0xc80, "cycleh"), (0xc81, "timeh"), (0xc82, "instreth"),
+    // supervisor
+    (0x100, "sstatus"), (0x104, "sie"), (0x105, "stvec"), (0x106, "scounteren"),
+    (0x10a, "senvcfg"), (0x10c, "sstateen0"), (0x10d, "sstateen1"), (0x10e, "sstateen2"),
+    (0x10f, "sstateen3"), (0x120, "scountinhibit"), (0x140, "sscratch"), (0x141, "sepc"),
+    (0x142, "scause"), (0x143, "stval"), (0x144, "sip"), (0x14d, "stimecmp"),
+    (0x15d, "stimecmph"), (0x180, "satp"), (0x5a8, "scontext"),
+    // hypervisor and virtual supervisor
+    (0x600, "hstatus"), (0x602, "hedeleg"), (0x603, "hideleg"), (0x604, "hie"),
+    (0x605, "htimedelta"), (0x606, "hcounteren"), (0x607, "hgeie"), (0x60a, "henvcfg"),
+    (0x60c, "hstateen0"), (0x60d, "hstateen1"), (0x60e, "hstateen2"), (0x60f, "hstateen3"),
+    (0x612, "hedelegh"), (0x615, "htimedeltah"), (0x61a, "henvcfgh"), (0x61c, "hstateen0h"),
+    (0x61d, "hstateen1h"), (0x61e, "hstateen2h"), (0x61f, "hstateen3h"), (0x643, "htval"),
+    (0x644, "hip"), (0x645, "hvip"), (0x64a, "htinst"), (0x680, "hgatp"),
+    (0x6a8, "hcontext"), (0xe12, "hgeip"),
+    (0x200, "vsstatus"), (0x204, "vsie"), (0x205, "vstvec"), (0x240, "vsscratch"),
+    (0x241, "vsepc"), (0x242, "vscause"), (0x243, "vstval"), (0x244, "vsip"),
+    (0x24d, "vstimecmp"), (0x25d, "vstimecmph"), (0x280, "vsatp"),
+    // machine information, trap setup and handling
+    (0xf11, "mvendorid"), (0xf12, "marchid"), (0xf13, "mimpid"), (0xf14, "mhartid"),
+    (0xf15, "mconfigptr"),
+    (0x300, "mstatus"), (0x301, "misa"), (0x302, "medeleg"), (0x303, "mideleg"),
+    (0x304, "mie"), (0x305, "mtvec"), (0x306, "mcounteren"), (0x30a, "menvcfg"),
+    (0x30c, "mstateen0"), (0x30d, "mstateen1"), (0x30e, "mstateen2"), (0x30f, "mstateen3"),
+    (0x310, "mstatush"), (0x312, "medelegh"), (0x31a, "menvcfgh"), (0x31c, "mstateen0h"),
+    (0x31d, "mstateen1h"), (0x31e, "mstateen2h"), (0x31f, "mstateen3h"), (0x320, "mcountinhibit"),
+    (0x340, "mscratch"), (0x341, "mepc"), (0x342, "mcause"), (0x343, "mtval"),
+    (0x344, "mip"), (0x34a, "mtinst"), (0x34b, "mtval2"),
+    (0x740, "mnscratch"), (0x741, "mnepc"), (0x742, "mncause"), (0x744, "mnstatus"),
+    (0x747, "mseccfg"), (0x757, "mseccfgh"),
+    // machine counters
+    (0xb00, "mcycle"), (0xb02, "minstret"), (0xb80, "mcycleh"), (0xb82, "minstreth"),
+    // debug and trace triggers, then debug mode
+    (0x7a0, "tselect"), (0x7a1, "tdata1"), (0x7a2, "tdata2"), (0x7a3, "tdata3"),
+    (0x7a4, "tinfo"), (0x7a5, "tcontrol"), (0x7a8, "mcontext"), (0x7aa, "mscontext"),
+    (0x7b0, "dcsr"), (0x7b1, "dpc"), (0x7b2, "dscratch0"), (0x7b3, "dscratch1"),
+];
+
+// numbered CSRs: name prefix and suffix, address of the first index, index range
+#[rustfmt::skip]
+const CSR_SERIES: &[(&str, &str, u16, u16, u16)] = &[
+    ("hpmcounter", "", 0xc03, 3, 31),
+    ("hpmcounter", "h", 0xc83, 3, 31),
+    ("mhpmcounter", "", 0xb03, 3, 31),
+    ("mhpmcounter", "h", 0xb83, 3, 31),
+    ("mhpmevent", "", 0x323, 3, 31),
+    ("mhpmevent", "h", 0x723, 3, 31),
+    ("pmpcfg", "", 0x3a0, 0, 15),
+    ("pmpaddr", "", 0x3b0, 0, 63),
+];
+
+// csr[9:8], the lowest privilege level that can access the CSR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Privilege {
+    User,
+    Supervisor,
+    Hypervisor,
+    Machine,
+}
+
+// a CSR name, numbered ones like `pmpaddr3` are put together when displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsrName {
+    prefix: &'static str,
+    index: Option<u16>,
+    suffix: &'static str,
+}
+
+pub fn csr_name(addr: u16) -> Option<CsrName> {
+    if let Some(&(_, name)) = CSR_NAMES.iter().find(|&&(a, _)| a == addr) {
+        return Some(CsrName {
+            prefix: name,
+            index: None,
+            suffix: "",
+        });
+    }
+    CSR_SERIES
+        .iter()
+        .find(|&&(_, _, base, first, last)| (base..=base + last - first).contains(&addr))
+        .map(|&(prefix, suffix, base, first, _)| CsrName {
+            prefix,
+            index: Some(first + addr - base),
+            suffix,
+        })
+}
+
+pub fn csr_address(name: &str) -> Option<u16> {
+    if let Some(&(addr, _)) = CSR_NAMES.iter().find(|&&(_, n)| n == name) {
+        return Some(addr);
+    }
+    CSR_SERIES
+        .iter()
+        .find_map(|&(prefix, suffix, base, first, last)| {
+            let digits = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            // no sign and no leading zeros, `pmpcfg01` is not a name
+            if !digits.bytes().all(|b| b.is_ascii_digit())
+                || (digits.len() > 1 && digits.starts_with('0'))
+            {
+                return None;
+            }
+            let index = digits.parse::<u16>().ok()?;
+            (first..=last)
+                .contains(&index)
+                .then(|| base + index - first)
+        })
+}
+
+impl fmt::Display for CsrName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.prefix)?;
+        if let Some(index) = self.index {
+            write!(f, "{}", index)?;
+        }
+        f.write_str(self.suffix)
+    }
+}
+
+impl fmt::Display for Privilege {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Privilege::User => "user",
+            Privilege::Supervisor => "supervisor",
+            Privilege::Hypervisor => "hypervisor",
+            Privilege::Machine => "machine",
+        })
+    }
+}
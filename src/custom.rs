@@ -0,0 +1,459 @@
+// user-defined instructions in the custom-0 to custom-3 opcodes, loaded at runtime from a
+// definition file. Each instruction is a section with its match/mask, the field layout
+// of the word from bit 31 down to 0 and the fields that hold its operands:
+//
+//   # multiply-accumulate of the accelerator
+//   [acc.mac]
+//   match = 0x0000000b
+//   mask = 0xfe00707f
+//   fields = funct7 31:25, rs2 24:20, rs1 19:15, funct3 14:12, rd 11:7, opcode 6:0
+//   operands = rd: reg, rs1: reg, rs2: reg
+//
+// operand kinds are `reg`, `freg`, `vreg`, `imm` (sign-extended) and `uimm`. Every bit is
+// either fixed by the mask or part of an operand, so each matching word round-trips.
+// The definitions live in a `CustomTable` that `decode_with`, `encode_with` and
+// `assemble_with` consult, `decode` and `encode` only know the built-in instructions
+use crate::encoder;
+use crate::error::{Error, Result};
+use crate::format::{
+    CustomType, EncodedInstruction, Instruction, MAX_OPERANDS, MNEMONIC_LEN, Mnemonic, Operands,
+    Xlen,
+};
+use crate::operand::Operand;
+use crate::util::{is_hex, parse_freg, parse_immediate, parse_reg, parse_vreg, sign_extend};
+
+// custom-0, custom-1, custom-2/rv128 and custom-3/rv128, the last two are free below RV128
+const CUSTOM_OPCODES: [u32; 4] = [0b0001011, 0b0101011, 0b1011011, 0b1111011];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperandKind {
+    Reg,
+    FReg,
+    VReg,
+    Imm,
+    UImm,
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    hi: u32,
+    lo: u32,
+}
+
+#[derive(Debug, Clone)]
+struct CustomInstruction {
+    mnemonic: Mnemonic,
+    match_bits: u32,
+    mask: u32,
+    // msb first, covering the whole word
+    fields: Vec<Field>,
+    // index into fields, in assembly order
+    operands: Vec<(usize, OperandKind)>,
+}
+
+// the custom instructions of one or more definition files
+#[derive(Debug, Clone, Default)]
+pub struct CustomTable {
+    instructions: Vec<CustomInstruction>,
+}
+
+impl Field {
+    fn width(&self) -> u32 {
+        self.hi - self.lo + 1
+    }
+
+    fn mask(&self) -> u32 {
+        (u32::MAX >> (32 - self.width())) << self.lo
+    }
+
+    fn get(&self, instr: u32) -> u32 {
+        (instr & self.mask()) >> self.lo
+    }
+}
+
+impl CustomInstruction {
+    fn matches(&self, instr: u32) -> bool {
+        instr & self.mask == self.match_bits
+    }
+
+    // two definitions overlap when a word matches both
+    fn overlaps(&self, other: &CustomInstruction) -> bool {
+        (self.match_bits ^ other.match_bits) & self.mask & other.mask == 0
+    }
+}
+
+impl CustomTable {
+    // the table of a single definition file
+    pub fn parse(definitions: &str) -> Result<Self> {
+        let mut table = CustomTable::default();
+        table.load(definitions)?;
+        Ok(table)
+    }
+
+    // adds the definitions of another file, all of them or none. Names taken by a
+    // built-in or an earlier instruction and encodings that overlap another definition
+    // are rejected
+    pub fn load(&mut self, definitions: &str) -> Result<()> {
+        let parsed = parse_definitions(definitions)?;
+        for (idx, def) in parsed.iter().enumerate() {
+            let builtin = !matches!(
+                encoder::encode(&def.mnemonic, Xlen::Rv64),
+                Err(Error::UnknownInstruction)
+            );
+            let mut others = self.instructions.iter().chain(&parsed[..idx]);
+            if builtin || others.any(|other| other.mnemonic == def.mnemonic || other.overlaps(def))
+            {
+                return Err(Error::InvalidDefinition);
+            }
+        }
+        self.instructions.extend(parsed);
+        Ok(())
+    }
+
+    // the loaded instruction a word belongs to, None for any other word
+    pub(crate) fn decode(&self, instr: u32, xlen: Xlen) -> Option<EncodedInstruction> {
+        let def = self.instructions.iter().find(|def| def.matches(instr))?;
+        let mut custom = CustomType {
+            word: instr,
+            field_lsbs: def
+                .fields
+                .iter()
+                .fold(0, |lsbs, field| lsbs | 1 << field.lo),
+            operand_lsbs: [0; MAX_OPERANDS],
+        };
+        let mut operands = Operands::new();
+        for (&(idx, kind), lsb) in def.operands.iter().zip(&mut custom.operand_lsbs) {
+            let field = &def.fields[idx];
+            let value = field.get(instr);
+            operands.push(match kind {
+                OperandKind::Reg => Operand::reg(value),
+                OperandKind::FReg => Operand::freg(value),
+                OperandKind::VReg => Operand::vreg(value),
+                OperandKind::Imm => Operand::imm(sign_extend(value, field.width())),
+                OperandKind::UImm => Operand::imm(value as i32),
+            });
+            *lsb = field.lo as u8;
+        }
+        Some(EncodedInstruction {
+            instr: Instruction::Custom(custom),
+            mnemonic: def.mnemonic,
+            operands,
+            xlen,
+        })
+    }
+
+    // the instruction word of a loaded instruction, None for any other mnemonic
+    pub(crate) fn encode(&self, mnemonic: &str, operands: &[String]) -> Result<Option<u32>> {
+        let Some(def) = self
+            .instructions
+            .iter()
+            .find(|def| def.mnemonic == mnemonic)
+        else {
+            return Ok(None);
+        };
+        if operands.len() != def.operands.len() {
+            return Err(Error::InvalidFormat);
+        }
+        let mut instr = def.match_bits;
+        for (op, &(idx, kind)) in operands.iter().zip(&def.operands) {
+            let field = &def.fields[idx];
+            let width = field.width();
+            let value = match kind {
+                OperandKind::Reg => parse_reg(op)?,
+                OperandKind::FReg => parse_freg(op)?,
+                OperandKind::VReg => parse_vreg(op)?,
+                OperandKind::Imm => {
+                    let imm = parse_immediate(op)?;
+                    let (min, max) = (-(1i64 << (width - 1)), (1i64 << (width - 1)) - 1);
+                    if !(min..=max).contains(&(imm as i64)) {
+                        return Err(Error::ImmediateOutOfRange);
+                    }
+                    imm as u32
+                }
+                OperandKind::UImm => {
+                    let imm = parse_immediate(op)?;
+                    if imm < 0 || imm as u64 >= 1 << width {
+                        return Err(Error::ImmediateOutOfRange);
+                    }
+                    imm as u32
+                }
+            };
+            instr |= (value << field.lo) & field.mask();
+        }
+        Ok(Some(instr))
+    }
+}
+
+fn parse_definitions(definitions: &str) -> Result<Vec<CustomInstruction>> {
+    // each section collects its keys before they are checked together
+    let mut sections: Vec<(&str, Vec<(&str, &str)>)> = Vec::new();
+    for line in definitions.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim(), Vec::new()));
+        } else {
+            let (key, value) = line.split_once('=').ok_or(Error::InvalidDefinition)?;
+            let (_, keys) = sections.last_mut().ok_or(Error::InvalidDefinition)?;
+            keys.push((key.trim(), value.trim()));
+        }
+    }
+    sections
+        .iter()
+        .map(|(name, keys)| parse_instruction(name, keys))
+        .collect()
+}
+
+fn parse_instruction(name: &str, keys: &[(&str, &str)]) -> Result<CustomInstruction> {
+    // exactly the four keys, each once
+    let known = ["match", "mask", "fields", "operands"];
+    if keys.len() != known.len() || keys.iter().any(|(key, _)| !known.contains(key)) {
+        return Err(Error::InvalidDefinition);
+    }
+    let value = |key: &str| {
+        keys.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+            .ok_or(Error::InvalidDefinition)
+    };
+    // the encoder reads mnemonics lowercase and splits operands at spaces and commas
+    let valid_name = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '_');
+    if name.is_empty() || name.len() > MNEMONIC_LEN || !valid_name {
+        return Err(Error::InvalidDefinition);
+    }
+
+    let match_bits = parse_word(value("match")?)?;
+    let mask = parse_word(value("mask")?)?;
+    if match_bits & !mask != 0
+        || mask & 0x7f != 0x7f
+        || !CUSTOM_OPCODES.contains(&(match_bits & 0x7f))
+    {
+        return Err(Error::InvalidDefinition);
+    }
+
+    let fields = value("fields")?
+        .split(',')
+        .map(parse_field)
+        .collect::<Result<Vec<_>>>()?;
+    // the fields run from bit 31 down to 0 without gaps
+    let mut next = 31;
+    for (idx, field) in fields.iter().enumerate() {
+        let unique = fields[..idx].iter().all(|f| f.name != field.name);
+        if field.hi != next || !unique {
+            return Err(Error::InvalidDefinition);
+        }
+        next = field.lo.wrapping_sub(1);
+    }
+    if next != u32::MAX {
+        return Err(Error::InvalidDefinition);
+    }
+
+    let mut operands = Vec::new();
+    let mut operand_bits = 0;
+    for operand in value("operands")?
+        .split(',')
+        .filter(|op| !op.trim().is_empty())
+    {
+        let (field, kind) = operand.split_once(':').ok_or(Error::InvalidDefinition)?;
+        let idx = fields
+            .iter()
+            .position(|f| f.name == field.trim())
+            .ok_or(Error::InvalidDefinition)?;
+        let kind = match kind.trim() {
+            "reg" => OperandKind::Reg,
+            "freg" => OperandKind::FReg,
+            "vreg" => OperandKind::VReg,
+            "imm" => OperandKind::Imm,
+            "uimm" => OperandKind::UImm,
+            _ => return Err(Error::InvalidDefinition),
+        };
+        let is_reg = matches!(
+            kind,
+            OperandKind::Reg | OperandKind::FReg | OperandKind::VReg
+        );
+        let bits = fields[idx].mask();
+        if is_reg && fields[idx].width() != 5 || bits & (mask | operand_bits) != 0 {
+            return Err(Error::InvalidDefinition);
+        }
+        operand_bits |= bits;
+        operands.push((idx, kind));
+    }
+    if mask | operand_bits != u32::MAX || operands.len() > MAX_OPERANDS {
+        return Err(Error::InvalidDefinition);
+    }
+
+    Ok(CustomInstruction {
+        mnemonic: Mnemonic::from(name),
+        match_bits,
+        mask,
+        fields,
+        operands,
+    })
+}
+
+fn parse_word(s: &str) -> Result<u32> {
+    if is_hex(s) {
+        u32::from_str_radix(&s[2..].replace('_', ""), 16).map_err(|_| Error::InvalidDefinition)
+    } else {
+        s.parse::<u32>().map_err(|_| Error::InvalidDefinition)
+    }
+}
+
+// `name hi:lo`, or `name bit` for a single bit
+fn parse_field(s: &str) -> Result<Field> {
+    let (name, bits) = s.trim().split_once(' ').ok_or(Error::InvalidDefinition)?;
+    let (hi, lo) = bits.trim().split_once(':').unwrap_or((bits, bits));
+    let bit = |b: &str| match b.trim().parse::<u32>() {
+        Ok(b) if b < 32 => Ok(b),
+        _ => Err(Error::InvalidDefinition),
+    };
+    let (hi, lo) = (bit(hi)?, bit(lo)?);
+    if hi < lo {
+        return Err(Error::InvalidDefinition);
+    }
+    Ok(Field {
+        name: name.to_string(),
+        hi,
+        lo,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{decode, decode_with};
+    use crate::encoder::{encode, encode_with};
+
+    const MAC: &str = "
+        [acc.mac]
+        match = 0x0000000b
+        mask = 0xfe00707f
+        fields = funct7 31:25, rs2 24:20, rs1 19:15, funct3 14:12, rd 11:7, opcode 6:0
+        operands = rd: reg, rs1: reg, rs2: reg
+    ";
+
+    // acc.mac with the fields and operands swapped for the given ones
+    fn mac(name: &str, match_bits: &str, fields: &str, operands: &str) -> String {
+        format!(
+            "[{}]\nmatch = {}\nmask = 0xfe00707f\nfields = {}\noperands = {}\n",
+            name, match_bits, fields, operands
+        )
+    }
+
+    #[test]
+    fn table_round_trip() {
+        let table = CustomTable::parse(MAC).unwrap();
+        let d = encode_with(&table, "acc.mac a0, a1, a2", Xlen::Rv32).unwrap();
+        assert_eq!(d.instr.raw(), 0x00c5850b);
+        let d = decode_with(&table, 0x00c5850b, Xlen::Rv64).unwrap();
+        assert_eq!(d.to_string(), "acc.mac x10, x11, x12");
+        assert_eq!(d.xlen, Xlen::Rv64);
+        // the built-in instructions still encode through the table
+        let d = encode_with(&table, "addi a0, a0, 1", Xlen::Rv32).unwrap();
+        assert_eq!(d.instr.raw(), 0x00150513);
+        // without the table the custom opcodes hold nothing
+        assert_eq!(
+            decode(0x00c5850b, Xlen::Rv32).unwrap_err(),
+            Error::InvalidOpcode
+        );
+        let err = encode("acc.mac a0, a1, a2", Xlen::Rv32).unwrap_err();
+        assert_eq!(err, Error::UnknownInstruction);
+        let other = CustomTable::default();
+        let err = decode_with(&other, 0x00c5850b, Xlen::Rv32).unwrap_err();
+        assert_eq!(err, Error::InvalidOpcode);
+    }
+
+    #[test]
+    fn definition_errors() {
+        let fields = "funct7 31:25, rs2 24:20, rs1 19:15, funct3 14:12, rd 11:7, opcode 6:0";
+        let operands = "rd: reg, rs1: reg, rs2: reg";
+        assert!(CustomTable::parse(&mac("acc.ok", "0x0000000b", fields, operands)).is_ok());
+        for definitions in [
+            // overlapping encodings in one file
+            format!(
+                "{}{}",
+                mac("acc.a", "0x0000000b", fields, operands),
+                mac("acc.b", "0x0000000b", fields, operands)
+            ),
+            // gaps in the field layout
+            mac(
+                "acc.gap",
+                "0x0000000b",
+                "funct7 31:25, rs2 23:20, rs1 19:15, funct3 14:12, rd 11:7, opcode 6:0",
+                operands,
+            ),
+            mac(
+                "acc.short",
+                "0x0000000b",
+                "funct7 31:25, rs2 24:20, rs1 19:15, funct3 14:12, rd 11:7, opcode 6:1",
+                operands,
+            ),
+            mac(
+                "acc.overlap",
+                "0x0000000b",
+                "funct7 31:25, rs2 25:20, rs1 19:15, funct3 14:12, rd 11:7, opcode 6:0",
+                operands,
+            ),
+            // register fields that are not 5 bits wide
+            mac(
+                "acc.wide",
+                "0x0000000b",
+                "funct7 31:25, rs1 24:15, funct3 14:12, rd 11:7, opcode 6:0",
+                "rd: reg, rs1: reg",
+            ),
+            mac(
+                "acc.narrow",
+                "0x0000000b",
+                "funct7 31:25, rs2 24:20, rs1 19:15, funct3 14:12, rd 11:8, lo 7, opcode 6:0",
+                "rd: reg, rs1: reg, rs2: reg, lo: uimm",
+            ),
+            // names of built-in instructions and pseudo-instructions
+            mac("add", "0x0000000b", fields, operands),
+            mac("li", "0x0000000b", fields, operands),
+            mac("c.nop", "0x0000000b", fields, operands),
+            // the same name twice
+            format!(
+                "{}{}",
+                mac("acc.a", "0x0000000b", fields, operands),
+                mac("acc.a", "0x0200000b", fields, operands)
+            ),
+        ] {
+            assert_eq!(
+                CustomTable::parse(&definitions).unwrap_err(),
+                Error::InvalidDefinition,
+                "{}",
+                definitions
+            );
+        }
+    }
+
+    // a later file may not clash with the earlier ones and leaves the table as it was
+    #[test]
+    fn load_rejects_clashes() {
+        let fields = "funct7 31:25, rs2 24:20, rs1 19:15, funct3 14:12, rd 11:7, opcode 6:0";
+        let operands = "rd: reg, rs1: reg, rs2: reg";
+        let mut table = CustomTable::parse(MAC).unwrap();
+        for definitions in [
+            mac("acc.mul", "0x0000000b", fields, operands),
+            mac("acc.mac", "0x0200000b", fields, operands),
+            format!(
+                "{}{}",
+                mac("acc.new", "0x0200000b", fields, operands),
+                mac("acc.bad", "0x0000000b", fields, operands)
+            ),
+        ] {
+            assert_eq!(table.load(&definitions), Err(Error::InvalidDefinition));
+        }
+        assert_eq!(table.instructions.len(), 1);
+        table
+            .load(&mac("acc.mul", "0x0200000b", fields, operands))
+            .unwrap();
+        let d = encode_with(&table, "acc.mul a0, a1, a2", Xlen::Rv32).unwrap();
+        assert_eq!(d.instr.raw(), 0x02c5850b);
+    }
+}
@@ -1,11 +1,35 @@
+#[cfg(feature = "std")]
+use crate::custom::CustomTable;
 use crate::{
     error::{Error, Result},
-    format::{BType, CSRType, EncodedInstruction, IType, Instruction, JType, RType, SType, UType},
+    format::{
+        AMOType, BType, ByteSelectType, CAType, CB_BRANCH_6_2, CB_BRANCH_12_10, CBType,
+        CI_ADDI16SP_6_2, CI_ADDI16SP_12, CI_IMM_6_2, CI_IMM_12, CI_LDSP_6_2, CI_LWSP_6_2, CIType,
+        CIW_ADDI4SPN, CIWType, CJ_TARGET, CJType, CL_DOUBLE_6_5, CL_DOUBLE_12_10, CL_WORD_6_5,
+        CL_WORD_12_10, CLBType, CLType, CMJTType, CMMVType, CMPPType, CRType, CSBType, CSRType,
+        CSS_SDSP, CSS_SWSP, CSSType, CSType, CUType, EncodedInstruction, FenceType, IType,
+        Instruction, JType, Mnemonic, Operands, R4Type, RType, SType, UType, VectorMemType,
+        VectorType, Xlen,
+    },
+    operand::{CsrAddr, FenceSet, Operand, RegList, RoundingMode, VType},
+    util::{
+        fp_default_rm, fp_fmt_suffix, fp_int_suffix, fp_move_suffix, is_register_pair_cas,
+        requires_rv32, requires_rv64, rounding_mode_name, saved_reg, sign_extend, stack_adj_base,
+        unpack_imm,
+    },
+    vector::{OP_V, OPCFG, slot_operand, vector_mem_op, vector_op},
 };
 
-pub fn decode(instr: u32) -> Result<EncodedInstruction> {
+pub fn decode(instr: u32, xlen: Xlen) -> Result<EncodedInstruction> {
+    // anything but 0b11 in the low two bits is a 16-bit compressed instruction
+    if instr & 0b11 != 0b11 {
+        let compressed = u16::try_from(instr).map_err(|_| Error::InvalidFormat)?;
+        return decode_compressed(compressed, xlen);
+    }
+
     let opcode = instr & 0x7f;
     let (mnemonic, operands, instr_enum) = match opcode {
+        0b0110011 if is_byte_select(instr) => decode_byte_select(ByteSelectType(instr))?,
         0b0110011 => {
             // R-type
             let r = RType(instr);
@@ -15,6 +39,10 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
             let funct3 = r.funct3();
             let funct7 = r.funct7();
             let mnemonic = match (funct3, funct7) {
+                // Zihintntl, the non-temporal locality hints are adds to x0 from x0
+                (0x0, 0x00) if rd == 0 && rs1 == 0 && (2..=5).contains(&rs2) => {
+                    ["ntl.p1", "ntl.pall", "ntl.s1", "ntl.all"][rs2 as usize - 2]
+                }
                 (0x0, 0x00) => "add",
                 (0x0, 0x20) => "sub",
                 (0x4, 0x00) => "xor",
@@ -25,42 +53,164 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
                 (0x5, 0x20) => "sra",
                 (0x2, 0x00) => "slt",
                 (0x3, 0x00) => "sltu",
+                (0x0, 0x01) => "mul",
+                (0x1, 0x01) => "mulh",
+                (0x2, 0x01) => "mulhsu",
+                (0x3, 0x01) => "mulhu",
+                (0x4, 0x01) => "div",
+                (0x5, 0x01) => "divu",
+                (0x6, 0x01) => "rem",
+                (0x7, 0x01) => "remu",
+                // Zba
+                (0x2, 0x10) => "sh1add",
+                (0x4, 0x10) => "sh2add",
+                (0x6, 0x10) => "sh3add",
+                // Zbb
+                (0x7, 0x20) => "andn",
+                (0x6, 0x20) => "orn",
+                (0x4, 0x20) => "xnor",
+                (0x6, 0x05) => "max",
+                (0x7, 0x05) => "maxu",
+                (0x4, 0x05) => "min",
+                (0x5, 0x05) => "minu",
+                (0x1, 0x30) => "rol",
+                (0x5, 0x30) => "ror",
+                (0x4, 0x04) if rs2 == 0 && xlen == Xlen::Rv32 => "zext.h",
+                // Zbkb and Zbkx
+                (0x4, 0x04) => "pack",
+                (0x7, 0x04) => "packh",
+                (0x4, 0x14) => "xperm8",
+                (0x2, 0x14) => "xperm4",
+                // Zknd, Zkne and Zknh
+                (0x0, 0x1d) => "aes64ds",
+                (0x0, 0x1f) => "aes64dsm",
+                (0x0, 0x19) => "aes64es",
+                (0x0, 0x1b) => "aes64esm",
+                (0x0, 0x3f) => "aes64ks2",
+                (0x0, 0x2e) => "sha512sig0h",
+                (0x0, 0x2a) => "sha512sig0l",
+                (0x0, 0x2f) => "sha512sig1h",
+                (0x0, 0x2b) => "sha512sig1l",
+                (0x0, 0x28) => "sha512sum0r",
+                (0x0, 0x29) => "sha512sum1r",
+                // Zbc
+                (0x1, 0x05) => "clmul",
+                (0x3, 0x05) => "clmulh",
+                (0x2, 0x05) => "clmulr",
+                // Zbs
+                (0x1, 0x24) => "bclr",
+                (0x5, 0x24) => "bext",
+                (0x1, 0x34) => "binv",
+                (0x1, 0x14) => "bset",
+                // Zicond
+                (0x5, 0x07) => "czero.eqz",
+                (0x7, 0x07) => "czero.nez",
                 _ => return Err(Error::UnknownInstruction),
             };
-            let operands = vec![format!("x{}", rd), format!("x{}", rs1), format!("x{}", rs2)];
-            (mnemonic.to_string(), operands, Instruction::RType(r))
+            let operands = match mnemonic {
+                m if m.starts_with("ntl.") => Operands::new(),
+                "zext.h" => Operands::from([Operand::reg(rd), Operand::reg(rs1)]),
+                _ => Operands::from([Operand::reg(rd), Operand::reg(rs1), Operand::reg(rs2)]),
+            };
+            (Mnemonic::from(mnemonic), operands, Instruction::RType(r))
         }
+        0b0010011 if is_prefetch(instr) => decode_prefetch(SType(instr)),
+        0b0010011 | 0b0011011 if is_bitmanip_imm(instr) => decode_bitmanip_imm(IType(instr), xlen)?,
         0b0010011 => {
             // I-type
             let i = IType(instr);
             let rd = i.rd();
             let rs1 = i.rs1();
             let funct3 = i.funct3();
-            let imm = ((instr as i32) >> 20) as i32;
+            let imm = (instr as i32) >> 20;
             let (mnemonic, arg3) = match funct3 {
-                0x0 => ("addi", format!("{}", imm)),
-                0x4 => ("xori", format!("{}", imm)),
-                0x6 => ("ori", format!("{}", imm)),
-                0x7 => ("andi", format!("{}", imm)),
-                0x1 => {
-                    let shamt = (instr >> 20) & 0x1f;
-                    ("slli", format!("{}", shamt))
-                }
-                0x5 => {
-                    let shamt = (instr >> 20) & 0x1f;
-                    let funct7 = (instr >> 25) & 0x7f;
-                    match funct7 {
-                        0x00 => ("srli", format!("{}", shamt)),
-                        0x20 => ("srai", format!("{}", shamt)),
+                0x0 => ("addi", Operand::imm(imm)),
+                0x4 => ("xori", Operand::imm(imm)),
+                0x6 => ("ori", Operand::imm(imm)),
+                0x7 => ("andi", Operand::imm(imm)),
+                0x1 | 0x5 => {
+                    // shamt[5] is only valid on RV64, the remaining funct6 selects the shift
+                    let shamt = (instr >> 20) & 0x3f;
+                    let funct6 = (instr >> 26) & 0x3f;
+                    if xlen == Xlen::Rv32 && shamt > 31 {
+                        return Err(Error::UnsupportedXlen);
+                    }
+                    match (funct3, funct6) {
+                        (0x1, 0x00) => ("slli", Operand::imm(shamt as i32)),
+                        (0x5, 0x00) => ("srli", Operand::imm(shamt as i32)),
+                        (0x5, 0x10) => ("srai", Operand::imm(shamt as i32)),
+                        (0x5, 0x18) => ("rori", Operand::imm(shamt as i32)),
+                        (0x1, 0x12) => ("bclri", Operand::imm(shamt as i32)),
+                        (0x5, 0x12) => ("bexti", Operand::imm(shamt as i32)),
+                        (0x1, 0x1a) => ("binvi", Operand::imm(shamt as i32)),
+                        (0x1, 0x0a) => ("bseti", Operand::imm(shamt as i32)),
                         _ => return Err(Error::UnknownInstruction),
                     }
                 }
-                0x2 => ("slti", format!("{}", imm)),
-                0x3 => ("sltiu", format!("{}", imm)),
+                0x2 => ("slti", Operand::imm(imm)),
+                0x3 => ("sltiu", Operand::imm(imm)),
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let operands = Operands::from([Operand::reg(rd), Operand::reg(rs1), arg3]);
+            (Mnemonic::from(mnemonic), operands, Instruction::IType(i))
+        }
+        0b0011011 => {
+            // I-type OP-IMM-32
+            let i = IType(instr);
+            let rd = i.rd();
+            let rs1 = i.rs1();
+            let funct3 = i.funct3();
+            let imm = (instr as i32) >> 20;
+            let shamt = (instr >> 20) & 0x1f;
+            let funct7 = (instr >> 25) & 0x7f;
+            let (mnemonic, arg3) = match (funct3, funct7) {
+                (0x0, _) => ("addiw", Operand::imm(imm)),
+                (0x1, 0x00) => ("slliw", Operand::imm(shamt as i32)),
+                (0x5, 0x00) => ("srliw", Operand::imm(shamt as i32)),
+                (0x5, 0x20) => ("sraiw", Operand::imm(shamt as i32)),
+                (0x5, 0x30) => ("roriw", Operand::imm(shamt as i32)),
+                // slli.uw has funct6 and a 6-bit shift amount
+                (0x1, 0x04 | 0x05) => ("slli.uw", Operand::imm(((instr >> 20) & 0x3f) as i32)),
                 _ => return Err(Error::UnknownInstruction),
             };
-            let operands = vec![format!("x{}", rd), format!("x{}", rs1), arg3];
-            (mnemonic.to_string(), operands, Instruction::IType(i))
+            let operands = Operands::from([Operand::reg(rd), Operand::reg(rs1), arg3]);
+            (Mnemonic::from(mnemonic), operands, Instruction::IType(i))
+        }
+        0b0111011 => {
+            // R-type OP-32
+            let r = RType(instr);
+            let rd = r.rd();
+            let rs1 = r.rs1();
+            let rs2 = r.rs2();
+            let funct3 = r.funct3();
+            let funct7 = r.funct7();
+            let mnemonic = match (funct3, funct7) {
+                (0x0, 0x00) => "addw",
+                (0x0, 0x20) => "subw",
+                (0x1, 0x00) => "sllw",
+                (0x5, 0x00) => "srlw",
+                (0x5, 0x20) => "sraw",
+                (0x0, 0x01) => "mulw",
+                (0x4, 0x01) => "divw",
+                (0x5, 0x01) => "divuw",
+                (0x6, 0x01) => "remw",
+                (0x7, 0x01) => "remuw",
+                (0x0, 0x04) => "add.uw",
+                (0x2, 0x10) => "sh1add.uw",
+                (0x4, 0x10) => "sh2add.uw",
+                (0x6, 0x10) => "sh3add.uw",
+                (0x1, 0x30) => "rolw",
+                (0x5, 0x30) => "rorw",
+                (0x4, 0x04) if rs2 == 0 && xlen == Xlen::Rv64 => "zext.h",
+                (0x4, 0x04) if rs2 == 0 => return Err(Error::UnsupportedXlen),
+                (0x4, 0x04) => "packw",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let operands = match mnemonic {
+                "zext.h" => Operands::from([Operand::reg(rd), Operand::reg(rs1)]),
+                _ => Operands::from([Operand::reg(rd), Operand::reg(rs1), Operand::reg(rs2)]),
+            };
+            (Mnemonic::from(mnemonic), operands, Instruction::RType(r))
         }
         0b0000011 => {
             // I-type LOAD
@@ -68,17 +218,19 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
             let rd = i.rd();
             let rs1 = i.rs1();
             let funct3 = i.funct3();
-            let imm = ((instr as i32) >> 20) as i32;
+            let imm = (instr as i32) >> 20;
             let mnemonic = match funct3 {
                 0x0 => "lb",
                 0x1 => "lh",
                 0x2 => "lw",
+                0x3 => "ld",
                 0x4 => "lbu",
                 0x5 => "lhu",
+                0x6 => "lwu",
                 _ => return Err(Error::UnknownInstruction),
             };
-            let operands = vec![format!("x{}", rd), format!("{}", imm), format!("x{}", rs1)];
-            (mnemonic.to_string(), operands, Instruction::IType(i))
+            let operands = Operands::from([Operand::reg(rd), Operand::imm(imm), Operand::reg(rs1)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::IType(i))
         }
         0b0100011 => {
             // S-type
@@ -93,10 +245,12 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
                 0x0 => "sb",
                 0x1 => "sh",
                 0x2 => "sw",
+                0x3 => "sd",
                 _ => return Err(Error::UnknownInstruction),
             };
-            let operands = vec![format!("x{}", rs2), format!("{}", imm), format!("x{}", rs1)];
-            (mnemonic.to_string(), operands, Instruction::SType(s))
+            let operands =
+                Operands::from([Operand::reg(rs2), Operand::imm(imm), Operand::reg(rs1)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::SType(s))
         }
         0b1100011 => {
             // BRANCH
@@ -119,8 +273,9 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
                 0x7 => "bgeu",
                 _ => return Err(Error::UnknownInstruction),
             };
-            let operands = vec![format!("x{}", rs1), format!("x{}", rs2), format!("{}", imm)];
-            (mnemonic.to_string(), operands, Instruction::BType(b))
+            let operands =
+                Operands::from([Operand::reg(rs1), Operand::reg(rs2), Operand::imm(imm)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::BType(b))
         }
         0b1101111 => {
             // JAL
@@ -133,8 +288,8 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
                 .wrapping_shl(11)
                 .wrapping_shr(11);
             let mnemonic = "jal";
-            let operands = vec![format!("x{}", rd), format!("{}", j_imm)];
-            (mnemonic.to_string(), operands, Instruction::JType(j))
+            let operands = Operands::from([Operand::reg(rd), Operand::imm(j_imm)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::JType(j))
         }
         0b1100111 => {
             // JALR
@@ -142,11 +297,12 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
             let rd = i.rd();
             let rs1 = i.rs1();
             let funct3 = i.funct3();
-            let imm = ((instr as i32) >> 20) as i32;
+            let imm = (instr as i32) >> 20;
             let mnemonic = "jalr";
             if funct3 == 0x0 {
-                let operands = vec![format!("x{}", rd), format!("{}", imm), format!("x{}", rs1)];
-                (mnemonic.to_string(), operands, Instruction::IType(i))
+                let operands =
+                    Operands::from([Operand::reg(rd), Operand::imm(imm), Operand::reg(rs1)]);
+                (Mnemonic::from(mnemonic), operands, Instruction::IType(i))
             } else {
                 return Err(Error::UnknownInstruction);
             }
@@ -155,20 +311,21 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
             // LUI
             let u = UType(instr);
             let rd = u.rd();
-            let imm = (instr & 0xfffff000) as i32;
+            let imm = u.imm() as i32;
             let mnemonic = "lui";
-            let operands = vec![format!("x{}", rd), format!("{}", imm)];
-            (mnemonic.to_string(), operands, Instruction::UType(u))
+            let operands = Operands::from([Operand::reg(rd), Operand::imm(imm)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::UType(u))
         }
         0b0010111 => {
             // AUIPC
             let u = UType(instr);
             let rd = u.rd();
-            let imm = (instr & 0xfffff000) as i32;
+            let imm = u.imm() as i32;
             let mnemonic = "auipc";
-            let operands = vec![format!("x{}", rd), format!("{}", imm)];
-            (mnemonic.to_string(), operands, Instruction::UType(u))
+            let operands = Operands::from([Operand::reg(rd), Operand::imm(imm)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::UType(u))
         }
+        0b1110011 if is_privileged(instr) => decode_privileged(RType(instr))?,
         0b1110011 => {
             // SYSTEM
             let c = CSRType(instr);
@@ -189,28 +346,853 @@ pub fn decode(instr: u32) -> Result<EncodedInstruction> {
                 _ => return Err(Error::UnknownInstruction),
             };
             let operands = match mnemonic {
-                "ecall" => vec![],
-                "ebreak" => vec![],
-                "csrrw" | "csrrs" | "csrrc" => {
-                    vec![format!("x{}", rd), format!("{}", csr), format!("x{}", rs1)]
-                }
-                "csrrwi" | "csrrsi" | "csrrci" => {
-                    vec![format!("x{}", rd), format!("{}", csr), format!("{}", zimm)]
+                "ecall" => Operands::new(),
+                "ebreak" => Operands::new(),
+                "csrrw" | "csrrs" | "csrrc" => Operands::from([
+                    Operand::reg(rd),
+                    Operand::Csr(CsrAddr(csr as u16)),
+                    Operand::reg(rs1),
+                ]),
+                "csrrwi" | "csrrsi" | "csrrci" => Operands::from([
+                    Operand::reg(rd),
+                    Operand::Csr(CsrAddr(csr as u16)),
+                    Operand::imm(zimm as i32),
+                ]),
+                _ => unreachable!(),
+            };
+            (Mnemonic::from(mnemonic), operands, Instruction::CSRType(c))
+        }
+        0b0001111 if (instr >> 12) & 0x7 == 0x2 => decode_cache_block(IType(instr))?,
+        0b0001111 => {
+            // MISC-MEM
+            let f = FenceType(instr);
+            // rd and rs1 are reserved and must be zero, fence.i reserves its whole imm too
+            if f.rd() != 0 || f.rs1() != 0 {
+                return Err(Error::UnknownInstruction);
+            }
+            let (mnemonic, operands) = match (f.funct3(), f.fm(), f.pred(), f.succ()) {
+                (0x0, 0b1000, 0b0011, 0b0011) => ("fence.tso", Operands::new()),
+                (0x0, 0b0000, 0b0001, 0b0000) => ("pause", Operands::new()),
+                (0x0, 0b0000, pred, succ) => (
+                    "fence",
+                    Operands::from([
+                        Operand::FenceSet(FenceSet(pred as u8)),
+                        Operand::FenceSet(FenceSet(succ as u8)),
+                    ]),
+                ),
+                (0x1, 0b0000, 0b0000, 0b0000) => ("fence.i", Operands::new()),
+                _ => return Err(Error::UnknownInstruction),
+            };
+            (Mnemonic::from(mnemonic), operands, Instruction::Fence(f))
+        }
+        0b0101111 => {
+            // AMO
+            let a = AMOType(instr);
+            let rd = a.rd();
+            let rs1 = a.rs1();
+            let rs2 = a.rs2();
+            // Zabha adds the byte and halfword widths, Zacas the quadword amocas
+            let width = match a.funct3() {
+                0x0 => "b",
+                0x1 => "h",
+                0x2 => "w",
+                0x3 => "d",
+                0x4 => "q",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let name = match a.funct5() {
+                0b00010 | 0b00011 if !matches!(width, "w" | "d") => {
+                    return Err(Error::UnknownInstruction);
                 }
+                0b00101 => "amocas",
+                _ if width == "q" => return Err(Error::UnknownInstruction),
+                0b00010 if rs2 == 0 => "lr",
+                0b00011 => "sc",
+                0b00001 => "amoswap",
+                0b00000 => "amoadd",
+                0b00100 => "amoxor",
+                0b01100 => "amoand",
+                0b01000 => "amoor",
+                0b10000 => "amomin",
+                0b10100 => "amomax",
+                0b11000 => "amominu",
+                0b11100 => "amomaxu",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let ordering = match (a.aq(), a.rl()) {
+                (true, true) => ".aqrl",
+                (true, false) => ".aq",
+                (false, true) => ".rl",
+                (false, false) => "",
+            };
+            if is_register_pair_cas(name, width, xlen) && (rd | rs2) & 1 != 0 {
+                return Err(Error::InvalidRegisterPair);
+            }
+            let mnemonic = mnemonic!("{}.{}{}", name, width, ordering);
+            let operands = if name == "lr" {
+                Operands::from([Operand::reg(rd), Operand::reg(rs1)])
+            } else {
+                Operands::from([Operand::reg(rd), Operand::reg(rs2), Operand::reg(rs1)])
+            };
+            (mnemonic, operands, Instruction::Amo(a))
+        }
+        0b0000111 | 0b0100111 if is_vector_memory(instr) => {
+            decode_vector_memory(VectorMemType(instr))?
+        }
+        0b0000111 => {
+            // I-type LOAD-FP
+            let i = IType(instr);
+            let rd = i.rd();
+            let rs1 = i.rs1();
+            let imm = (instr as i32) >> 20;
+            let mnemonic = match i.funct3() {
+                0x1 => "flh",
+                0x2 => "flw",
+                0x3 => "fld",
+                0x4 => "flq",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let operands =
+                Operands::from([Operand::freg(rd), Operand::imm(imm), Operand::reg(rs1)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::IType(i))
+        }
+        0b0100111 => {
+            // S-type STORE-FP
+            let s = SType(instr);
+            let rs2 = s.rs2();
+            let rs1 = s.rs1();
+            let imm = (((s.imm11_5() << 5) | s.imm4_0()) as i32)
+                .wrapping_shl(20)
+                .wrapping_shr(20);
+            let mnemonic = match s.funct3() {
+                0x1 => "fsh",
+                0x2 => "fsw",
+                0x3 => "fsd",
+                0x4 => "fsq",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let operands =
+                Operands::from([Operand::freg(rs2), Operand::imm(imm), Operand::reg(rs1)]);
+            (Mnemonic::from(mnemonic), operands, Instruction::SType(s))
+        }
+        0b1000011 | 0b1000111 | 0b1001011 | 0b1001111 => {
+            // R4-type fused multiply-add
+            let r = R4Type(instr);
+            let fmt = fp_fmt_suffix(r.fmt()).ok_or(Error::UnknownInstruction)?;
+            let name = match opcode {
+                0b1000011 => "fmadd",
+                0b1000111 => "fmsub",
+                0b1001011 => "fnmsub",
+                0b1001111 => "fnmadd",
                 _ => unreachable!(),
             };
-            (mnemonic.to_string(), operands, Instruction::CSRType(c))
+            let mut operands = Operands::from([
+                Operand::freg(r.rd()),
+                Operand::freg(r.rs1()),
+                Operand::freg(r.rs2()),
+                Operand::freg(r.rs3()),
+            ]);
+            // dyn is the assembler default and is left out
+            if r.rm() != 0b111 {
+                operands.push(rounding_mode(r.rm())?);
+            }
+            (
+                mnemonic!("{}.{}", name, fmt),
+                operands,
+                Instruction::R4Type(r),
+            )
+        }
+        0b1010011 => {
+            // R-type OP-FP
+            let r = RType(instr);
+            let rd = r.rd();
+            let rs1 = r.rs1();
+            let rs2 = r.rs2();
+            let funct3 = r.funct3();
+            let funct5 = r.funct7() >> 2;
+            let fmt = r.funct7() & 0x3;
+            let suffix = fp_fmt_suffix(fmt).ok_or(Error::UnknownInstruction)?;
+            // each operand kind is the register prefix of rd, rs1, rs2 in that order
+            let (mnemonic, kinds, has_rm) = match (funct5, funct3, rs2) {
+                (0b00000, _, _) => (mnemonic!("fadd.{}", suffix), "fff", true),
+                (0b00001, _, _) => (mnemonic!("fsub.{}", suffix), "fff", true),
+                (0b00010, _, _) => (mnemonic!("fmul.{}", suffix), "fff", true),
+                (0b00011, _, _) => (mnemonic!("fdiv.{}", suffix), "fff", true),
+                (0b01011, _, 0) => (mnemonic!("fsqrt.{}", suffix), "ff", true),
+                (0b00100, 0x0, _) => (mnemonic!("fsgnj.{}", suffix), "fff", false),
+                (0b00100, 0x1, _) => (mnemonic!("fsgnjn.{}", suffix), "fff", false),
+                (0b00100, 0x2, _) => (mnemonic!("fsgnjx.{}", suffix), "fff", false),
+                (0b00101, 0x0, _) => (mnemonic!("fmin.{}", suffix), "fff", false),
+                (0b00101, 0x1, _) => (mnemonic!("fmax.{}", suffix), "fff", false),
+                (0b10100, 0x2, _) => (mnemonic!("feq.{}", suffix), "xff", false),
+                (0b10100, 0x1, _) => (mnemonic!("flt.{}", suffix), "xff", false),
+                (0b10100, 0x0, _) => (mnemonic!("fle.{}", suffix), "xff", false),
+                (0b11000, _, _) => {
+                    let int = fp_int_suffix(rs2).ok_or(Error::UnknownInstruction)?;
+                    (mnemonic!("fcvt.{}.{}", int, suffix), "xf", true)
+                }
+                (0b11010, _, _) => {
+                    let int = fp_int_suffix(rs2).ok_or(Error::UnknownInstruction)?;
+                    (mnemonic!("fcvt.{}.{}", suffix, int), "fx", true)
+                }
+                (0b01000, _, _) if rs2 != fmt => {
+                    let src = fp_fmt_suffix(rs2).ok_or(Error::UnknownInstruction)?;
+                    (mnemonic!("fcvt.{}.{}", suffix, src), "ff", true)
+                }
+                (0b11100, 0x0, 0) => {
+                    let w = fp_move_suffix(fmt).ok_or(Error::UnknownInstruction)?;
+                    (mnemonic!("fmv.x.{}", w), "xf", false)
+                }
+                (0b11100, 0x1, 0) => (mnemonic!("fclass.{}", suffix), "xf", false),
+                (0b11110, 0x0, 0) => {
+                    let w = fp_move_suffix(fmt).ok_or(Error::UnknownInstruction)?;
+                    (mnemonic!("fmv.{}.x", w), "fx", false)
+                }
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let mut operands = kinds
+                .chars()
+                .zip([rd, rs1, rs2])
+                .map(|(kind, reg)| match kind {
+                    'x' => Operand::reg(reg),
+                    _ => Operand::freg(reg),
+                })
+                .collect::<Operands>();
+            // the default rounding mode is left out, like the assembler does
+            if has_rm && funct3 != fp_default_rm(&mnemonic) {
+                operands.push(rounding_mode(funct3)?);
+            }
+            (mnemonic, operands, Instruction::RType(r))
         }
-/*         0b0001111 => {
-            // FENCE 
-            
-        } */
+        0b1010111 | 0b1110111 => decode_vector(VectorType(instr))?,
         _ => return Err(Error::InvalidOpcode),
     };
 
+    if xlen == Xlen::Rv32 && requires_rv64(&mnemonic)
+        || xlen == Xlen::Rv64 && requires_rv32(&mnemonic)
+    {
+        return Err(Error::UnsupportedXlen);
+    }
+
     Ok(EncodedInstruction {
         instr: instr_enum,
         mnemonic,
         operands,
+        xlen,
+    })
+}
+
+// the bit-manipulation and crypto ops that put a function code where the immediate or
+// shift amount goes
+fn is_bitmanip_imm(instr: u32) -> bool {
+    let funct7 = instr >> 25;
+    match (instr & 0x7f, (instr >> 12) & 0x7) {
+        (0b0011011, 0x1) => funct7 == 0b0110000,
+        (0b0010011, 0x1) => matches!(funct7, 0b0110000 | 0b0001000 | 0b0011000 | 0b0000100),
+        (0b0010011, 0x5) => funct7 == 0b0010100 || funct7 == 0b0000100 || funct7 >> 1 == 0b011010,
+        _ => false,
+    }
+}
+
+// clz, ctz, cpop, the sign and byte-order ops of Zbb and Zbkb with their RV64 word forms,
+// and the SHA-2, SM3 and AES helpers of scalar crypto. All take rd and rs1 only, except
+// for the round number of aes64ks1i
+fn decode_bitmanip_imm(i: IType, xlen: Xlen) -> Result<(Mnemonic, Operands, Instruction)> {
+    let rd = Operand::reg(i.rd());
+    let rs1 = Operand::reg(i.rs1());
+    let mnemonic = match (i.opcode(), i.funct3(), i.imm()) {
+        (0b0010011, 0x1, 0x600) => "clz",
+        (0b0010011, 0x1, 0x601) => "ctz",
+        (0b0010011, 0x1, 0x602) => "cpop",
+        (0b0010011, 0x1, 0x604) => "sext.b",
+        (0b0010011, 0x1, 0x605) => "sext.h",
+        (0b0011011, 0x1, 0x600) => "clzw",
+        (0b0011011, 0x1, 0x601) => "ctzw",
+        (0b0011011, 0x1, 0x602) => "cpopw",
+        (0b0010011, 0x5, 0x287) => "orc.b",
+        // rev8 reverses XLEN / 8 bytes, the shift amount field holds XLEN - 8
+        (0b0010011, 0x5, 0x698) if xlen == Xlen::Rv32 => "rev8",
+        (0b0010011, 0x5, 0x6b8) if xlen == Xlen::Rv64 => "rev8",
+        (0b0010011, 0x5, 0x6b8) => return Err(Error::UnsupportedXlen),
+        (0b0010011, 0x5, 0x687) => "brev8",
+        (0b0010011, 0x1, 0x08f) => "zip",
+        (0b0010011, 0x5, 0x08f) => "unzip",
+        (0b0010011, 0x1, 0x100) => "sha256sum0",
+        (0b0010011, 0x1, 0x101) => "sha256sum1",
+        (0b0010011, 0x1, 0x102) => "sha256sig0",
+        (0b0010011, 0x1, 0x103) => "sha256sig1",
+        (0b0010011, 0x1, 0x104) => "sha512sum0",
+        (0b0010011, 0x1, 0x105) => "sha512sum1",
+        (0b0010011, 0x1, 0x106) => "sha512sig0",
+        (0b0010011, 0x1, 0x107) => "sha512sig1",
+        (0b0010011, 0x1, 0x108) => "sm3p0",
+        (0b0010011, 0x1, 0x109) => "sm3p1",
+        (0b0010011, 0x1, 0x300) => "aes64im",
+        // the low four bits are the round number, 0 to 10
+        (0b0010011, 0x1, imm @ 0x310..=0x31a) => {
+            let operands = Operands::from([rd, rs1, Operand::imm(imm as i32 & 0xf)]);
+            return Ok((Mnemonic::from("aes64ks1i"), operands, Instruction::IType(i)));
+        }
+        _ => return Err(Error::UnknownInstruction),
+    };
+    Ok((
+        Mnemonic::from(mnemonic),
+        Operands::from([rd, rs1]),
+        Instruction::IType(i),
+    ))
+}
+
+// funct3 0 with one of the AES or SM4 round codes in funct5, under the byte select
+fn is_byte_select(instr: u32) -> bool {
+    let funct5 = (instr >> 25) & 0x1f;
+    (instr >> 12) & 0x7 == 0x0
+        && matches!(
+            funct5,
+            0b10001 | 0b10011 | 0b10101 | 0b10111 | 0b11000 | 0b11010
+        )
+}
+
+fn decode_byte_select(b: ByteSelectType) -> Result<(Mnemonic, Operands, Instruction)> {
+    let mnemonic = match b.funct5() {
+        0b10001 => "aes32esi",
+        0b10011 => "aes32esmi",
+        0b10101 => "aes32dsi",
+        0b10111 => "aes32dsmi",
+        0b11000 => "sm4ed",
+        0b11010 => "sm4ks",
+        _ => return Err(Error::UnknownInstruction),
+    };
+    let operands = Operands::from([
+        Operand::reg(b.rd()),
+        Operand::reg(b.rs1()),
+        Operand::reg(b.rs2()),
+        Operand::imm(b.bs() as i32),
+    ]);
+    Ok((
+        Mnemonic::from(mnemonic),
+        operands,
+        Instruction::ByteSelect(b),
+    ))
+}
+
+// ori with rd = x0 is a hint, the low five immediate bits 0, 1 and 3 are the Zicbop
+// prefetches and the upper seven a 32-byte aligned offset
+fn is_prefetch(instr: u32) -> bool {
+    (instr >> 12) & 0x7 == 0x6
+        && (instr >> 7) & 0x1f == 0
+        && matches!((instr >> 20) & 0x1f, 0 | 1 | 3)
+}
+
+// laid out like a store, the offset split around the function code in rs2
+fn decode_prefetch(s: SType) -> (Mnemonic, Operands, Instruction) {
+    let mnemonic = match s.rs2() {
+        0b00000 => "prefetch.i",
+        0b00001 => "prefetch.r",
+        _ => "prefetch.w",
+    };
+    let offset = ((s.imm11_5() << 25) as i32) >> 20;
+    let operands = Operands::from([Operand::imm(offset), Operand::reg(s.rs1())]);
+    (Mnemonic::from(mnemonic), operands, Instruction::SType(s))
+}
+
+// the Zicbom and Zicboz cache-block operations, the function code takes the place of the
+// immediate and rd is reserved
+fn decode_cache_block(i: IType) -> Result<(Mnemonic, Operands, Instruction)> {
+    if i.rd() != 0 {
+        return Err(Error::UnknownInstruction);
+    }
+    let mnemonic = match i.imm() {
+        0x000 => "cbo.inval",
+        0x001 => "cbo.clean",
+        0x002 => "cbo.flush",
+        0x004 => "cbo.zero",
+        _ => return Err(Error::UnknownInstruction),
+    };
+    let operands = Operands::from([Operand::reg(i.rs1())]);
+    Ok((Mnemonic::from(mnemonic), operands, Instruction::IType(i)))
+}
+
+// funct3 0 beyond ecall and ebreak, and funct3 0b100 for the hypervisor loads and stores
+fn is_privileged(instr: u32) -> bool {
+    let funct3 = (instr >> 12) & 0x7;
+    funct3 == 0x4 || funct3 == 0x0 && instr >> 20 > 0x001
+}
+
+// the trap returns, wfi and the address-translation fences of the privileged spec, and the
+// hypervisor virtual-machine loads and stores. All are R-type, funct7 and rs2 pick the
+// instruction when they are not register operands
+fn decode_privileged(r: RType) -> Result<(Mnemonic, Operands, Instruction)> {
+    let rd = r.rd();
+    let rs1 = r.rs1();
+    let rs2 = r.rs2();
+    let (mnemonic, operands) = match (r.funct3(), r.funct7()) {
+        (0x0, _) if rd != 0 => return Err(Error::UnknownInstruction),
+        (0x0, 0b0001001 | 0b0001011 | 0b0010001 | 0b0110001 | 0b0010011 | 0b0110011) => {
+            let mnemonic = match r.funct7() {
+                0b0001001 => "sfence.vma",
+                0b0001011 => "sinval.vma",
+                0b0010001 => "hfence.vvma",
+                0b0110001 => "hfence.gvma",
+                0b0010011 => "hinval.vvma",
+                _ => "hinval.gvma",
+            };
+            let operands = Operands::from([Operand::reg(rs1), Operand::reg(rs2)]);
+            (Mnemonic::from(mnemonic), operands)
+        }
+        (0x0, funct7) if rs1 == 0 => {
+            let mnemonic = match (funct7, rs2) {
+                (0b0000000, 0b00010) => "uret",
+                (0b0001000, 0b00010) => "sret",
+                (0b0011000, 0b00010) => "mret",
+                (0b0111000, 0b00010) => "mnret",
+                (0b0111101, 0b10010) => "dret",
+                (0b0001000, 0b00101) => "wfi",
+                (0b0000000, 0b01101) => "wrs.nto",
+                (0b0000000, 0b11101) => "wrs.sto",
+                (0b0001100, 0b00000) => "sfence.w.inval",
+                (0b0001100, 0b00001) => "sfence.inval.ir",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            (Mnemonic::from(mnemonic), Operands::new())
+        }
+        // funct7 is 0b0110 then the width, the low bit set for stores
+        (0x4, funct7) if funct7 >> 3 == 0b0110 => {
+            let width = ["b", "h", "w", "d"][((funct7 >> 1) & 0b11) as usize];
+            if funct7 & 1 == 1 {
+                if rd != 0 {
+                    return Err(Error::UnknownInstruction);
+                }
+                let operands = Operands::from([Operand::reg(rs2), Operand::reg(rs1)]);
+                (mnemonic!("hsv.{}", width), operands)
+            } else {
+                // rs2 selects the signed, unsigned or execute-permission load
+                let mnemonic = match (rs2, width) {
+                    (0b00000, _) => mnemonic!("hlv.{}", width),
+                    (0b00001, "b" | "h" | "w") => mnemonic!("hlv.{}u", width),
+                    (0b00011, "h" | "w") => mnemonic!("hlvx.{}u", width),
+                    _ => return Err(Error::UnknownInstruction),
+                };
+                (
+                    mnemonic,
+                    Operands::from([Operand::reg(rd), Operand::reg(rs1)]),
+                )
+            }
+        }
+        _ => return Err(Error::UnknownInstruction),
+    };
+    Ok((mnemonic, operands, Instruction::RType(r)))
+}
+
+// OP-V: vsetvli, vsetivli and vsetvl under funct3 0b111, the other categories are looked up
+// by funct3 and funct6 together with the function code in vs1 of the unary ops. OP-VE holds
+// the vector crypto ops
+fn decode_vector(v: VectorType) -> Result<(Mnemonic, Operands, Instruction)> {
+    if v.opcode() == OP_V && v.funct3() == OPCFG {
+        let rd = Operand::reg(v.vd());
+        let rs1 = Operand::reg(v.vs1());
+        let (mnemonic, operands) = match v.0 >> 30 {
+            0b00 | 0b01 => {
+                let vtype = Operand::VType(VType(((v.0 >> 20) & 0x7ff) as u16));
+                ("vsetvli", Operands::from([rd, rs1, vtype]))
+            }
+            0b11 => {
+                let vtype = Operand::VType(VType(((v.0 >> 20) & 0x3ff) as u16));
+                let avl = Operand::imm(v.vs1() as i32);
+                ("vsetivli", Operands::from([rd, avl, vtype]))
+            }
+            _ if v.funct6() == 0b100000 && !v.vm() => {
+                ("vsetvl", Operands::from([rd, rs1, Operand::reg(v.vs2())]))
+            }
+            _ => return Err(Error::UnknownInstruction),
+        };
+        return Ok((Mnemonic::from(mnemonic), operands, Instruction::Vector(v)));
+    }
+    let (mnemonic, layout) = vector_op(v).ok_or(Error::UnknownInstruction)?;
+    let operands = layout
+        .iter()
+        .filter_map(|&slot| slot_operand(slot, v.0))
+        .collect();
+    Ok((mnemonic, operands, Instruction::Vector(v)))
+}
+
+// the element widths 8, 16, 32 and 64 of the vector loads and stores sit between the scalar
+// FP widths
+fn is_vector_memory(instr: u32) -> bool {
+    matches!((instr >> 12) & 0x7, 0b000 | 0b101 | 0b110 | 0b111)
+}
+
+fn decode_vector_memory(v: VectorMemType) -> Result<(Mnemonic, Operands, Instruction)> {
+    // mew is reserved for element widths of 128 bits and up
+    if v.mew() {
+        return Err(Error::UnknownInstruction);
+    }
+    let store = v.opcode() == 0b0100111;
+    let (mnemonic, layout) = vector_mem_op(store, v.nf(), v.mop(), v.rs2(), v.width(), v.vm())
+        .ok_or(Error::UnknownInstruction)?;
+    let operands = layout
+        .iter()
+        .filter_map(|&slot| slot_operand(slot, v.0))
+        .collect();
+    Ok((mnemonic, operands, Instruction::VectorMem(v)))
+}
+
+fn decode_compressed(instr: u16, xlen: Xlen) -> Result<EncodedInstruction> {
+    let rv64 = xlen == Xlen::Rv64;
+    // rd', rs1' and rs2' address x8-x15 / f8-f15
+    let (x, f) = (|n: u16| Operand::reg(n), |n: u16| Operand::freg(n));
+    let (xc, fc) = (|n: u16| Operand::reg(n + 8), |n: u16| Operand::freg(n + 8));
+    let funct3 = instr >> 13;
+    let (mnemonic, operands, instr_enum) = match (instr & 0b11, funct3) {
+        (0b00, 0b000) => {
+            // CIW, the all-zero word is the defined illegal instruction and a zero immediate
+            // with any other rd is reserved
+            let c = CIWType(instr);
+            let imm = unpack_imm(c.imm(), &CIW_ADDI4SPN);
+            if instr == 0 {
+                ("c.unimp", Operands::new(), Instruction::CIWType(c))
+            } else if imm == 0 {
+                return Err(Error::UnknownInstruction);
+            } else {
+                let operands = Operands::from([xc(c.rd()), x(2), Operand::imm(imm as i32)]);
+                ("c.addi4spn", operands, Instruction::CIWType(c))
+            }
+        }
+        (0b00, 0b001..=0b011) => {
+            // CL
+            let c = CLType(instr);
+            let (mnemonic, double) = match funct3 {
+                0b001 => ("c.fld", true),
+                0b010 => ("c.lw", false),
+                _ if rv64 => ("c.ld", true),
+                _ => ("c.flw", false),
+            };
+            let imm = if double {
+                unpack_imm(c.imm12_10(), &CL_DOUBLE_12_10) | unpack_imm(c.imm6_5(), &CL_DOUBLE_6_5)
+            } else {
+                unpack_imm(c.imm12_10(), &CL_WORD_12_10) | unpack_imm(c.imm6_5(), &CL_WORD_6_5)
+            };
+            let rd = if mnemonic.starts_with("c.f") {
+                fc(c.rd())
+            } else {
+                xc(c.rd())
+            };
+            let operands = Operands::from([rd, Operand::imm(imm as i32), xc(c.rs1())]);
+            (mnemonic, operands, Instruction::CLType(c))
+        }
+        (0b00, 0b100) => {
+            // Zcb byte and halfword loads and stores, c.lbu and c.sb take uimm[0] from bit 6
+            // and the halfword ops a function bit
+            let c = CLBType(instr);
+            let (byte, bit6) = (c.funct6() & 1 == 0, c.imm() >> 1);
+            let uimm = if byte {
+                (c.imm() & 1) << 1 | bit6
+            } else {
+                (c.imm() & 1) << 1
+            };
+            let mnemonic = match (c.funct6(), bit6) {
+                (0b100000, _) => "c.lbu",
+                (0b100001, 0) => "c.lhu",
+                (0b100001, _) => "c.lh",
+                (0b100010, _) => "c.sb",
+                (0b100011, 0) => "c.sh",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let operands = Operands::from([xc(c.rd()), Operand::imm(uimm as i32), xc(c.rs1())]);
+            let instr_enum = if mnemonic.starts_with("c.s") {
+                Instruction::CSBType(CSBType(instr))
+            } else {
+                Instruction::CLBType(c)
+            };
+            (mnemonic, operands, instr_enum)
+        }
+        (0b00, 0b101..=0b111) => {
+            // CS
+            let c = CSType(instr);
+            let (mnemonic, double) = match funct3 {
+                0b101 => ("c.fsd", true),
+                0b110 => ("c.sw", false),
+                _ if rv64 => ("c.sd", true),
+                _ => ("c.fsw", false),
+            };
+            let imm = if double {
+                unpack_imm(c.imm12_10(), &CL_DOUBLE_12_10) | unpack_imm(c.imm6_5(), &CL_DOUBLE_6_5)
+            } else {
+                unpack_imm(c.imm12_10(), &CL_WORD_12_10) | unpack_imm(c.imm6_5(), &CL_WORD_6_5)
+            };
+            let rs2 = if mnemonic.starts_with("c.f") {
+                fc(c.rs2())
+            } else {
+                xc(c.rs2())
+            };
+            let operands = Operands::from([rs2, Operand::imm(imm as i32), xc(c.rs1())]);
+            (mnemonic, operands, Instruction::CSType(c))
+        }
+        (0b01, 0b001) if !rv64 => {
+            // CJ
+            let c = CJType(instr);
+            let imm = sign_extend(unpack_imm(c.target(), &CJ_TARGET), 12);
+            (
+                "c.jal",
+                Operands::from([Operand::imm(imm)]),
+                Instruction::CJType(c),
+            )
+        }
+        (0b01, 0b000..=0b011) => {
+            // CI
+            let c = CIType(instr);
+            let rd = c.rd();
+            let imm = unpack_imm(c.imm12(), &CI_IMM_12) | unpack_imm(c.imm6_2(), &CI_IMM_6_2);
+            let imm = sign_extend(imm, 6);
+            let (mnemonic, operands) = match funct3 {
+                0b000 if rd == 0 && imm == 0 => ("c.nop", Operands::new()),
+                0b000 => ("c.addi", Operands::from([x(rd), Operand::imm(imm)])),
+                0b001 if rd == 0 => return Err(Error::UnknownInstruction),
+                0b001 => ("c.addiw", Operands::from([x(rd), Operand::imm(imm)])),
+                0b010 => ("c.li", Operands::from([x(rd), Operand::imm(imm)])),
+                0b011 if rd == 2 => {
+                    let imm = unpack_imm(c.imm12(), &CI_ADDI16SP_12)
+                        | unpack_imm(c.imm6_2(), &CI_ADDI16SP_6_2);
+                    if imm == 0 {
+                        return Err(Error::UnknownInstruction);
+                    }
+                    let imm = sign_extend(imm, 10);
+                    ("c.addi16sp", Operands::from([x(2), Operand::imm(imm)]))
+                }
+                // c.lui holds nzimm[17:12], shown like the lui operand as the 20-bit
+                // pattern, e.g. 1048575 for -1
+                _ if imm == 0 => return Err(Error::UnknownInstruction),
+                _ => (
+                    "c.lui",
+                    Operands::from([x(rd), Operand::imm(imm & 0xfffff)]),
+                ),
+            };
+            (mnemonic, operands, Instruction::CIType(c))
+        }
+        (0b01, 0b100) => {
+            let c = CBType(instr);
+            let funct2 = (instr >> 10) & 0x3;
+            if funct2 != 0b11 {
+                // CB with an immediate
+                let imm = unpack_imm(c.offset12_10() >> 2, &CI_IMM_12)
+                    | unpack_imm(c.offset6_2(), &CI_IMM_6_2);
+                let (mnemonic, imm) = match funct2 {
+                    0b00 => ("c.srli", imm as i32),
+                    0b01 => ("c.srai", imm as i32),
+                    _ => ("c.andi", sign_extend(imm, 6)),
+                };
+                if !rv64 && mnemonic != "c.andi" && imm > 31 {
+                    return Err(Error::UnsupportedXlen);
+                }
+                let operands = Operands::from([xc(c.rs1()), Operand::imm(imm)]);
+                (mnemonic, operands, Instruction::CBType(c))
+            } else if instr >> 10 == 0b100111 && (instr >> 5) & 0b11 == 0b11 {
+                // CU, the Zcb unary ops have a function code in place of rs2'
+                let c = CUType(instr);
+                let mnemonic = match c.funct5() {
+                    0b11000 => "c.zext.b",
+                    0b11001 => "c.sext.b",
+                    0b11010 => "c.zext.h",
+                    0b11011 => "c.sext.h",
+                    0b11100 => "c.zext.w",
+                    0b11101 => "c.not",
+                    _ => return Err(Error::UnknownInstruction),
+                };
+                (
+                    mnemonic,
+                    Operands::from([xc(c.rd())]),
+                    Instruction::CUType(c),
+                )
+            } else {
+                // CA
+                let c = CAType(instr);
+                let mnemonic = match (c.funct6() & 0b100, c.funct2()) {
+                    (0b000, 0b00) => "c.sub",
+                    (0b000, 0b01) => "c.xor",
+                    (0b000, 0b10) => "c.or",
+                    (0b000, 0b11) => "c.and",
+                    (0b100, 0b00) => "c.subw",
+                    (0b100, 0b01) => "c.addw",
+                    (0b100, 0b10) => "c.mul",
+                    _ => return Err(Error::UnknownInstruction),
+                };
+                let operands = Operands::from([xc(c.rd()), xc(c.rs2())]);
+                (mnemonic, operands, Instruction::CAType(c))
+            }
+        }
+        (0b01, 0b101) => {
+            // CJ
+            let c = CJType(instr);
+            let imm = sign_extend(unpack_imm(c.target(), &CJ_TARGET), 12);
+            (
+                "c.j",
+                Operands::from([Operand::imm(imm)]),
+                Instruction::CJType(c),
+            )
+        }
+        (0b01, 0b110 | 0b111) => {
+            // CB branch
+            let c = CBType(instr);
+            let imm = unpack_imm(c.offset12_10(), &CB_BRANCH_12_10)
+                | unpack_imm(c.offset6_2(), &CB_BRANCH_6_2);
+            let imm = sign_extend(imm, 9);
+            let mnemonic = if funct3 == 0b110 { "c.beqz" } else { "c.bnez" };
+            let operands = Operands::from([xc(c.rs1()), Operand::imm(imm)]);
+            (mnemonic, operands, Instruction::CBType(c))
+        }
+        (0b10, 0b000..=0b011) => {
+            // CI
+            let c = CIType(instr);
+            let rd = c.rd();
+            let (mnemonic, layout) = match funct3 {
+                0b000 => ("c.slli", CI_IMM_6_2),
+                0b001 => ("c.fldsp", CI_LDSP_6_2),
+                0b010 => ("c.lwsp", CI_LWSP_6_2),
+                _ if rv64 => ("c.ldsp", CI_LDSP_6_2),
+                _ => ("c.flwsp", CI_LWSP_6_2),
+            };
+            let imm = unpack_imm(c.imm12(), &CI_IMM_12) | unpack_imm(c.imm6_2(), &layout);
+            let operands = match mnemonic {
+                "c.slli" if !rv64 && imm > 31 => return Err(Error::UnsupportedXlen),
+                "c.slli" => Operands::from([x(rd), Operand::imm(imm as i32)]),
+                "c.lwsp" | "c.ldsp" if rd == 0 => return Err(Error::UnknownInstruction),
+                "c.lwsp" | "c.ldsp" => Operands::from([x(rd), Operand::imm(imm as i32), x(2)]),
+                _ => Operands::from([f(rd), Operand::imm(imm as i32), x(2)]),
+            };
+            (mnemonic, operands, Instruction::CIType(c))
+        }
+        (0b10, 0b100) => {
+            // CR
+            let c = CRType(instr);
+            let (rd, rs2) = (c.rd(), c.rs2());
+            let (mnemonic, operands) = match (c.funct4() & 1, rd, rs2) {
+                (0, 0, 0) => return Err(Error::UnknownInstruction),
+                (0, _, 0) => ("c.jr", Operands::from([x(rd)])),
+                (0, _, _) => ("c.mv", Operands::from([x(rd), x(rs2)])),
+                (_, 0, 0) => ("c.ebreak", Operands::new()),
+                // the compressed Zihintntl hints, c.add to x0
+                (_, 0, 2..=5) => {
+                    let hints = ["c.ntl.p1", "c.ntl.pall", "c.ntl.s1", "c.ntl.all"];
+                    (hints[rs2 as usize - 2], Operands::new())
+                }
+                (_, _, 0) => ("c.jalr", Operands::from([x(rd)])),
+                (_, _, _) => ("c.add", Operands::from([x(rd), x(rs2)])),
+            };
+            (mnemonic, operands, Instruction::CRType(c))
+        }
+        (0b10, 0b101..=0b111) => {
+            // CSS
+            let c = CSSType(instr);
+            let (mnemonic, layout) = match funct3 {
+                0b101 => ("c.fsdsp", CSS_SDSP.as_slice()),
+                0b110 => ("c.swsp", CSS_SWSP.as_slice()),
+                _ if rv64 => ("c.sdsp", CSS_SDSP.as_slice()),
+                _ => ("c.fswsp", CSS_SWSP.as_slice()),
+            };
+            let imm = unpack_imm(c.imm(), layout);
+            let rs2 = if mnemonic.starts_with("c.f") {
+                f(c.rs2())
+            } else {
+                x(c.rs2())
+            };
+            let operands = Operands::from([rs2, Operand::imm(imm as i32), x(2)]);
+            (mnemonic, operands, Instruction::CSSType(c))
+        }
+        _ => return Err(Error::UnknownInstruction),
+    };
+
+    // c.subw, c.addw and c.zext.w are reserved on RV32
+    if !rv64 && requires_rv64(mnemonic) {
+        return Err(Error::UnsupportedXlen);
+    }
+
+    Ok(EncodedInstruction {
+        instr: instr_enum,
+        mnemonic: Mnemonic::from(mnemonic),
+        operands,
+        xlen,
+    })
+}
+
+// decode and decode_zcmp with the instructions of a custom table in the custom-0 to
+// custom-3 opcodes, which hold nothing otherwise
+#[cfg(feature = "std")]
+pub fn decode_with(table: &CustomTable, instr: u32, xlen: Xlen) -> Result<EncodedInstruction> {
+    table
+        .decode(instr, xlen)
+        .map_or_else(|| decode(instr, xlen), Ok)
+}
+
+#[cfg(feature = "std")]
+pub fn decode_zcmp_with(table: &CustomTable, instr: u32, xlen: Xlen) -> Result<EncodedInstruction> {
+    table
+        .decode(instr, xlen)
+        .map_or_else(|| decode_zcmp(instr, xlen), Ok)
+}
+
+// Zcmp and Zcmt take over the c.fsdsp encodings on targets without Zcd, every other
+// word decodes as usual
+pub fn decode_zcmp(instr: u32, xlen: Xlen) -> Result<EncodedInstruction> {
+    if instr & 0b11 != 0b10 || instr >> 13 != 0b101 {
+        return decode(instr, xlen);
+    }
+    let instr = instr as u16;
+    let (mnemonic, operands, instr_enum) = match instr >> 10 {
+        0b101110 | 0b101111 => {
+            // push and pop, the stack adjustment is negative for cm.push
+            let c = CMPPType(instr);
+            let mnemonic = match (c.funct6(), c.funct2()) {
+                (0b101110, 0b00) => "cm.push",
+                (0b101110, 0b10) => "cm.pop",
+                (0b101111, 0b00) => "cm.popretz",
+                (0b101111, 0b10) => "cm.popret",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            // rlist 0-3 are reserved
+            if c.rlist() < 4 {
+                return Err(Error::UnknownInstruction);
+            }
+            let rlist = c.rlist() as u32;
+            let adj = (stack_adj_base(rlist, xlen) + c.spimm() as u32 * 16) as i32;
+            let adj = if mnemonic == "cm.push" { -adj } else { adj };
+            let operands =
+                Operands::from([Operand::RegList(RegList(rlist as u8)), Operand::imm(adj)]);
+            (mnemonic, operands, Instruction::CMPPType(c))
+        }
+        0b101011 => {
+            let c = CMMVType(instr);
+            let mnemonic = match c.funct2() {
+                // cm.mvsa01 cannot write both a0 and a1 to the same register
+                0b01 if c.r1s() == c.r2s() => return Err(Error::UnknownInstruction),
+                0b01 => "cm.mvsa01",
+                0b11 => "cm.mva01s",
+                _ => return Err(Error::UnknownInstruction),
+            };
+            let r1s = Operand::reg(saved_reg(c.r1s() as u32));
+            let r2s = Operand::reg(saved_reg(c.r2s() as u32));
+            (
+                mnemonic,
+                Operands::from([r1s, r2s]),
+                Instruction::CMMVType(c),
+            )
+        }
+        0b101000 => {
+            let c = CMJTType(instr);
+            let mnemonic = if c.index() < 32 { "cm.jt" } else { "cm.jalt" };
+            let operands = Operands::from([Operand::imm(c.index() as i32)]);
+            (mnemonic, operands, Instruction::CMJTType(c))
+        }
+        _ => return Err(Error::UnknownInstruction),
+    };
+    Ok(EncodedInstruction {
+        instr: instr_enum,
+        mnemonic: Mnemonic::from(mnemonic),
+        operands,
+        xlen,
     })
 }
+
+// reserved rounding modes do not decode
+fn rounding_mode(rm: u32) -> Result<Operand> {
+    rounding_mode_name(rm)?;
+    Ok(Operand::RoundingMode(RoundingMode(rm as u8)))
+}
@@ -1,12 +1,29 @@
+use std::str::FromStr;
+
+use crate::custom::CustomTable;
+use crate::decoder::{decode, decode_with, decode_zcmp};
 use crate::error::{Error, Result};
 use crate::format::{
-    BType, CSRType, EncodedInstruction, IType, Instruction, JType, RType, SType, UType,
+    AMOType, BType, ByteSelectType, CAType, CB_BRANCH_6_2, CB_BRANCH_12_10, CBType,
+    CI_ADDI16SP_6_2, CI_ADDI16SP_12, CI_IMM_6_2, CI_IMM_12, CI_LDSP_6_2, CI_LWSP_6_2, CIType,
+    CIW_ADDI4SPN, CIWType, CJ_TARGET, CJType, CL_DOUBLE_6_5, CL_DOUBLE_12_10, CL_WORD_6_5,
+    CL_WORD_12_10, CLBType, CLType, CMJTType, CMMVType, CMPPType, CRType, CSBType, CSRType,
+    CSS_SDSP, CSS_SWSP, CSSType, CSType, CUType, EncodedInstruction, FenceType, IType, Instruction,
+    JType, R4Type, RType, SType, UType, VectorMemType, VectorType, Xlen,
+};
+use crate::pseudo;
+use crate::util::{
+    abis_to_operands, fp_default_rm, fp_fmt_code, fp_int_code, fp_move_code, is_fp_load_store,
+    is_register_pair_cas, pack_imm, parse_csr, parse_fence_set, parse_freg, parse_immediate,
+    parse_reg, parse_reg_list, parse_rounding_mode, parse_vreg, requires_rv32, requires_rv64,
+    saved_reg_index, split_amo_ordering, stack_adj_base,
 };
-use crate::util::{abis_to_operands, parse_immediate, parse_reg};
+use crate::vector::{OP_V, OPCFG, Slot, parse_vtype, vector_mem_code, vector_op_code};
 
-pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
+// splits a line into its lowercase mnemonic and operands, ABI names turned numeric
+fn tokenize(instr_string: &str) -> Result<(String, Vec<String>)> {
     let tokens: Vec<&str> = instr_string
-        .split(|c| c == ' ' || c == ',' || c == '(' || c == ')')
+        .split([' ', ',', '(', ')'])
         .filter(|s| !s.is_empty())
         .collect();
 
@@ -15,15 +32,79 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
     }
 
     let mnemonic = tokens[0].to_lowercase();
+    // a `{ra, s0-s3}` register list is split at its commas, join it back into one operand
+    let mut raw_operands: Vec<String> = Vec::new();
+    for token in &tokens[1..] {
+        match raw_operands.last_mut() {
+            Some(list) if list.starts_with('{') && !list.ends_with('}') => {
+                list.push(',');
+                list.push_str(token);
+            }
+            _ => raw_operands.push(token.to_string()),
+        }
+    }
+    Ok((mnemonic, abis_to_operands(&raw_operands)))
+}
+
+// assembles a line into every word it stands for, pseudo-instructions like `li` or
+// `call` expand to several
+pub fn assemble(instr_string: &str, xlen: Xlen) -> Result<Vec<EncodedInstruction>> {
+    assemble_with(&CustomTable::default(), instr_string, xlen)
+}
+
+// assemble and encode with the instructions of a custom table, which may take any name
+// the built-in ones leave free
+pub fn assemble_with(
+    table: &CustomTable,
+    instr_string: &str,
+    xlen: Xlen,
+) -> Result<Vec<EncodedInstruction>> {
+    let (mnemonic, operands) = tokenize(instr_string)?;
+    match pseudo::expand(&mnemonic, &operands, xlen)? {
+        Some(words) => words.iter().map(|word| encode(word, xlen)).collect(),
+        None => Ok(vec![encode_with(table, instr_string, xlen)?]),
+    }
+}
+
+pub fn encode_with(
+    table: &CustomTable,
+    instr_string: &str,
+    xlen: Xlen,
+) -> Result<EncodedInstruction> {
+    let (mnemonic, operands) = tokenize(instr_string)?;
+    match table.encode(&mnemonic, &operands)? {
+        Some(instr) => decode_with(table, instr, xlen),
+        None => encode(instr_string, xlen),
+    }
+}
+
+pub fn encode(instr_string: &str, xlen: Xlen) -> Result<EncodedInstruction> {
+    let (mnemonic, operands) = tokenize(instr_string)?;
 
-    let raw_operands = tokens[1..]
-        .iter()
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>();
-    let operands = abis_to_operands(&raw_operands);
+    // pseudo-instructions that stand for a single word encode like it
+    if let Some(words) = pseudo::expand(&mnemonic, &operands, xlen)? {
+        return match words.as_slice() {
+            [word] => encode(word, xlen),
+            _ => Err(Error::MultipleInstructions),
+        };
+    }
+
+    if xlen == Xlen::Rv32 && requires_rv64(&mnemonic)
+        || xlen == Xlen::Rv64 && requires_rv32(&mnemonic)
+    {
+        return Err(Error::UnsupportedXlen);
+    }
 
     let instr = match mnemonic.as_str() {
-        "add" | "sub" | "sll" | "slt" | "sltu" | "xor" | "srl" | "sra" | "or" | "and" => {
+        "add" | "sub" | "sll" | "slt" | "sltu" | "xor" | "srl" | "sra" | "or" | "and" | "mul"
+        | "mulh" | "mulhsu" | "mulhu" | "div" | "divu" | "rem" | "remu" | "addw" | "subw"
+        | "sllw" | "srlw" | "sraw" | "mulw" | "divw" | "divuw" | "remw" | "remuw" | "sh1add"
+        | "sh2add" | "sh3add" | "add.uw" | "sh1add.uw" | "sh2add.uw" | "sh3add.uw" | "andn"
+        | "orn" | "xnor" | "max" | "maxu" | "min" | "minu" | "rol" | "ror" | "rolw" | "rorw"
+        | "clmul" | "clmulh" | "clmulr" | "bclr" | "bext" | "binv" | "bset" | "pack" | "packh"
+        | "packw" | "xperm4" | "xperm8" | "aes64ds" | "aes64dsm" | "aes64es" | "aes64esm"
+        | "aes64ks2" | "sha512sig0h" | "sha512sig0l" | "sha512sig1h" | "sha512sig1l"
+        | "sha512sum0r" | "sha512sum1r" | "czero.eqz" | "czero.nez" => {
             if operands.len() != 3 {
                 return Err(Error::InvalidFormat);
             }
@@ -41,9 +122,68 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
                 "sra" => (0x5, 0x20),
                 "slt" => (0x2, 0x00),
                 "sltu" => (0x3, 0x00),
+                "mul" => (0x0, 0x01),
+                "mulh" => (0x1, 0x01),
+                "mulhsu" => (0x2, 0x01),
+                "mulhu" => (0x3, 0x01),
+                "div" => (0x4, 0x01),
+                "divu" => (0x5, 0x01),
+                "rem" => (0x6, 0x01),
+                "remu" => (0x7, 0x01),
+                "addw" => (0x0, 0x00),
+                "subw" => (0x0, 0x20),
+                "sllw" => (0x1, 0x00),
+                "srlw" => (0x5, 0x00),
+                "sraw" => (0x5, 0x20),
+                "mulw" => (0x0, 0x01),
+                "divw" => (0x4, 0x01),
+                "divuw" => (0x5, 0x01),
+                "remw" => (0x6, 0x01),
+                "remuw" => (0x7, 0x01),
+                "sh1add" | "sh1add.uw" => (0x2, 0x10),
+                "sh2add" | "sh2add.uw" => (0x4, 0x10),
+                "sh3add" | "sh3add.uw" => (0x6, 0x10),
+                "add.uw" => (0x0, 0x04),
+                "andn" => (0x7, 0x20),
+                "orn" => (0x6, 0x20),
+                "xnor" => (0x4, 0x20),
+                "max" => (0x6, 0x05),
+                "maxu" => (0x7, 0x05),
+                "min" => (0x4, 0x05),
+                "minu" => (0x5, 0x05),
+                "rol" | "rolw" => (0x1, 0x30),
+                "ror" | "rorw" => (0x5, 0x30),
+                "clmul" => (0x1, 0x05),
+                "clmulh" => (0x3, 0x05),
+                "clmulr" => (0x2, 0x05),
+                "bclr" => (0x1, 0x24),
+                "bext" => (0x5, 0x24),
+                "binv" => (0x1, 0x34),
+                "bset" => (0x1, 0x14),
+                "pack" | "packw" => (0x4, 0x04),
+                "packh" => (0x7, 0x04),
+                "xperm8" => (0x4, 0x14),
+                "xperm4" => (0x2, 0x14),
+                "aes64ds" => (0x0, 0x1d),
+                "aes64dsm" => (0x0, 0x1f),
+                "aes64es" => (0x0, 0x19),
+                "aes64esm" => (0x0, 0x1b),
+                "aes64ks2" => (0x0, 0x3f),
+                "sha512sig0h" => (0x0, 0x2e),
+                "sha512sig0l" => (0x0, 0x2a),
+                "sha512sig1h" => (0x0, 0x2f),
+                "sha512sig1l" => (0x0, 0x2b),
+                "sha512sum0r" => (0x0, 0x28),
+                "sha512sum1r" => (0x0, 0x29),
+                "czero.eqz" => (0x5, 0x07),
+                "czero.nez" => (0x7, 0x07),
                 _ => unreachable!(),
             };
-            let opcode = 0b0110011;
+            let opcode = if mnemonic.ends_with('w') {
+                0b0111011
+            } else {
+                0b0110011
+            };
 
             let mut r = RType(0);
             r.set_funct7(funct7);
@@ -57,14 +197,19 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
         }
 
         "addi" | "xori" | "ori" | "andi" | "slli" | "srli" | "srai" | "slti" | "sltiu" | "lb"
-        | "lh" | "lw" | "lbu" | "lhu" | "jalr" => {
+        | "lh" | "lw" | "ld" | "lbu" | "lhu" | "lwu" | "jalr" | "addiw" | "slliw" | "srliw"
+        | "sraiw" | "flh" | "flw" | "fld" | "flq" => {
             if operands.len() != 3 {
                 return Err(Error::InvalidFormat);
             }
-            let rd = parse_reg(&operands[0])?;
+            let rd = if is_fp_load_store(&mnemonic) {
+                parse_freg(&operands[0])?
+            } else {
+                parse_reg(&operands[0])?
+            };
             let rs1: u32;
             let mut imm: i32;
-            if ["lb", "lh", "lw", "lbu", "lhu", "jalr"].contains(&mnemonic.as_str()) {
+            if mnemonic.starts_with('l') || mnemonic.starts_with("fl") || mnemonic == "jalr" {
                 imm = parse_immediate(&operands[1])?;
                 rs1 = parse_reg(&operands[2])?;
             } else {
@@ -72,15 +217,9 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
                 rs1 = parse_reg(&operands[1])?;
             }
 
-            // uimm variant dont check range
-            if ["sltiu", "lbu", "lhu"].contains(&mnemonic.as_str()) {
-                if imm < 0 || imm > 4095 {
-                    return Err(Error::ImmediateOutOfRange);
-                }
-            } else {
-                if imm < -2048 || imm > 2047 {
-                    return Err(Error::ImmediateOutOfRange);
-                }
+            // sltiu and the unsigned loads sign-extend their immediate as well
+            if !(-2048..=2047).contains(&imm) {
+                return Err(Error::ImmediateOutOfRange);
             }
             let funct3 = match mnemonic.as_str() {
                 "addi" => 0x0,
@@ -95,20 +234,41 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
                 "lb" => 0x0,
                 "lh" => 0x1,
                 "lw" => 0x2,
+                "ld" => 0x3,
                 "lbu" => 0x4,
                 "lhu" => 0x5,
+                "lwu" => 0x6,
                 "jalr" => 0x0,
+                "addiw" => 0x0,
+                "slliw" => 0x1,
+                "srliw" => 0x5,
+                "sraiw" => 0x5,
+                "flh" => 0x1,
+                "flw" => 0x2,
+                "fld" => 0x3,
+                "flq" => 0x4,
                 _ => unreachable!(),
             };
-            if mnemonic == "slli" || mnemonic == "srli" || mnemonic == "srai" {
-                imm = imm & 0x1f; // shamt
+            if ["slli", "srli", "srai", "slliw", "srliw", "sraiw"].contains(&mnemonic.as_str()) {
+                // shamt is 6 bits wide on RV64, except for the word shifts
+                let max_shamt = if xlen == Xlen::Rv64 && !mnemonic.ends_with('w') {
+                    63
+                } else {
+                    31
+                };
+                if !(0..=max_shamt).contains(&imm) {
+                    return Err(Error::ImmediateOutOfRange);
+                }
+                if mnemonic.starts_with("srai") {
+                    imm |= 0x400;
+                }
             }
-            let opcode = if ["lb", "lh", "lw", "lbu", "lhu"].contains(&mnemonic.as_str()) {
-                0b0000011
-            } else if mnemonic == "jalr" {
-                0b1100111
-            } else {
-                0b0010011
+            let opcode = match mnemonic.as_str() {
+                "lb" | "lh" | "lw" | "ld" | "lbu" | "lhu" | "lwu" => 0b0000011,
+                "flh" | "flw" | "fld" | "flq" => 0b0000111,
+                "jalr" => 0b1100111,
+                "addiw" | "slliw" | "srliw" | "sraiw" => 0b0011011,
+                _ => 0b0010011,
             };
             let mut i = IType(0);
             i.set_imm(imm as u32);
@@ -120,15 +280,228 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
             Instruction::IType(i)
         }
 
-        "sb" | "sh" | "sw" => {
+        "ntl.p1" | "ntl.pall" | "ntl.s1" | "ntl.all" => {
+            if !operands.is_empty() {
+                return Err(Error::InvalidFormat);
+            }
+            // add x0, x0, rs2 with the locality level in rs2
+            let rs2 = match mnemonic.as_str() {
+                "ntl.p1" => 2,
+                "ntl.pall" => 3,
+                "ntl.s1" => 4,
+                "ntl.all" => 5,
+                _ => unreachable!(),
+            };
+            let mut r = RType(0);
+            r.set_rs2(rs2);
+            r.set_opcode(0b0110011);
+
+            Instruction::RType(r)
+        }
+        "prefetch.i" | "prefetch.r" | "prefetch.w" => {
+            // `prefetch.r (rs1)` leaves out a zero offset
+            let (offset, rs1) = match operands.as_slice() {
+                [base] => (0, parse_reg(base)?),
+                [offset, base] => (parse_immediate(offset)?, parse_reg(base)?),
+                _ => return Err(Error::InvalidFormat),
+            };
+            let offset = check_imm(offset, -2048, 2016, 32)?;
+            let rs2 = match mnemonic.as_str() {
+                "prefetch.i" => 0b00000,
+                "prefetch.r" => 0b00001,
+                "prefetch.w" => 0b00011,
+                _ => unreachable!(),
+            };
+            let mut s = SType(0);
+            s.set_imm11_5(offset >> 5);
+            s.set_rs2(rs2);
+            s.set_rs1(rs1);
+            s.set_funct3(0x6);
+            s.set_opcode(0b0010011);
+
+            Instruction::SType(s)
+        }
+        "cbo.inval" | "cbo.clean" | "cbo.flush" | "cbo.zero" => {
+            // the offset is always zero and may be left out
+            let rs1 = match operands.as_slice() {
+                [base] => parse_reg(base)?,
+                [offset, base] if parse_immediate(offset)? == 0 => parse_reg(base)?,
+                [_, _] => return Err(Error::ImmediateOutOfRange),
+                _ => return Err(Error::InvalidFormat),
+            };
+            let imm = match mnemonic.as_str() {
+                "cbo.inval" => 0x000,
+                "cbo.clean" => 0x001,
+                "cbo.flush" => 0x002,
+                "cbo.zero" => 0x004,
+                _ => unreachable!(),
+            };
+            let mut i = IType(0);
+            i.set_imm(imm);
+            i.set_rs1(rs1);
+            i.set_funct3(0x2);
+            i.set_opcode(0b0001111);
+
+            Instruction::IType(i)
+        }
+        "rori" | "roriw" | "bclri" | "bexti" | "binvi" | "bseti" | "slli.uw" => {
+            if operands.len() != 3 {
+                return Err(Error::InvalidFormat);
+            }
+            let rd = parse_reg(&operands[0])?;
+            let rs1 = parse_reg(&operands[1])?;
+            let shamt = parse_immediate(&operands[2])?;
+            let max_shamt = if xlen == Xlen::Rv64 && mnemonic != "roriw" {
+                63
+            } else {
+                31
+            };
+            if !(0..=max_shamt).contains(&shamt) {
+                return Err(Error::ImmediateOutOfRange);
+            }
+            // funct6 sits above the 6-bit shift amount
+            let (funct6, funct3, opcode) = match mnemonic.as_str() {
+                "rori" => (0b011000, 0x5, 0b0010011),
+                "roriw" => (0b011000, 0x5, 0b0011011),
+                "bclri" => (0b010010, 0x1, 0b0010011),
+                "bexti" => (0b010010, 0x5, 0b0010011),
+                "binvi" => (0b011010, 0x1, 0b0010011),
+                "bseti" => (0b001010, 0x1, 0b0010011),
+                "slli.uw" => (0b000010, 0x1, 0b0011011),
+                _ => unreachable!(),
+            };
+            let mut i = IType(0);
+            i.set_imm(funct6 << 6 | shamt as u32);
+            i.set_rs1(rs1);
+            i.set_funct3(funct3);
+            i.set_rd(rd);
+            i.set_opcode(opcode);
+
+            Instruction::IType(i)
+        }
+        "clz" | "ctz" | "cpop" | "sext.b" | "sext.h" | "clzw" | "ctzw" | "cpopw" | "orc.b"
+        | "rev8" | "brev8" | "zip" | "unzip" | "sha256sum0" | "sha256sum1" | "sha256sig0"
+        | "sha256sig1" | "sha512sum0" | "sha512sum1" | "sha512sig0" | "sha512sig1" | "sm3p0"
+        | "sm3p1" | "aes64im" => {
+            if operands.len() != 2 {
+                return Err(Error::InvalidFormat);
+            }
+            let rd = parse_reg(&operands[0])?;
+            let rs1 = parse_reg(&operands[1])?;
+            // the function code takes the place of the immediate
+            let (imm, funct3, opcode) = match mnemonic.as_str() {
+                "clz" => (0x600, 0x1, 0b0010011),
+                "ctz" => (0x601, 0x1, 0b0010011),
+                "cpop" => (0x602, 0x1, 0b0010011),
+                "sext.b" => (0x604, 0x1, 0b0010011),
+                "sext.h" => (0x605, 0x1, 0b0010011),
+                "clzw" => (0x600, 0x1, 0b0011011),
+                "ctzw" => (0x601, 0x1, 0b0011011),
+                "cpopw" => (0x602, 0x1, 0b0011011),
+                "orc.b" => (0x287, 0x5, 0b0010011),
+                "rev8" if xlen == Xlen::Rv64 => (0x6b8, 0x5, 0b0010011),
+                "rev8" => (0x698, 0x5, 0b0010011),
+                "brev8" => (0x687, 0x5, 0b0010011),
+                "zip" => (0x08f, 0x1, 0b0010011),
+                "unzip" => (0x08f, 0x5, 0b0010011),
+                "sha256sum0" => (0x100, 0x1, 0b0010011),
+                "sha256sum1" => (0x101, 0x1, 0b0010011),
+                "sha256sig0" => (0x102, 0x1, 0b0010011),
+                "sha256sig1" => (0x103, 0x1, 0b0010011),
+                "sha512sum0" => (0x104, 0x1, 0b0010011),
+                "sha512sum1" => (0x105, 0x1, 0b0010011),
+                "sha512sig0" => (0x106, 0x1, 0b0010011),
+                "sha512sig1" => (0x107, 0x1, 0b0010011),
+                "sm3p0" => (0x108, 0x1, 0b0010011),
+                "sm3p1" => (0x109, 0x1, 0b0010011),
+                "aes64im" => (0x300, 0x1, 0b0010011),
+                _ => unreachable!(),
+            };
+            let mut i = IType(0);
+            i.set_imm(imm);
+            i.set_rs1(rs1);
+            i.set_funct3(funct3);
+            i.set_rd(rd);
+            i.set_opcode(opcode);
+
+            Instruction::IType(i)
+        }
+        "aes64ks1i" => {
             if operands.len() != 3 {
                 return Err(Error::InvalidFormat);
             }
-            let rs2 = parse_reg(&operands[0])?;
+            let rnum = parse_immediate(&operands[2])?;
+            if !(0..=10).contains(&rnum) {
+                return Err(Error::ImmediateOutOfRange);
+            }
+            let mut i = IType(0);
+            i.set_imm(0x310 | rnum as u32);
+            i.set_rs1(parse_reg(&operands[1])?);
+            i.set_funct3(0x1);
+            i.set_rd(parse_reg(&operands[0])?);
+            i.set_opcode(0b0010011);
+
+            Instruction::IType(i)
+        }
+        "aes32esi" | "aes32esmi" | "aes32dsi" | "aes32dsmi" | "sm4ed" | "sm4ks" => {
+            if operands.len() != 4 {
+                return Err(Error::InvalidFormat);
+            }
+            let bs = parse_immediate(&operands[3])?;
+            if !(0..=3).contains(&bs) {
+                return Err(Error::ImmediateOutOfRange);
+            }
+            let funct5 = match mnemonic.as_str() {
+                "aes32esi" => 0b10001,
+                "aes32esmi" => 0b10011,
+                "aes32dsi" => 0b10101,
+                "aes32dsmi" => 0b10111,
+                "sm4ed" => 0b11000,
+                "sm4ks" => 0b11010,
+                _ => unreachable!(),
+            };
+            let mut b = ByteSelectType(0);
+            b.set_bs(bs as u32);
+            b.set_funct5(funct5);
+            b.set_rs2(parse_reg(&operands[2])?);
+            b.set_rs1(parse_reg(&operands[1])?);
+            b.set_rd(parse_reg(&operands[0])?);
+            b.set_opcode(0b0110011);
+
+            Instruction::ByteSelect(b)
+        }
+        "zext.h" => {
+            if operands.len() != 2 {
+                return Err(Error::InvalidFormat);
+            }
+            // pack with rs2 = x0, in OP-32 on RV64
+            let opcode = if xlen == Xlen::Rv64 {
+                0b0111011
+            } else {
+                0b0110011
+            };
+            let mut r = RType(0);
+            r.set_funct7(0x04);
+            r.set_rs1(parse_reg(&operands[1])?);
+            r.set_funct3(0x4);
+            r.set_rd(parse_reg(&operands[0])?);
+            r.set_opcode(opcode);
+
+            Instruction::RType(r)
+        }
+        "sb" | "sh" | "sw" | "sd" | "fsh" | "fsw" | "fsd" | "fsq" => {
+            if operands.len() != 3 {
+                return Err(Error::InvalidFormat);
+            }
+            let rs2 = if is_fp_load_store(&mnemonic) {
+                parse_freg(&operands[0])?
+            } else {
+                parse_reg(&operands[0])?
+            };
             let imm = parse_immediate(&operands[1])?;
             let rs1 = parse_reg(&operands[2])?;
 
-            if imm < -2048 || imm > 2047 {
+            if !(-2048..=2047).contains(&imm) {
                 return Err(Error::ImmediateOutOfRange);
             }
 
@@ -136,9 +509,18 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
                 "sb" => 0x0,
                 "sh" => 0x1,
                 "sw" => 0x2,
+                "sd" => 0x3,
+                "fsh" => 0x1,
+                "fsw" => 0x2,
+                "fsd" => 0x3,
+                "fsq" => 0x4,
                 _ => unreachable!(),
             };
-            let opcode = 0b0100011;
+            let opcode = if is_fp_load_store(&mnemonic) {
+                0b0100111
+            } else {
+                0b0100011
+            };
 
             let mut s = SType(0);
             s.set_imm4_0((imm as u32) & 0x1f);
@@ -158,7 +540,7 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
             let rs2 = parse_reg(&operands[1])?;
             let imm = parse_immediate(&operands[2])?;
 
-            if imm < -4096 || imm > 4094 {
+            if !(-4096..=4094).contains(&imm) {
                 return Err(Error::ImmediateOutOfRange);
             }
             if imm % 2 != 0 {
@@ -194,7 +576,8 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
             }
             let rd = parse_reg(&operands[0])?;
             let imm = parse_immediate(&operands[1])?;
-            if imm < -524288 || imm > 524287 {
+            // the 20-bit upper immediate may be written signed or unsigned
+            if !(-524288..=1048575).contains(&imm) {
                 return Err(Error::ImmediateOutOfRange);
             }
             let opcode = if mnemonic == "lui" {
@@ -215,7 +598,7 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
             }
             let rd = parse_reg(&operands[0])?;
             let imm = parse_immediate(&operands[1])?;
-            if imm < -1048576 || imm > 1048574 {
+            if !(-1048576..=1048574).contains(&imm) {
                 return Err(Error::ImmediateOutOfRange);
             }
             if imm % 2 != 0 {
@@ -236,10 +619,7 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
                 return Err(Error::InvalidFormat);
             }
             let rd = parse_reg(&operands[0])?;
-            let csr = parse_immediate(&operands[1])? as u32;
-            if csr > 0xfff {
-                return Err(Error::ImmediateOutOfRange);
-            }
+            let csr = parse_csr(&operands[1])?;
             let rs1: u32;
             let is_imm: bool;
             if ["csrrwi", "csrrsi", "csrrci"].contains(&mnemonic.as_str()) {
@@ -272,12 +652,1598 @@ pub fn encode(instr_string: &str) -> Result<EncodedInstruction> {
 
             Instruction::CSRType(c)
         }
+        "ecall" | "ebreak" => {
+            if !operands.is_empty() {
+                return Err(Error::InvalidFormat);
+            }
+            let mut c = CSRType(0);
+            c.set_csr(if mnemonic == "ecall" { 0x000 } else { 0x001 });
+            c.set_opcode(0b1110011);
+
+            Instruction::CSRType(c)
+        }
+        "uret" | "sret" | "mret" | "mnret" | "dret" | "wfi" | "sfence.w.inval"
+        | "sfence.inval.ir" | "wrs.nto" | "wrs.sto" => {
+            if !operands.is_empty() {
+                return Err(Error::InvalidFormat);
+            }
+            let (funct7, rs2) = match mnemonic.as_str() {
+                "uret" => (0b0000000, 0b00010),
+                "sret" => (0b0001000, 0b00010),
+                "mret" => (0b0011000, 0b00010),
+                "mnret" => (0b0111000, 0b00010),
+                "dret" => (0b0111101, 0b10010),
+                "wfi" => (0b0001000, 0b00101),
+                "sfence.w.inval" => (0b0001100, 0b00000),
+                "sfence.inval.ir" => (0b0001100, 0b00001),
+                "wrs.nto" => (0b0000000, 0b01101),
+                "wrs.sto" => (0b0000000, 0b11101),
+                _ => unreachable!(),
+            };
+            let mut r = RType(0);
+            r.set_funct7(funct7);
+            r.set_rs2(rs2);
+            r.set_opcode(0b1110011);
+
+            Instruction::RType(r)
+        }
+        "sfence.vma" | "sinval.vma" | "hfence.vvma" | "hfence.gvma" | "hinval.vvma"
+        | "hinval.gvma" => {
+            if operands.len() != 2 {
+                return Err(Error::InvalidFormat);
+            }
+            let rs1 = parse_reg(&operands[0])?;
+            let rs2 = parse_reg(&operands[1])?;
+            let funct7 = match mnemonic.as_str() {
+                "sfence.vma" => 0b0001001,
+                "sinval.vma" => 0b0001011,
+                "hfence.vvma" => 0b0010001,
+                "hfence.gvma" => 0b0110001,
+                "hinval.vvma" => 0b0010011,
+                "hinval.gvma" => 0b0110011,
+                _ => unreachable!(),
+            };
+            let mut r = RType(0);
+            r.set_funct7(funct7);
+            r.set_rs2(rs2);
+            r.set_rs1(rs1);
+            r.set_opcode(0b1110011);
+
+            Instruction::RType(r)
+        }
+        "hlv.b" | "hlv.bu" | "hlv.h" | "hlv.hu" | "hlv.w" | "hlv.wu" | "hlv.d" | "hlvx.hu"
+        | "hlvx.wu" | "hsv.b" | "hsv.h" | "hsv.w" | "hsv.d" => {
+            if operands.len() != 2 {
+                return Err(Error::InvalidFormat);
+            }
+            let rs1 = parse_reg(&operands[1])?;
+            // loads pick signed, unsigned or execute-permission access with rs2, stores
+            // take their data register there
+            let (funct7, rs2, rd) = match mnemonic.as_str() {
+                "hlv.b" => (0b0110000, 0b00000, parse_reg(&operands[0])?),
+                "hlv.bu" => (0b0110000, 0b00001, parse_reg(&operands[0])?),
+                "hlv.h" => (0b0110010, 0b00000, parse_reg(&operands[0])?),
+                "hlv.hu" => (0b0110010, 0b00001, parse_reg(&operands[0])?),
+                "hlvx.hu" => (0b0110010, 0b00011, parse_reg(&operands[0])?),
+                "hlv.w" => (0b0110100, 0b00000, parse_reg(&operands[0])?),
+                "hlv.wu" => (0b0110100, 0b00001, parse_reg(&operands[0])?),
+                "hlvx.wu" => (0b0110100, 0b00011, parse_reg(&operands[0])?),
+                "hlv.d" => (0b0110110, 0b00000, parse_reg(&operands[0])?),
+                "hsv.b" => (0b0110001, parse_reg(&operands[0])?, 0),
+                "hsv.h" => (0b0110011, parse_reg(&operands[0])?, 0),
+                "hsv.w" => (0b0110101, parse_reg(&operands[0])?, 0),
+                "hsv.d" => (0b0110111, parse_reg(&operands[0])?, 0),
+                _ => unreachable!(),
+            };
+            let mut r = RType(0);
+            r.set_funct7(funct7);
+            r.set_rs2(rs2);
+            r.set_rs1(rs1);
+            r.set_funct3(0x4);
+            r.set_rd(rd);
+            r.set_opcode(0b1110011);
+
+            Instruction::RType(r)
+        }
+        "fence" | "fence.tso" | "pause" | "fence.i" => {
+            // a bare `fence` orders everything, like the GNU assembler
+            let (funct3, fm, pred, succ) = match mnemonic.as_str() {
+                "fence" => match operands.len() {
+                    0 => (0x0, 0b0000, 0b1111, 0b1111),
+                    2 => (
+                        0x0,
+                        0b0000,
+                        parse_fence_set(&operands[0])?,
+                        parse_fence_set(&operands[1])?,
+                    ),
+                    _ => return Err(Error::InvalidFormat),
+                },
+                _ if !operands.is_empty() => return Err(Error::InvalidFormat),
+                "fence.tso" => (0x0, 0b1000, 0b0011, 0b0011),
+                "pause" => (0x0, 0b0000, 0b0001, 0b0000),
+                "fence.i" => (0x1, 0b0000, 0b0000, 0b0000),
+                _ => unreachable!(),
+            };
+            let opcode = 0b0001111;
+
+            let mut f = FenceType(0);
+            f.set_fm(fm);
+            f.set_pred(pred);
+            f.set_succ(succ);
+            f.set_funct3(funct3);
+            f.set_opcode(opcode);
+
+            Instruction::Fence(f)
+        }
+        m if m.starts_with("lr.") || m.starts_with("sc.") || m.starts_with("amo") => {
+            let (base, aq, rl) = split_amo_ordering(m);
+            let (name, width) = base.split_once('.').ok_or(Error::UnknownInstruction)?;
+            let funct3 = match width {
+                "b" => 0x0,
+                "h" => 0x1,
+                "w" => 0x2,
+                "d" => 0x3,
+                "q" => 0x4,
+                _ => return Err(Error::UnknownInstruction),
+            };
+            // only amocas comes in quadwords, lr and sc in words and doublewords
+            let funct5 = match name {
+                "amocas" => 0b00101,
+                _ if width == "q" => return Err(Error::UnknownInstruction),
+                "lr" | "sc" if !matches!(width, "w" | "d") => {
+                    return Err(Error::UnknownInstruction);
+                }
+                "lr" => 0b00010,
+                "sc" => 0b00011,
+                "amoswap" => 0b00001,
+                "amoadd" => 0b00000,
+                "amoxor" => 0b00100,
+                "amoand" => 0b01100,
+                "amoor" => 0b01000,
+                "amomin" => 0b10000,
+                "amomax" => 0b10100,
+                "amominu" => 0b11000,
+                "amomaxu" => 0b11100,
+                _ => return Err(Error::UnknownInstruction),
+            };
+            // the address may come with a zero offset, `0(rs1)`, as GNU as accepts
+            let mut operands = operands.clone();
+            let registers = if name == "lr" { 2 } else { 3 };
+            if operands.len() == registers + 1 {
+                let offset = operands.remove(registers - 1);
+                if parse_immediate(&offset)? != 0 {
+                    return Err(Error::ImmediateOutOfRange);
+                }
+            }
+            // lr has no rs2: `lr.w rd, (rs1)`
+            let (rd, rs2, rs1) = match (name, operands.len()) {
+                ("lr", 2) => (parse_reg(&operands[0])?, 0, parse_reg(&operands[1])?),
+                (_, 3) if name != "lr" => (
+                    parse_reg(&operands[0])?,
+                    parse_reg(&operands[1])?,
+                    parse_reg(&operands[2])?,
+                ),
+                _ => return Err(Error::InvalidFormat),
+            };
+            if is_register_pair_cas(name, width, xlen) && (rd | rs2) & 1 != 0 {
+                return Err(Error::InvalidRegisterPair);
+            }
+            let opcode = 0b0101111;
+
+            let mut a = AMOType(0);
+            a.set_funct5(funct5);
+            a.set_aq(aq);
+            a.set_rl(rl);
+            a.set_rs2(rs2);
+            a.set_rs1(rs1);
+            a.set_funct3(funct3);
+            a.set_rd(rd);
+            a.set_opcode(opcode);
+
+            Instruction::Amo(a)
+        }
+        m if m.starts_with("c.") => encode_compressed(m, &operands, xlen)?,
+        m if m.starts_with("cm.") => encode_zcmp(m, &operands, xlen)?,
+        "vsetvli" | "vsetivli" | "vsetvl" => encode_vector_config(&mnemonic, &operands)?,
+        m if m.starts_with('v') => encode_vector(m, &operands)?,
+        // the fence and floating-point load/store arms above also start with `f`
+        m if m.starts_with('f') => encode_fp(m, &operands)?,
+        _ => return Err(Error::UnknownInstruction),
+    };
+
+    // the typed operands come from decoding the word, which also normalises the text.
+    // Zcmp and Zcmt share their encodings with c.fsdsp
+    if mnemonic.starts_with("cm.") {
+        decode_zcmp(instr.raw(), xlen)
+    } else {
+        decode(instr.raw(), xlen)
+    }
+}
+
+impl EncodedInstruction {
+    // parses assembly for the given XLEN, the text of a decoded instruction parses back
+    // to its word with the `xlen` it was decoded for
+    pub fn parse_with(s: &str, xlen: Xlen) -> Result<Self> {
+        encode(s, xlen)
+    }
+}
+
+// parses assembly as RV64, the instructions that only exist on RV32 (the compressed
+// encodings RV64 reassigns, the smaller cm.push/cm.pop adjustments) fall back to it. The
+// XLEN used ends up in `xlen`. rev8, zext.h and some cm.push/cm.pop adjustments are the
+// same text on both with different words, they come out as RV64; `parse_with` picks
+impl FromStr for EncodedInstruction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match encode(s, Xlen::Rv64) {
+            Err(Error::UnsupportedXlen) => encode(s, Xlen::Rv32),
+            result => result,
+        }
+    }
+}
+
+// parses register operands by kind (`x` or `f` prefix) followed by an optional rounding mode
+fn parse_fp_operands(
+    kinds: &str,
+    operands: &[String],
+    has_rm: bool,
+) -> Result<(Vec<u32>, Option<u32>)> {
+    let rm = match operands.len().checked_sub(kinds.len()) {
+        Some(0) => None,
+        Some(1) if has_rm => Some(parse_rounding_mode(&operands[kinds.len()])?),
+        _ => return Err(Error::InvalidFormat),
+    };
+    let regs = kinds
+        .chars()
+        .zip(operands)
+        .map(|(kind, op)| {
+            if kind == 'f' {
+                parse_freg(op)
+            } else {
+                parse_reg(op)
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((regs, rm))
+}
+
+fn encode_fp(mnemonic: &str, operands: &[String]) -> Result<Instruction> {
+    let parts = mnemonic.split('.').collect::<Vec<_>>();
+
+    if let [name, fmt] = parts.as_slice()
+        && ["fmadd", "fmsub", "fnmsub", "fnmadd"].contains(name)
+    {
+        let fmt = fp_fmt_code(fmt).ok_or(Error::UnknownInstruction)?;
+        let (regs, rm) = parse_fp_operands("ffff", operands, true)?;
+        let rm = rm.unwrap_or(0b111); // dyn
+        let opcode = match *name {
+            "fmadd" => 0b1000011,
+            "fmsub" => 0b1000111,
+            "fnmsub" => 0b1001011,
+            "fnmadd" => 0b1001111,
+            _ => unreachable!(),
+        };
+
+        let mut r = R4Type(0);
+        r.set_rs3(regs[3]);
+        r.set_fmt(fmt);
+        r.set_rs2(regs[2]);
+        r.set_rs1(regs[1]);
+        r.set_rm(rm);
+        r.set_rd(regs[0]);
+        r.set_opcode(opcode);
+        return Ok(Instruction::R4Type(r));
+    }
+
+    // funct3 is None where it holds the rounding mode, rs2 is fixed unless
+    // there are three register operands
+    let (funct5, fmt, funct3, rs2, kinds) = match parts.as_slice() {
+        [name, fmt] => {
+            let fmt = fp_fmt_code(fmt).ok_or(Error::UnknownInstruction)?;
+            let (funct5, funct3, kinds) = match *name {
+                "fadd" => (0b00000, None, "fff"),
+                "fsub" => (0b00001, None, "fff"),
+                "fmul" => (0b00010, None, "fff"),
+                "fdiv" => (0b00011, None, "fff"),
+                "fsqrt" => (0b01011, None, "ff"),
+                "fsgnj" => (0b00100, Some(0x0), "fff"),
+                "fsgnjn" => (0b00100, Some(0x1), "fff"),
+                "fsgnjx" => (0b00100, Some(0x2), "fff"),
+                "fmin" => (0b00101, Some(0x0), "fff"),
+                "fmax" => (0b00101, Some(0x1), "fff"),
+                "feq" => (0b10100, Some(0x2), "xff"),
+                "flt" => (0b10100, Some(0x1), "xff"),
+                "fle" => (0b10100, Some(0x0), "xff"),
+                "fclass" => (0b11100, Some(0x1), "xf"),
+                _ => return Err(Error::UnknownInstruction),
+            };
+            (funct5, fmt, funct3, 0, kinds)
+        }
+        ["fcvt", dst, src] => {
+            if let (Some(int), Some(fmt)) = (fp_int_code(dst), fp_fmt_code(src)) {
+                (0b11000, fmt, None, int, "xf")
+            } else if let (Some(fmt), Some(int)) = (fp_fmt_code(dst), fp_int_code(src)) {
+                (0b11010, fmt, None, int, "fx")
+            } else if let (Some(fmt), Some(src)) = (fp_fmt_code(dst), fp_fmt_code(src))
+                && fmt != src
+            {
+                (0b01000, fmt, None, src, "ff")
+            } else {
+                return Err(Error::UnknownInstruction);
+            }
+        }
+        ["fmv", "x", w] => {
+            let fmt = fp_move_code(w).ok_or(Error::UnknownInstruction)?;
+            (0b11100, fmt, Some(0x0), 0, "xf")
+        }
+        ["fmv", w, "x"] => {
+            let fmt = fp_move_code(w).ok_or(Error::UnknownInstruction)?;
+            (0b11110, fmt, Some(0x0), 0, "fx")
+        }
+        _ => return Err(Error::UnknownInstruction),
+    };
+    let (regs, rm) = parse_fp_operands(kinds, operands, funct3.is_none())?;
+    let rm = rm.unwrap_or(fp_default_rm(mnemonic));
+    let rs2 = regs.get(2).copied().unwrap_or(rs2);
+    let opcode = 0b1010011;
+
+    let mut r = RType(0);
+    r.set_funct7((funct5 << 2) | fmt);
+    r.set_rs2(rs2);
+    r.set_rs1(regs[1]);
+    r.set_funct3(funct3.unwrap_or(rm));
+    r.set_rd(regs[0]);
+    r.set_opcode(opcode);
+    Ok(Instruction::RType(r))
+}
+
+// vsetvli and vsetivli put their vtype immediate in place of funct6, vm and vs2, with the
+// top bits picking the instruction
+fn encode_vector_config(mnemonic: &str, operands: &[String]) -> Result<Instruction> {
+    let [rd, avl, rest @ ..] = operands else {
+        return Err(Error::InvalidFormat);
+    };
+    // bits 31:20
+    let (avl, upper) = match mnemonic {
+        "vsetvli" => {
+            let vtype = check_imm(parse_vtype(rest)? as i32, 0, 0x7ff, 1)?;
+            (parse_reg(avl)?, vtype)
+        }
+        "vsetivli" => {
+            let vtype = check_imm(parse_vtype(rest)? as i32, 0, 0x3ff, 1)?;
+            (
+                check_imm(parse_immediate(avl)?, 0, 31, 1)?,
+                0b11 << 10 | vtype,
+            )
+        }
+        _ => {
+            let [rs2] = rest else {
+                return Err(Error::InvalidFormat);
+            };
+            (parse_reg(avl)?, 0b1000000 << 5 | parse_reg(rs2)?)
+        }
+    };
+    let mut v = VectorType(0);
+    v.set_funct6(upper >> 6);
+    v.set_vm((upper >> 5) & 1 == 1);
+    v.set_vs2(upper & 0x1f);
+    v.set_vs1(avl);
+    v.set_funct3(OPCFG);
+    v.set_vd(parse_reg(rd)?);
+    v.set_opcode(OP_V);
+    Ok(Instruction::Vector(v))
+}
+
+// the OP-V arithmetic ops and the vector loads and stores, their operands are laid out by
+// the same slots the decoder uses
+fn encode_vector(mnemonic: &str, operands: &[String]) -> Result<Instruction> {
+    if let Some((store, nf, mop, umop, width, layout)) = vector_mem_code(mnemonic) {
+        let (vd, rs1, rs2, vm) = parse_vector_operands(&layout, operands, 0, umop)?;
+        let opcode = if store { 0b0100111 } else { 0b0000111 };
+
+        let mut v = VectorMemType(0);
+        v.set_nf(nf);
+        v.set_mop(mop);
+        v.set_vm(vm);
+        v.set_rs2(rs2);
+        v.set_rs1(rs1);
+        v.set_width(width);
+        v.set_vd(vd);
+        v.set_opcode(opcode);
+        return Ok(Instruction::VectorMem(v));
+    }
+
+    let (mut v, layout) = vector_op_code(mnemonic).ok_or(Error::UnknownInstruction)?;
+    let (vd, vs1, vs2, vm) = parse_vector_operands(&layout, operands, v.vs1(), 0)?;
+    // the sixth bit of a vror.vi shift amount goes to funct6
+    v.set_funct6(v.funct6() | vs1 >> 5);
+    v.set_vm(vm);
+    v.set_vs2(vs2);
+    v.set_vs1(vs1 & 0x1f);
+    v.set_vd(vd);
+    Ok(Instruction::Vector(v))
+}
+
+// vd, vs1, vs2 and the vm bit from the operands in layout order, the fields without an
+// operand keep the function code they start with. The `v0.t` mask is optional, the carry-in
+// is spelled `v0`
+fn parse_vector_operands(
+    layout: &[Slot],
+    operands: &[String],
+    vs1: u32,
+    vs2: u32,
+) -> Result<(u32, u32, u32, bool)> {
+    let (mut vd, mut vs1, mut vs2, mut vm) = (0, vs1, vs2, true);
+    let mut operands = operands.iter().peekable();
+    for &slot in layout {
+        if slot == Slot::Mask {
+            vm = operands.next_if(|op| op.as_str() == "v0.t").is_none();
+            continue;
+        }
+        let op = operands.next().ok_or(Error::InvalidFormat)?;
+        match slot {
+            Slot::Vd => vd = parse_vreg(op)?,
+            Slot::Rd => vd = parse_reg(op)?,
+            Slot::Fd => vd = parse_freg(op)?,
+            Slot::Vs2 | Slot::Index => vs2 = parse_vreg(op)?,
+            Slot::Stride => vs2 = parse_reg(op)?,
+            Slot::Vs1 => vs1 = parse_vreg(op)?,
+            Slot::Rs1 | Slot::Base => vs1 = parse_reg(op)?,
+            Slot::Fs1 => vs1 = parse_freg(op)?,
+            Slot::Simm => vs1 = check_imm(parse_immediate(op)?, -16, 15, 1)? & 0x1f,
+            Slot::Uimm => vs1 = check_imm(parse_immediate(op)?, 0, 31, 1)?,
+            Slot::Uimm6 => vs1 = check_imm(parse_immediate(op)?, 0, 63, 1)?,
+            Slot::Carry if op == "v0" => vm = false,
+            Slot::Carry => return Err(Error::InvalidRegister),
+            Slot::Mask => unreachable!(),
+        }
+    }
+    if operands.next().is_some() {
+        return Err(Error::InvalidFormat);
+    }
+    Ok((vd, vs1, vs2, vm))
+}
+
+fn check_imm(imm: i32, min: i32, max: i32, align: i32) -> Result<u32> {
+    if !(min..=max).contains(&imm) {
+        return Err(Error::ImmediateOutOfRange);
+    }
+    if imm % align != 0 {
+        return Err(Error::ImmediateMisaligned);
+    }
+    Ok(imm as u32)
+}
+
+// Zcmp push, pop and moves and the Zcmt table jumps
+fn encode_zcmp(mnemonic: &str, operands: &[String], xlen: Xlen) -> Result<Instruction> {
+    let instr = match mnemonic {
+        "cm.push" | "cm.pop" | "cm.popretz" | "cm.popret" => {
+            let [list, adj] = operands else {
+                return Err(Error::InvalidFormat);
+            };
+            let rlist = parse_reg_list(list)?;
+            // cm.push grows the stack, its adjustment is written negative
+            let adj = parse_immediate(adj)?;
+            let adj = if mnemonic == "cm.push" { -adj } else { adj };
+            let base = stack_adj_base(rlist, xlen) as i32;
+            // the smaller RV32 adjustments of a list do not exist on RV64
+            let other = if xlen == Xlen::Rv64 {
+                Xlen::Rv32
+            } else {
+                Xlen::Rv64
+            };
+            let other_base = stack_adj_base(rlist, other) as i32;
+            if adj % 16 == 0
+                && !(base..=base + 48).contains(&adj)
+                && (other_base..=other_base + 48).contains(&adj)
+            {
+                return Err(Error::UnsupportedXlen);
+            }
+            let spimm = (check_imm(adj, base, base + 48, 16)? - base as u32) / 16;
+            let (funct6, funct2) = match mnemonic {
+                "cm.push" => (0b101110, 0b00),
+                "cm.pop" => (0b101110, 0b10),
+                "cm.popretz" => (0b101111, 0b00),
+                "cm.popret" => (0b101111, 0b10),
+                _ => unreachable!(),
+            };
+            let mut c = CMPPType(0);
+            c.set_funct6(funct6);
+            c.set_funct2(funct2);
+            c.set_rlist(rlist as u16);
+            c.set_spimm(spimm as u16);
+            c.set_op(0b10);
+            Instruction::CMPPType(c)
+        }
+        "cm.mvsa01" | "cm.mva01s" => {
+            let [r1s, r2s] = operands else {
+                return Err(Error::InvalidFormat);
+            };
+            // only s0-s7 have a 3-bit encoding
+            let saved = |op: &str| match saved_reg_index(parse_reg(op)?) {
+                Some(idx @ 0..=7) => Ok(idx as u16),
+                _ => Err(Error::InvalidRegister),
+            };
+            let (r1s, r2s) = (saved(r1s)?, saved(r2s)?);
+            if mnemonic == "cm.mvsa01" && r1s == r2s {
+                return Err(Error::InvalidRegister);
+            }
+            let mut c = CMMVType(0);
+            c.set_funct6(0b101011);
+            c.set_r1s(r1s);
+            c.set_funct2(if mnemonic == "cm.mvsa01" { 0b01 } else { 0b11 });
+            c.set_r2s(r2s);
+            c.set_op(0b10);
+            Instruction::CMMVType(c)
+        }
+        "cm.jt" | "cm.jalt" => {
+            let [index] = operands else {
+                return Err(Error::InvalidFormat);
+            };
+            let index = parse_immediate(index)?;
+            let index = if mnemonic == "cm.jt" {
+                check_imm(index, 0, 31, 1)?
+            } else {
+                check_imm(index, 32, 255, 1)?
+            };
+            let mut c = CMJTType(0);
+            c.set_funct6(0b101000);
+            c.set_index(index as u16);
+            c.set_op(0b10);
+            Instruction::CMJTType(c)
+        }
+        _ => return Err(Error::UnknownInstruction),
+    };
+    Ok(instr)
+}
+
+// rd', rs1' and rs2' only address x8-x15 / f8-f15
+fn parse_compressed_reg(op: &str, float: bool) -> Result<u16> {
+    let reg = if float {
+        parse_freg(op)?
+    } else {
+        parse_reg(op)?
+    };
+    if (8..=15).contains(&reg) {
+        Ok((reg - 8) as u16)
+    } else {
+        Err(Error::InvalidRegister)
+    }
+}
+
+fn parse_sp(op: &str) -> Result<()> {
+    if parse_reg(op)? == 2 {
+        Ok(())
+    } else {
+        Err(Error::InvalidRegister)
+    }
+}
+
+fn encode_compressed(mnemonic: &str, operands: &[String], xlen: Xlen) -> Result<Instruction> {
+    let rv64 = xlen == Xlen::Rv64;
+    let float = mnemonic.starts_with("c.f");
+    let expected_operands = match mnemonic {
+        "c.unimp" | "c.nop" | "c.ebreak" | "c.ntl.p1" | "c.ntl.pall" | "c.ntl.s1" | "c.ntl.all" => {
+            0
+        }
+        "c.jal" | "c.j" | "c.jr" | "c.jalr" | "c.zext.b" | "c.sext.b" | "c.zext.h" | "c.sext.h"
+        | "c.zext.w" | "c.not" => 1,
+        "c.addi4spn" | "c.lw" | "c.ld" | "c.flw" | "c.fld" | "c.sw" | "c.sd" | "c.fsw"
+        | "c.fsd" | "c.lwsp" | "c.ldsp" | "c.flwsp" | "c.fldsp" | "c.swsp" | "c.sdsp"
+        | "c.fswsp" | "c.fsdsp" | "c.lbu" | "c.lhu" | "c.lh" | "c.sb" | "c.sh" => 3,
+        "c.addi" | "c.addiw" | "c.li" | "c.addi16sp" | "c.lui" | "c.srli" | "c.srai" | "c.andi"
+        | "c.sub" | "c.xor" | "c.or" | "c.and" | "c.subw" | "c.addw" | "c.beqz" | "c.bnez"
+        | "c.slli" | "c.mv" | "c.add" | "c.mul" => 2,
         _ => return Err(Error::UnknownInstruction),
     };
+    if operands.len() != expected_operands {
+        return Err(Error::InvalidFormat);
+    }
 
-    Ok(EncodedInstruction {
-        instr,
-        mnemonic,
-        operands,
-    })
+    let instr = match mnemonic {
+        "c.unimp" => Instruction::CIWType(CIWType(0)),
+        "c.addi4spn" => {
+            parse_sp(&operands[1])?;
+            let imm = check_imm(parse_immediate(&operands[2])?, 4, 1020, 4)?;
+            let mut c = CIWType(0);
+            c.set_funct3(0b000);
+            c.set_imm(pack_imm(imm, &CIW_ADDI4SPN));
+            c.set_rd(parse_compressed_reg(&operands[0], false)?);
+            c.set_op(0b00);
+            Instruction::CIWType(c)
+        }
+        "c.lw" | "c.ld" | "c.flw" | "c.fld" | "c.sw" | "c.sd" | "c.fsw" | "c.fsd" => {
+            let reg = parse_compressed_reg(&operands[0], float)?;
+            let imm = parse_immediate(&operands[1])?;
+            let rs1 = parse_compressed_reg(&operands[2], false)?;
+            let (funct3, double) = match mnemonic {
+                "c.fld" => (0b001, true),
+                "c.lw" => (0b010, false),
+                "c.ld" => (0b011, true),
+                "c.flw" => (0b011, false),
+                "c.fsd" => (0b101, true),
+                "c.sw" => (0b110, false),
+                "c.sd" => (0b111, true),
+                "c.fsw" => (0b111, false),
+                _ => unreachable!(),
+            };
+            let (imm12_10, imm6_5) = if double {
+                let imm = check_imm(imm, 0, 248, 8)?;
+                (
+                    pack_imm(imm, &CL_DOUBLE_12_10),
+                    pack_imm(imm, &CL_DOUBLE_6_5),
+                )
+            } else {
+                let imm = check_imm(imm, 0, 124, 4)?;
+                (pack_imm(imm, &CL_WORD_12_10), pack_imm(imm, &CL_WORD_6_5))
+            };
+            if funct3 & 0b100 == 0 {
+                let mut c = CLType(0);
+                c.set_funct3(funct3);
+                c.set_imm12_10(imm12_10);
+                c.set_rs1(rs1);
+                c.set_imm6_5(imm6_5);
+                c.set_rd(reg);
+                c.set_op(0b00);
+                Instruction::CLType(c)
+            } else {
+                let mut c = CSType(0);
+                c.set_funct3(funct3);
+                c.set_imm12_10(imm12_10);
+                c.set_rs1(rs1);
+                c.set_imm6_5(imm6_5);
+                c.set_rs2(reg);
+                c.set_op(0b00);
+                Instruction::CSType(c)
+            }
+        }
+        "c.nop" | "c.addi" | "c.addiw" | "c.li" | "c.addi16sp" | "c.lui" | "c.slli" => {
+            let (rd, imm) = match mnemonic {
+                "c.nop" => (0, 0),
+                _ => (parse_reg(&operands[0])?, parse_immediate(&operands[1])?),
+            };
+            let (funct3, op) = match mnemonic {
+                "c.nop" | "c.addi" => (0b000, 0b01),
+                "c.addiw" => (0b001, 0b01),
+                "c.li" => (0b010, 0b01),
+                "c.addi16sp" | "c.lui" => (0b011, 0b01),
+                "c.slli" => (0b000, 0b10),
+                _ => unreachable!(),
+            };
+            let (imm12, imm6_2) = match mnemonic {
+                "c.addi16sp" => {
+                    if rd != 2 {
+                        return Err(Error::InvalidRegister);
+                    }
+                    if imm == 0 {
+                        return Err(Error::ImmediateOutOfRange);
+                    }
+                    let imm = check_imm(imm, -512, 496, 16)?;
+                    (
+                        pack_imm(imm, &CI_ADDI16SP_12),
+                        pack_imm(imm, &CI_ADDI16SP_6_2),
+                    )
+                }
+                "c.slli" => {
+                    let imm = check_imm(imm, 0, if rv64 { 63 } else { 31 }, 1)?;
+                    (pack_imm(imm, &CI_IMM_12), pack_imm(imm, &CI_IMM_6_2))
+                }
+                _ => {
+                    // rd = x0 is reserved for c.addiw, rd = x2 selects c.addi16sp for c.lui
+                    if mnemonic == "c.addiw" && rd == 0 || mnemonic == "c.lui" && rd == 2 {
+                        return Err(Error::InvalidRegister);
+                    }
+                    if mnemonic == "c.lui" && imm == 0 {
+                        return Err(Error::ImmediateOutOfRange);
+                    }
+                    // GNU as and llvm-mc write a negative c.lui immediate as its 20-bit
+                    // pattern, e.g. `c.lui a0, 0xfffff` for -1
+                    let imm = match imm {
+                        0xfffe0..=0xfffff if mnemonic == "c.lui" => imm - 0x100000,
+                        _ => imm,
+                    };
+                    let imm = check_imm(imm, -32, 31, 1)?;
+                    (pack_imm(imm, &CI_IMM_12), pack_imm(imm, &CI_IMM_6_2))
+                }
+            };
+            let mut c = CIType(0);
+            c.set_funct3(funct3);
+            c.set_imm12(imm12);
+            c.set_rd(rd as u16);
+            c.set_imm6_2(imm6_2);
+            c.set_op(op);
+            Instruction::CIType(c)
+        }
+        "c.lwsp" | "c.ldsp" | "c.flwsp" | "c.fldsp" => {
+            let rd = if float {
+                parse_freg(&operands[0])?
+            } else {
+                parse_reg(&operands[0])?
+            };
+            if !float && rd == 0 {
+                return Err(Error::InvalidRegister);
+            }
+            let imm = parse_immediate(&operands[1])?;
+            parse_sp(&operands[2])?;
+            let (funct3, imm6_2) = match mnemonic {
+                "c.fldsp" => (0b001, pack_imm(check_imm(imm, 0, 504, 8)?, &CI_LDSP_6_2)),
+                "c.lwsp" => (0b010, pack_imm(check_imm(imm, 0, 252, 4)?, &CI_LWSP_6_2)),
+                "c.ldsp" => (0b011, pack_imm(check_imm(imm, 0, 504, 8)?, &CI_LDSP_6_2)),
+                "c.flwsp" => (0b011, pack_imm(check_imm(imm, 0, 252, 4)?, &CI_LWSP_6_2)),
+                _ => unreachable!(),
+            };
+            let mut c = CIType(0);
+            c.set_funct3(funct3);
+            c.set_imm12(pack_imm(imm as u32, &CI_IMM_12));
+            c.set_rd(rd as u16);
+            c.set_imm6_2(imm6_2);
+            c.set_op(0b10);
+            Instruction::CIType(c)
+        }
+        "c.srli" | "c.srai" | "c.andi" => {
+            let rd = parse_compressed_reg(&operands[0], false)?;
+            let imm = parse_immediate(&operands[1])?;
+            let (funct2, imm) = match mnemonic {
+                "c.srli" => (0b00, check_imm(imm, 0, if rv64 { 63 } else { 31 }, 1)?),
+                "c.srai" => (0b01, check_imm(imm, 0, if rv64 { 63 } else { 31 }, 1)?),
+                _ => (0b10, check_imm(imm, -32, 31, 1)?),
+            };
+            let mut c = CBType(0);
+            c.set_funct3(0b100);
+            c.set_offset12_10((pack_imm(imm, &CI_IMM_12) << 2) | funct2);
+            c.set_rs1(rd);
+            c.set_offset6_2(pack_imm(imm, &CI_IMM_6_2));
+            c.set_op(0b01);
+            Instruction::CBType(c)
+        }
+        "c.lbu" | "c.lhu" | "c.lh" | "c.sb" | "c.sh" => {
+            let reg = parse_compressed_reg(&operands[0], false)?;
+            let imm = parse_immediate(&operands[1])?;
+            let rs1 = parse_compressed_reg(&operands[2], false)?;
+            // the byte ops put uimm[0] in bit 6, the halfword ops a function bit
+            let (funct6, imm) = match mnemonic {
+                "c.lbu" | "c.sb" => {
+                    let imm = check_imm(imm, 0, 3, 1)? as u16;
+                    let funct6 = if mnemonic == "c.lbu" {
+                        0b100000
+                    } else {
+                        0b100010
+                    };
+                    (funct6, (imm & 1) << 1 | imm >> 1)
+                }
+                _ => {
+                    let imm = check_imm(imm, 0, 2, 2)? as u16;
+                    let funct6 = if mnemonic == "c.sh" {
+                        0b100011
+                    } else {
+                        0b100001
+                    };
+                    let bit6 = (mnemonic == "c.lh") as u16;
+                    (funct6, bit6 << 1 | imm >> 1)
+                }
+            };
+            if mnemonic.starts_with("c.l") {
+                let mut c = CLBType(0);
+                c.set_funct6(funct6);
+                c.set_rs1(rs1);
+                c.set_imm(imm);
+                c.set_rd(reg);
+                c.set_op(0b00);
+                Instruction::CLBType(c)
+            } else {
+                let mut c = CSBType(0);
+                c.set_funct6(funct6);
+                c.set_rs1(rs1);
+                c.set_imm(imm);
+                c.set_rs2(reg);
+                c.set_op(0b00);
+                Instruction::CSBType(c)
+            }
+        }
+        "c.zext.b" | "c.sext.b" | "c.zext.h" | "c.sext.h" | "c.zext.w" | "c.not" => {
+            let funct5 = match mnemonic {
+                "c.zext.b" => 0b11000,
+                "c.sext.b" => 0b11001,
+                "c.zext.h" => 0b11010,
+                "c.sext.h" => 0b11011,
+                "c.zext.w" => 0b11100,
+                "c.not" => 0b11101,
+                _ => unreachable!(),
+            };
+            let mut c = CUType(0);
+            c.set_funct6(0b100111);
+            c.set_rd(parse_compressed_reg(&operands[0], false)?);
+            c.set_funct5(funct5);
+            c.set_op(0b01);
+            Instruction::CUType(c)
+        }
+        "c.sub" | "c.xor" | "c.or" | "c.and" | "c.subw" | "c.addw" | "c.mul" => {
+            let (funct6, funct2) = match mnemonic {
+                "c.mul" => (0b100111, 0b10),
+                "c.sub" => (0b100011, 0b00),
+                "c.xor" => (0b100011, 0b01),
+                "c.or" => (0b100011, 0b10),
+                "c.and" => (0b100011, 0b11),
+                "c.subw" => (0b100111, 0b00),
+                "c.addw" => (0b100111, 0b01),
+                _ => unreachable!(),
+            };
+            let mut c = CAType(0);
+            c.set_funct6(funct6);
+            c.set_rd(parse_compressed_reg(&operands[0], false)?);
+            c.set_funct2(funct2);
+            c.set_rs2(parse_compressed_reg(&operands[1], false)?);
+            c.set_op(0b01);
+            Instruction::CAType(c)
+        }
+        "c.jal" | "c.j" => {
+            let imm = check_imm(parse_immediate(&operands[0])?, -2048, 2046, 2)?;
+            let mut c = CJType(0);
+            c.set_funct3(if mnemonic == "c.jal" { 0b001 } else { 0b101 });
+            c.set_target(pack_imm(imm, &CJ_TARGET));
+            c.set_op(0b01);
+            Instruction::CJType(c)
+        }
+        "c.beqz" | "c.bnez" => {
+            let rs1 = parse_compressed_reg(&operands[0], false)?;
+            let imm = check_imm(parse_immediate(&operands[1])?, -256, 254, 2)?;
+            let mut c = CBType(0);
+            c.set_funct3(if mnemonic == "c.beqz" { 0b110 } else { 0b111 });
+            c.set_offset12_10(pack_imm(imm, &CB_BRANCH_12_10));
+            c.set_rs1(rs1);
+            c.set_offset6_2(pack_imm(imm, &CB_BRANCH_6_2));
+            c.set_op(0b01);
+            Instruction::CBType(c)
+        }
+        "c.jr" | "c.jalr" | "c.mv" | "c.add" | "c.ebreak" | "c.ntl.p1" | "c.ntl.pall"
+        | "c.ntl.s1" | "c.ntl.all" => {
+            let (rd, rs2) = match mnemonic {
+                "c.ebreak" => (0, 0),
+                "c.ntl.p1" => (0, 2),
+                "c.ntl.pall" => (0, 3),
+                "c.ntl.s1" => (0, 4),
+                "c.ntl.all" => (0, 5),
+                "c.jr" | "c.jalr" => (parse_reg(&operands[0])?, 0),
+                _ => (parse_reg(&operands[0])?, parse_reg(&operands[1])?),
+            };
+            // rs1 = x0 and rs2 = x0 select other instructions in this space
+            let reserved = match mnemonic {
+                "c.jr" | "c.jalr" => rd == 0,
+                "c.mv" | "c.add" => rs2 == 0,
+                _ => false,
+            };
+            if reserved {
+                return Err(Error::InvalidRegister);
+            }
+            let funct4 = match mnemonic {
+                "c.jr" | "c.mv" => 0b1000,
+                _ => 0b1001,
+            };
+            let mut c = CRType(0);
+            c.set_funct4(funct4);
+            c.set_rd(rd as u16);
+            c.set_rs2(rs2 as u16);
+            c.set_op(0b10);
+            Instruction::CRType(c)
+        }
+        "c.swsp" | "c.sdsp" | "c.fswsp" | "c.fsdsp" => {
+            let rs2 = if float {
+                parse_freg(&operands[0])?
+            } else {
+                parse_reg(&operands[0])?
+            };
+            let imm = parse_immediate(&operands[1])?;
+            parse_sp(&operands[2])?;
+            let (funct3, imm) = match mnemonic {
+                "c.fsdsp" => (0b101, pack_imm(check_imm(imm, 0, 504, 8)?, &CSS_SDSP)),
+                "c.swsp" => (0b110, pack_imm(check_imm(imm, 0, 252, 4)?, &CSS_SWSP)),
+                "c.sdsp" => (0b111, pack_imm(check_imm(imm, 0, 504, 8)?, &CSS_SDSP)),
+                "c.fswsp" => (0b111, pack_imm(check_imm(imm, 0, 252, 4)?, &CSS_SWSP)),
+                _ => unreachable!(),
+            };
+            let mut c = CSSType(0);
+            c.set_funct3(funct3);
+            c.set_imm(imm);
+            c.set_rs2(rs2 as u16);
+            c.set_op(0b10);
+            Instruction::CSSType(c)
+        }
+        _ => unreachable!(),
+    };
+    Ok(instr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operand::{Operand, Reg};
+
+    // the M extension against the words GNU as and llvm-mc give for `<op> a0, a1, a2`
+    #[test]
+    fn multiply_divide_round_trip() {
+        let cases = [
+            ("mul", 0x02c58533),
+            ("mulh", 0x02c59533),
+            ("mulhsu", 0x02c5a533),
+            ("mulhu", 0x02c5b533),
+            ("div", 0x02c5c533),
+            ("divu", 0x02c5d533),
+            ("rem", 0x02c5e533),
+            ("remu", 0x02c5f533),
+        ];
+        for (mnemonic, word) in cases {
+            for xlen in [Xlen::Rv32, Xlen::Rv64] {
+                let e = encode(&format!("{} a0, a1, a2", mnemonic), xlen).unwrap();
+                assert_eq!(e.instr.raw(), word, "{}", mnemonic);
+
+                let d = decode(word, xlen).unwrap();
+                assert_eq!(d.mnemonic, mnemonic);
+                let regs = [Reg(10), Reg(11), Reg(12)].map(Operand::Reg);
+                assert_eq!(&d.operands[..], &regs[..]);
+                assert_eq!(d.to_string(), format!("{} x10, x11, x12", mnemonic));
+                assert_eq!(d.abi().to_string(), format!("{} a0, a1, a2", mnemonic));
+            }
+        }
+    }
+
+    // `0(rs1)` and `(rs1)` give the same word, any other offset is rejected
+    #[test]
+    fn atomic_zero_offset() {
+        let cases = [
+            ("lr.w a0, 0(a1)", 0x1005a52f),
+            ("amoadd.w a0, a1, 0(a2)", 0x00b6252f),
+            ("sc.w.aq a0, a1, 0(a2)", 0x1cb6252f),
+        ];
+        for (text, word) in cases {
+            assert_eq!(
+                encode(text, Xlen::Rv32).unwrap().instr.raw(),
+                word,
+                "{}",
+                text
+            );
+            let plain = text.replace("0(", "(");
+            assert_eq!(
+                encode(&plain, Xlen::Rv32).unwrap().instr.raw(),
+                word,
+                "{}",
+                plain
+            );
+        }
+        for text in ["lr.w a0, 4(a1)", "amoadd.w a0, a1, -8(a2)"] {
+            assert_eq!(
+                encode(text, Xlen::Rv32).unwrap_err(),
+                Error::ImmediateOutOfRange
+            );
+        }
+    }
+
+    // c.lui takes its negative immediates signed or as the 20-bit pattern
+    #[test]
+    fn compressed_lui_immediate() {
+        for (text, word) in [
+            ("c.lui a0, -1", 0x757d),
+            ("c.lui a0, 0xfffff", 0x757d),
+            ("c.lui a0, 1048575", 0x757d),
+            ("c.lui a0, 0xfffe0", 0x7501),
+            ("c.lui a0, 31", 0x657d),
+        ] {
+            assert_eq!(
+                encode(text, Xlen::Rv32).unwrap().instr.raw(),
+                word,
+                "{}",
+                text
+            );
+        }
+        for text in ["c.lui a0, 0xfffdf", "c.lui a0, 32", "c.lui a0, 0x100000"] {
+            assert_eq!(
+                encode(text, Xlen::Rv32).unwrap_err(),
+                Error::ImmediateOutOfRange
+            );
+        }
+        // decoded like lui, llvm-mc shows 0x757d as `c.lui a0, 1048575`
+        for (word, text) in [
+            (0x757d, "c.lui x10, 1048575"),
+            (0x7501, "c.lui x10, 1048544"),
+            (0x657d, "c.lui x10, 31"),
+        ] {
+            let d = decode(word, Xlen::Rv32).unwrap();
+            assert_eq!(d.to_string(), text);
+            let lui = crate::rvc::expand(&d, Xlen::Rv32).unwrap();
+            assert_eq!(lui.to_string(), &text[2..]);
+        }
+    }
+
+    // the H extension loads and stores against the words of the privileged spec, the
+    // doubleword forms and hlv.wu only exist on RV64
+    #[test]
+    fn hypervisor_load_store() {
+        for (text, word, rv32) in [
+            ("hlv.b x10, (x11)", 0x6005c573, true),
+            ("hlv.bu x10, (x11)", 0x6015c573, true),
+            ("hlv.h x10, (x11)", 0x6405c573, true),
+            ("hlv.hu x10, (x11)", 0x6415c573, true),
+            ("hlvx.hu x10, (x11)", 0x6435c573, true),
+            ("hlv.w x10, (x11)", 0x6805c573, true),
+            ("hlvx.wu x10, (x11)", 0x6835c573, true),
+            ("hlv.wu x10, (x11)", 0x6815c573, false),
+            ("hlv.d x10, (x11)", 0x6c05c573, false),
+            ("hsv.b x10, (x11)", 0x62a5c073, true),
+            ("hsv.h x10, (x11)", 0x66a5c073, true),
+            ("hsv.w x10, (x11)", 0x6aa5c073, true),
+            ("hsv.d x10, (x11)", 0x6ea5c073, false),
+        ] {
+            assert_eq!(
+                encode(text, Xlen::Rv64).unwrap().instr.raw(),
+                word,
+                "{}",
+                text
+            );
+            assert_eq!(decode(word, Xlen::Rv64).unwrap().to_string(), text);
+            if rv32 {
+                assert_eq!(
+                    encode(text, Xlen::Rv32).unwrap().instr.raw(),
+                    word,
+                    "{}",
+                    text
+                );
+                assert_eq!(decode(word, Xlen::Rv32).unwrap().to_string(), text);
+            } else {
+                assert_eq!(
+                    encode(text, Xlen::Rv32).unwrap_err(),
+                    Error::UnsupportedXlen
+                );
+                assert_eq!(
+                    decode(word, Xlen::Rv32).unwrap_err(),
+                    Error::UnsupportedXlen
+                );
+            }
+        }
+        let abi = encode("hlv.w a0, (a1)", Xlen::Rv32).unwrap();
+        assert_eq!(abi.instr.raw(), 0x6805c573);
+    }
+
+    // the all-zero word is c.unimp, shown as unimp like the 32-bit csrrw x0, cycle, x0
+    #[test]
+    fn compressed_unimp() {
+        for xlen in [Xlen::Rv32, Xlen::Rv64] {
+            let d = decode(0x0000, xlen).unwrap();
+            assert_eq!(d.mnemonic.as_str(), "c.unimp");
+            assert_eq!(encode("c.unimp", xlen).unwrap().instr.raw(), 0x0000);
+            assert_eq!(crate::alias(&d).unwrap().to_string(), "unimp");
+            assert_eq!(
+                crate::rvc::expand(&d, xlen).unwrap().instr.raw(),
+                0xc0001073
+            );
+            let unimp = encode("unimp", xlen).unwrap();
+            assert_eq!(
+                crate::rvc::compress(&unimp, xlen).unwrap().instr.raw(),
+                0x0000
+            );
+            // c.addi4spn with a zero immediate stays reserved
+            assert_eq!(decode(0x0004, xlen).unwrap_err(), Error::UnknownInstruction);
+        }
+    }
+
+    // Display and parse_with give back every decodable word with the XLEN it was decoded
+    // for. FromStr reads RV64 and falls back to RV32 only for what RV64 lacks, so it gives
+    // back every RV64 word and the RV32-only ones
+    #[test]
+    fn display_from_str_round_trip() {
+        // the same text on both XLENs with different words
+        for (word, text) in [
+            (0x698b5193, "rev8 x3, x22"),
+            (0x0805c533, "zext.h x10, x11"),
+            (0xb866, "cm.push {x1, x8-x9}, -32"),
+        ] {
+            let d = decode_zcmp(word, Xlen::Rv32).unwrap();
+            assert_eq!(d.to_string(), text);
+            let parsed = EncodedInstruction::parse_with(text, Xlen::Rv32).unwrap();
+            assert_eq!(parsed.instr.raw(), word);
+        }
+        // an adjustment only RV32 has
+        let d = decode_zcmp(0xbe62, Xlen::Rv32).unwrap();
+        assert_eq!(d.to_string(), "cm.popret {x1, x8-x9}, 16");
+        let err = encode("cm.popret {x1, x8-x9}, 16", Xlen::Rv64).unwrap_err();
+        assert_eq!(err, Error::UnsupportedXlen);
+        let d: EncodedInstruction = "cm.popret {x1, x8-x9}, 16".parse().unwrap();
+        assert_eq!((d.instr.raw(), d.xlen), (0xbe62, Xlen::Rv32));
+        // other errors are not retried as RV32
+        let err = "cm.popret {x1, x8-x9}, 8"
+            .parse::<EncodedInstruction>()
+            .unwrap_err();
+        assert_eq!(err, Error::ImmediateOutOfRange);
+
+        let mut state = 0x2545f491u32;
+        let words = (0..0x10000).chain((0..200_000).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        }));
+        for word in words.chain([0x698b5193, 0x6b8b5193, 0x0805c533, 0x0805c53b]) {
+            for xlen in [Xlen::Rv32, Xlen::Rv64] {
+                for d in [decode(word, xlen), decode_zcmp(word, xlen)] {
+                    let Ok(d) = d else { continue };
+                    let text = d.to_string();
+                    let raw = d.instr.raw();
+                    let parsed = EncodedInstruction::parse_with(&text, d.xlen).unwrap();
+                    assert_eq!(parsed.instr.raw(), raw, "{}", text);
+                    let rv32_only =
+                        matches!(encode(&text, Xlen::Rv64), Err(Error::UnsupportedXlen));
+                    if xlen == Xlen::Rv64 || rv32_only {
+                        let parsed: EncodedInstruction = text.parse().unwrap();
+                        assert_eq!(parsed.instr.raw(), raw, "{}", text);
+                    }
+                }
+            }
+        }
+    }
+
+    // encodes each text to its word and decodes the word back to the same text
+    fn known_answers(xlen: Xlen, cases: &[(&str, u32)]) {
+        for &(text, word) in cases {
+            assert_eq!(encode(text, xlen).unwrap().instr.raw(), word, "{}", text);
+            assert_eq!(
+                decode(word, xlen).unwrap().to_string(),
+                text,
+                "{:#010x}",
+                word
+            );
+        }
+    }
+
+    // the fence family against llvm-mc, a bare fence orders everything
+    #[test]
+    fn fence_family() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("fence iorw, iorw", 0x0ff0000f),
+                ("fence w, r", 0x0120000f),
+                ("fence.tso", 0x8330000f),
+                ("fence.i", 0x0000100f),
+            ],
+        );
+        assert_eq!(encode("fence", Xlen::Rv32).unwrap().instr.raw(), 0x0ff0000f);
+    }
+
+    // RV64I against llvm-mc, the 6-bit shift amounts and word forms do not exist on RV32
+    #[test]
+    fn rv64_base() {
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("slli x10, x11, 63", 0x03f59513),
+                ("srai x10, x11, 63", 0x43f5d513),
+                ("addiw x10, x11, -1", 0xfff5851b),
+                ("sraiw x10, x11, 31", 0x41f5d51b),
+                ("ld x10, 8(x11)", 0x0085b503),
+                ("sd x10, 8(x11)", 0x00a5b423),
+            ],
+        );
+        known_answers(Xlen::Rv32, &[("slli x10, x11, 31", 0x01f59513)]);
+        let err = encode("slli a0, a1, 63", Xlen::Rv32).unwrap_err();
+        assert_eq!(err, Error::ImmediateOutOfRange);
+        for text in ["addiw a0, a1, -1", "ld a0, 8(a1)", "sd a0, 8(a1)"] {
+            assert_eq!(
+                encode(text, Xlen::Rv32).unwrap_err(),
+                Error::UnsupportedXlen
+            );
+        }
+        assert_eq!(
+            decode(0x0085b503, Xlen::Rv32).unwrap_err(),
+            Error::UnsupportedXlen
+        );
+    }
+
+    // the RV64M word forms against llvm-mc, reserved on RV32
+    #[test]
+    fn rv64_multiply_divide() {
+        let cases = [
+            ("mulw x10, x11, x12", 0x02c5853b),
+            ("divw x10, x11, x12", 0x02c5c53b),
+            ("divuw x10, x11, x12", 0x02c5d53b),
+            ("remw x10, x11, x12", 0x02c5e53b),
+            ("remuw x10, x11, x12", 0x02c5f53b),
+        ];
+        known_answers(Xlen::Rv64, &cases);
+        for (text, word) in cases {
+            assert_eq!(
+                encode(text, Xlen::Rv32).unwrap_err(),
+                Error::UnsupportedXlen
+            );
+            assert_eq!(
+                decode(word, Xlen::Rv32).unwrap_err(),
+                Error::UnsupportedXlen
+            );
+        }
+    }
+
+    // the F extension against llvm-mc, the rounding mode sits in funct3 and the default
+    // dyn is left out of the text
+    #[test]
+    fn single_precision() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("fadd.s f10, f11, f12", 0x00c5f553),
+                ("fadd.s f10, f11, f12, rtz", 0x00c59553),
+                ("fadd.s f10, f11, f12, rne", 0x00c58553),
+                ("fmadd.s f10, f11, f12, f13", 0x68c5f543),
+                ("fsqrt.s f10, f11", 0x5805f553),
+                ("fcvt.w.s x10, f11, rtz", 0xc0059553),
+                ("fmv.x.w x10, f11", 0xe0058553),
+                ("feq.s x10, f11, f12", 0xa0c5a553),
+                ("flw f10, 4(x11)", 0x0045a507),
+                ("fsw f10, 4(x11)", 0x00a5a227),
+            ],
+        );
+        let rtz = encode("fadd.s fa0, fa1, fa2, rtz", Xlen::Rv32).unwrap();
+        assert_eq!(rtz.instr.raw() >> 12 & 0b111, 0b001);
+        let err = encode("fadd.s fa0, fa1, fa2, up", Xlen::Rv32).unwrap_err();
+        assert_eq!(err, Error::InvalidRoundingMode);
+    }
+
+    // D and Q against llvm-mc and the spec, fmt in bits 26:25 is 00 for S, 01 for D,
+    // 10 for H and 11 for Q
+    #[test]
+    fn double_quad_precision() {
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("fadd.s f10, f11, f12", 0x00c5f553),
+                ("fadd.d f10, f11, f12", 0x02c5f553),
+                ("fadd.h f10, f11, f12", 0x04c5f553),
+                ("fadd.q f10, f11, f12", 0x06c5f553),
+                ("fld f10, 8(x11)", 0x0085b507),
+                ("fsd f10, 8(x11)", 0x00a5b427),
+                ("flq f10, 16(x11)", 0x0105c507),
+                ("fsq f10, 16(x11)", 0x00a5c827),
+                ("fcvt.d.s f10, f11", 0x42058553),
+                ("fcvt.s.d f10, f11", 0x4015f553),
+                ("fcvt.q.d f10, f11", 0x46158553),
+                ("fmv.x.d x10, f11", 0xe2058553),
+            ],
+        );
+        for (text, fmt) in [
+            ("fadd.s", 0b00),
+            ("fadd.d", 0b01),
+            ("fadd.h", 0b10),
+            ("fadd.q", 0b11),
+        ] {
+            let e = encode(&format!("{} fa0, fa1, fa2", text), Xlen::Rv32).unwrap();
+            assert_eq!(e.instr.raw() >> 25 & 0b11, fmt, "{}", text);
+        }
+        let err = encode("fmv.x.d a0, fa1", Xlen::Rv32).unwrap_err();
+        assert_eq!(err, Error::UnsupportedXlen);
+    }
+
+    // CSRs by name against llvm-mc, a number without a name prints as hex
+    #[test]
+    fn csr_names() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("csrrw x0, mstatus, x5", 0x30029073),
+                ("csrrs x10, mepc, x0", 0x34102573),
+                ("csrrwi x0, sstatus, 5", 0x1002d073),
+                ("csrrw x0, 0x7ff, x5", 0x7ff29073),
+                ("csrrs x10, 0x5ff, x0", 0x5ff02573),
+            ],
+        );
+        let d = encode("csrrw x0, 0x300, t0", Xlen::Rv32).unwrap();
+        assert_eq!(d.to_string(), "csrrw x0, mstatus, x5");
+        let d = encode("csrrw x0, 2047, t0", Xlen::Rv32).unwrap();
+        assert_eq!(d.to_string(), "csrrw x0, 0x7ff, x5");
+        let err = encode("csrrw x0, 0x1000, t0", Xlen::Rv32).unwrap_err();
+        assert_eq!(err, Error::ImmediateOutOfRange);
+    }
+
+    // the privileged instructions against llvm-mc, sfence.vma defaults both registers to x0
+    #[test]
+    fn privileged_instructions() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("mret", 0x30200073),
+                ("sret", 0x10200073),
+                ("wfi", 0x10500073),
+                ("sfence.vma x0, x0", 0x12000073),
+                ("sfence.vma x10, x11", 0x12b50073),
+                ("sfence.vma x10, x0", 0x12050073),
+            ],
+        );
+        for (text, word) in [("sfence.vma", 0x12000073), ("sfence.vma a0", 0x12050073)] {
+            assert_eq!(
+                encode(text, Xlen::Rv64).unwrap().instr.raw(),
+                word,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn bit_manipulation() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("sh1add x10, x11, x12", 0x20c5a533),
+                ("andn x10, x11, x12", 0x40c5f533),
+                ("clz x10, x11", 0x60059513),
+                ("cpop x10, x11", 0x60259513),
+                ("max x10, x11, x12", 0x0ac5e533),
+                ("sext.b x10, x11", 0x60459513),
+                ("zext.h x10, x11", 0x0805c533),
+                ("rev8 x10, x11", 0x6985d513),
+                ("orc.b x10, x11", 0x2875d513),
+                ("rori x10, x11, 5", 0x6055d513),
+                ("clmul x10, x11, x12", 0x0ac59533),
+                ("clmulr x10, x11, x12", 0x0ac5a533),
+                ("bset x10, x11, x12", 0x28c59533),
+                ("bexti x10, x11, 31", 0x49f5d513),
+                ("binvi x10, x11, 3", 0x68359513),
+            ],
+        );
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("add.uw x10, x11, x12", 0x08c5853b),
+                ("zext.h x10, x11", 0x0805c53b),
+                ("rev8 x10, x11", 0x6b85d513),
+                ("rori x10, x11, 63", 0x63f5d513),
+            ],
+        );
+        assert!(matches!(
+            encode("add.uw a0, a1, a2", Xlen::Rv32),
+            Err(Error::UnsupportedXlen)
+        ));
+        assert!(matches!(
+            encode("rori a0, a1, 32", Xlen::Rv32),
+            Err(Error::ImmediateOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn scalar_crypto() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("aes32esmi x10, x11, x12, 3", 0xe6c58533),
+                ("aes32dsi x10, x11, x12, 0", 0x2ac58533),
+                ("sha256sig0 x10, x11", 0x10259513),
+                ("sha256sum1 x10, x11", 0x10159513),
+                ("sha512sig0h x10, x11, x12", 0x5cc58533),
+                ("sm4ed x10, x11, x12, 2", 0xb0c58533),
+                ("sm3p0 x10, x11", 0x10859513),
+                ("pack x10, x11, x12", 0x08c5c533),
+                ("brev8 x10, x11", 0x6875d513),
+                ("zip x10, x11", 0x08f59513),
+                ("xperm8 x10, x11, x12", 0x28c5c533),
+            ],
+        );
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("aes64es x10, x11, x12", 0x32c58533),
+                ("aes64ks1i x10, x11, 10", 0x31a59513),
+                ("sha512sig0 x10, x11", 0x10659513),
+                ("packw x10, x11, x12", 0x08c5c53b),
+            ],
+        );
+        assert!(matches!(
+            encode("aes32esmi a0, a1, a2, 4", Xlen::Rv32),
+            Err(Error::ImmediateOutOfRange)
+        ));
+        assert!(matches!(
+            encode("aes64ks1i a0, a1, 11", Xlen::Rv64),
+            Err(Error::ImmediateOutOfRange)
+        ));
+        for text in ["aes32esmi a0, a1, a2, 3", "zip a0, a1"] {
+            assert!(
+                matches!(encode(text, Xlen::Rv64), Err(Error::UnsupportedXlen)),
+                "{}",
+                text
+            );
+        }
+        assert!(matches!(
+            encode("aes64es a0, a1, a2", Xlen::Rv32),
+            Err(Error::UnsupportedXlen)
+        ));
+    }
+
+    #[test]
+    fn vector() {
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("vsetvli x10, x11, e32, m4, ta, ma", 0x0d25f557),
+                ("vsetivli x10, 5, e8, mf2, tu, mu", 0xc072f557),
+                ("vsetvl x10, x11, x12", 0x80c5f557),
+                ("vfmacc.vf v1, f10, v2", 0xb22550d7),
+                ("vle8.v v1, (x10), v0.t", 0x00050087),
+                ("vle8.v v1, (x10)", 0x02050087),
+                ("vse32.v v4, (x11)", 0x0205e227),
+                ("vlse16.v v1, (x10), x11", 0x0ab55087),
+                ("vluxei32.v v1, (x10), v2", 0x06256087),
+                ("vadd.vv v1, v2, v3", 0x022180d7),
+                ("vadd.vx v1, v2, x10, v0.t", 0x002540d7),
+                ("vadd.vi v1, v2, -16", 0x022830d7),
+                ("vmul.vv v1, v2, v3", 0x9621a0d7),
+                ("vfadd.vv v1, v2, v3", 0x022190d7),
+                ("vmv.v.x v1, x10", 0x5e0540d7),
+                ("vredsum.vs v1, v2, v3", 0x0221a0d7),
+                ("vl1re8.v v1, (x10)", 0x02850087),
+            ],
+        );
+        // v0.t clears vm, bit 25
+        let masked = encode("vle8.v v1, (a0), v0.t", Xlen::Rv64).unwrap();
+        assert_eq!((masked.instr.raw() >> 25) & 1, 0);
+        assert!(matches!(
+            encode("vadd.vi v1, v2, 16", Xlen::Rv64),
+            Err(Error::ImmediateOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn vector_crypto_and_bit_manipulation() {
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("vaesef.vv v1, v2", 0xa221a0f7),
+                ("vaesef.vs v1, v2", 0xa621a0f7),
+                ("vsha2ms.vv v1, v2, v3", 0xb621a0f7),
+                ("vghsh.vv v1, v2, v3", 0xb221a0f7),
+                ("vclmul.vv v1, v2, v3", 0x3221a0d7),
+                ("vandn.vv v1, v2, v3", 0x062180d7),
+                ("vandn.vx v1, v2, x10, v0.t", 0x042540d7),
+                ("vbrev.v v1, v2", 0x4a2520d7),
+                ("vrol.vv v1, v2, v3", 0x562180d7),
+                ("vwsll.vv v1, v2, v3", 0xd62180d7),
+                ("vfwcvt.f.f.v v1, v2", 0x4a2610d7),
+                ("vfncvt.f.f.w v1, v2", 0x4a2a10d7),
+            ],
+        );
+    }
+
+    #[test]
+    fn conditional_and_cache_ops() {
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("czero.eqz x10, x11, x12", 0x0ec5d533),
+                ("czero.nez x10, x11, x12", 0x0ec5f533),
+                ("wrs.nto", 0x00d00073),
+                ("wrs.sto", 0x01d00073),
+                ("ntl.p1", 0x00200033),
+                ("ntl.all", 0x00500033),
+                ("cbo.clean (x10)", 0x0015200f),
+                ("cbo.flush (x10)", 0x0025200f),
+                ("cbo.inval (x10)", 0x0005200f),
+                ("cbo.zero (x10)", 0x0045200f),
+                ("prefetch.i 32(x10)", 0x02056013),
+                ("prefetch.r -64(x10)", 0xfc156013),
+                ("prefetch.w 32(x10)", 0x02356013),
+                ("prefetch.w 0(x10)", 0x00356013),
+            ],
+        );
+        // ori x0 is a prefetch hint only when imm[4:0] selects one
+        assert_eq!(
+            decode(0x00256013, Xlen::Rv64).unwrap().to_string(),
+            "ori x0, x10, 2"
+        );
+        assert_eq!(
+            encode("ori x0, a0, 35", Xlen::Rv64).unwrap().to_string(),
+            "prefetch.w 32(x10)"
+        );
+        assert!(matches!(
+            encode("prefetch.w 16(a0)", Xlen::Rv64),
+            Err(Error::ImmediateMisaligned)
+        ));
+    }
+
+    #[test]
+    fn compare_and_swap_and_subword_atomics() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("amocas.w x10, x12, (x11)", 0x28c5a52f),
+                ("amocas.d x10, x12, (x11)", 0x28c5b52f),
+                ("amocas.w.aqrl x10, x12, (x11)", 0x2ec5a52f),
+                ("amoadd.b x10, x12, (x11)", 0x00c5852f),
+                ("amoswap.h.aq x10, x12, (x11)", 0x0cc5952f),
+            ],
+        );
+        known_answers(
+            Xlen::Rv64,
+            &[
+                ("amocas.d x11, x12, (x11)", 0x28c5b5af),
+                ("amocas.q x10, x12, (x11)", 0x28c5c52f),
+            ],
+        );
+        // amocas.d takes even register pairs on RV32 and amocas.q on RV64
+        for text in ["amocas.d a1, a2, (a1)", "amocas.d a0, a3, (a1)"] {
+            assert!(
+                matches!(encode(text, Xlen::Rv32), Err(Error::InvalidRegisterPair)),
+                "{}",
+                text
+            );
+        }
+        assert!(matches!(
+            encode("amocas.q a1, a2, (a1)", Xlen::Rv64),
+            Err(Error::InvalidRegisterPair)
+        ));
+        assert!(matches!(
+            decode(0x28c5b5af, Xlen::Rv32),
+            Err(Error::InvalidRegisterPair)
+        ));
+        assert!(matches!(
+            encode("amocas.q a0, a2, (a1)", Xlen::Rv32),
+            Err(Error::UnsupportedXlen)
+        ));
+    }
+
+    #[test]
+    fn code_size_reduction() {
+        known_answers(
+            Xlen::Rv32,
+            &[
+                ("c.lbu x10, 1(x11)", 0x81c8),
+                ("c.lhu x10, 2(x11)", 0x85a8),
+                ("c.sb x10, 3(x11)", 0x89e8),
+                ("c.mul x10, x11", 0x9d4d),
+                ("c.zext.b x10", 0x9d61),
+                ("c.not x10", 0x9d75),
+            ],
+        );
+        // zcmp reuses the c.fsdsp encodings, so these decode with decode_zcmp
+        for (text, asm, word) in [
+            (
+                "cm.push {ra, s0-s3}, -32",
+                "cm.push {x1, x8-x9, x18-x19}, -32",
+                0xb882,
+            ),
+            (
+                "cm.popret {ra, s0-s1}, 16",
+                "cm.popret {x1, x8-x9}, 16",
+                0xbe62,
+            ),
+            ("cm.mvsa01 s0, s1", "cm.mvsa01 x8, x9", 0xac26),
+            ("cm.mva01s s0, s1", "cm.mva01s x8, x9", 0xac66),
+            ("cm.jt 5", "cm.jt 5", 0xa016),
+            ("cm.jalt 32", "cm.jalt 32", 0xa082),
+        ] {
+            assert_eq!(
+                encode(text, Xlen::Rv32).unwrap().instr.raw(),
+                word,
+                "{}",
+                text
+            );
+            assert_eq!(
+                decode_zcmp(word, Xlen::Rv32).unwrap().to_string(),
+                asm,
+                "{:#06x}",
+                word
+            );
+        }
+        assert_eq!(decode(0xb882, Xlen::Rv32).unwrap().mnemonic, "c.fsdsp");
+        // five registers take 40 bytes on RV64, so -32 is an RV32-only adjustment
+        assert!(matches!(
+            encode("cm.push {ra, s0-s3}, -32", Xlen::Rv64),
+            Err(Error::UnsupportedXlen)
+        ));
+        assert!(matches!(
+            encode("cm.push {ra, s0-s3}, -16", Xlen::Rv32),
+            Err(Error::ImmediateOutOfRange)
+        ));
+        assert!(matches!(
+            encode("cm.jalt 31", Xlen::Rv32),
+            Err(Error::ImmediateOutOfRange)
+        ));
+        assert!(matches!(
+            encode("cm.jt 32", Xlen::Rv32),
+            Err(Error::ImmediateOutOfRange)
+        ));
+    }
 }
@@ -1,6 +1,8 @@
-pub type Result<T> = std::result::Result<T, Error>;
+use core::fmt;
 
-#[derive(Debug)]
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Error {
     InvalidOpcode,
     UnknownInstruction,
@@ -9,4 +11,33 @@ pub enum Error {
     ImmediateOutOfRange,
     InvalidImmediate,
     ImmediateMisaligned,
+    UnsupportedXlen,
+    InvalidRoundingMode,
+    MultipleInstructions,
+    InvalidVectorType,
+    InvalidRegisterPair,
+    InvalidDefinition,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Error::InvalidOpcode => "invalid opcode",
+            Error::UnknownInstruction => "unknown instruction",
+            Error::InvalidFormat => "invalid instruction format",
+            Error::InvalidRegister => "invalid register",
+            Error::ImmediateOutOfRange => "immediate out of range",
+            Error::InvalidImmediate => "invalid immediate",
+            Error::ImmediateMisaligned => "misaligned immediate",
+            Error::UnsupportedXlen => "instruction not supported for this XLEN",
+            Error::InvalidRoundingMode => "invalid rounding mode",
+            Error::MultipleInstructions => "expands to more than one instruction",
+            Error::InvalidVectorType => "invalid vector type",
+            Error::InvalidRegisterPair => "register pair must start at an even register",
+            Error::InvalidDefinition => "invalid custom instruction definition",
+        };
+        write!(f, "{}", msg)
+    }
 }
+
+impl core::error::Error for Error {}
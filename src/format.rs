@@ -1,9 +1,18 @@
+use crate::operand::Operand;
 use crate::util::*;
 use bitfield::bitfield;
-use colored::*;
+use core::fmt;
+use core::ops::Deref;
 
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Xlen {
+    #[default]
+    Rv32,
+    Rv64,
+}
+
+#[allow(dead_code, clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy)]
 pub enum Instruction {
     RType(RType),
     IType(IType),
@@ -12,6 +21,28 @@ pub enum Instruction {
     UType(UType),
     JType(JType),
     CSRType(CSRType),
+    Fence(FenceType),
+    Amo(AMOType),
+    R4Type(R4Type),
+    ByteSelect(ByteSelectType),
+    Vector(VectorType),
+    VectorMem(VectorMemType),
+    CRType(CRType),
+    CIType(CIType),
+    CSSType(CSSType),
+    CIWType(CIWType),
+    CLType(CLType),
+    CSType(CSType),
+    CAType(CAType),
+    CBType(CBType),
+    CJType(CJType),
+    CLBType(CLBType),
+    CSBType(CSBType),
+    CUType(CUType),
+    CMPPType(CMPPType),
+    CMMVType(CMMVType),
+    CMJTType(CMJTType),
+    Custom(CustomType),
 }
 
 bitfield! {
@@ -27,6 +58,20 @@ bitfield! {
     pub opcode, set_opcode: 6, 0;
 }
 
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct R4Type(u32);
+    impl Debug;
+    u32;
+    pub rs3, set_rs3: 31, 27;
+    pub fmt, set_fmt: 26, 25;
+    pub rs2, set_rs2: 24, 20;
+    pub rs1, set_rs1: 19, 15;
+    pub rm, set_rm: 14, 12;
+    pub rd, set_rd: 11, 7;
+    pub opcode, set_opcode: 6, 0;
+}
+
 bitfield! {
     #[derive(Clone, Copy)]
     pub struct IType(u32);
@@ -100,255 +145,594 @@ bitfield! {
     #[derive(Clone, Copy)]
     pub struct FenceType(u32);
     impl Debug;
-    pub pred, set_pred: 31, 24;
+    pub fm, set_fm: 31, 28;
+    pub pred, set_pred: 27, 24;
     pub succ, set_succ: 23, 20;
+    pub rs1, set_rs1: 19, 15;
     pub funct3, set_funct3: 14, 12;
     pub rd, set_rd: 11, 7;
     pub opcode, set_opcode: 6, 0;
 }
 
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct AMOType(u32);
+    impl Debug;
+    u32;
+    pub funct5, set_funct5: 31, 27;
+    pub aq, set_aq: 26;
+    pub rl, set_rl: 25;
+    pub rs2, set_rs2: 24, 20;
+    pub rs1, set_rs1: 19, 15;
+    pub funct3, set_funct3: 14, 12;
+    pub rd, set_rd: 11, 7;
+    pub opcode, set_opcode: 6, 0;
+}
+
+// the AES and SM4 round instructions of scalar crypto, bs picks the byte of rs2
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct ByteSelectType(u32);
+    impl Debug;
+    u32;
+    pub bs, set_bs: 31, 30;
+    pub funct5, set_funct5: 29, 25;
+    pub rs2, set_rs2: 24, 20;
+    pub rs1, set_rs1: 19, 15;
+    pub funct3, set_funct3: 14, 12;
+    pub rd, set_rd: 11, 7;
+    pub opcode, set_opcode: 6, 0;
+}
+
+// OP-V: vs1 holds rs1 or a 5-bit immediate depending on funct3, vm is 0 for masked
+// operations. vsetvli, vsetivli and vsetvl put their vtype and AVL operands in place of
+// funct6, vm, vs2 and vs1
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct VectorType(u32);
+    impl Debug;
+    u32;
+    pub funct6, set_funct6: 31, 26;
+    pub vm, set_vm: 25;
+    pub vs2, set_vs2: 24, 20;
+    pub vs1, set_vs1: 19, 15;
+    pub funct3, set_funct3: 14, 12;
+    pub vd, set_vd: 11, 7;
+    pub opcode, set_opcode: 6, 0;
+}
+
+// vector loads and stores in LOAD-FP and STORE-FP: nf is the segment count minus one,
+// mop the addressing mode, and the rs2 field a stride register, an index vector or the
+// lumop/sumop variant of unit-stride accesses. vd is vs3 for stores
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct VectorMemType(u32);
+    impl Debug;
+    u32;
+    pub nf, set_nf: 31, 29;
+    pub mew, set_mew: 28;
+    pub mop, set_mop: 27, 26;
+    pub vm, set_vm: 25;
+    pub rs2, set_rs2: 24, 20;
+    pub rs1, set_rs1: 19, 15;
+    pub width, set_width: 14, 12;
+    pub vd, set_vd: 11, 7;
+    pub opcode, set_opcode: 6, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CRType(u16);
+    impl Debug;
+    u16;
+    pub funct4, set_funct4: 15, 12;
+    pub rd, set_rd: 11, 7;
+    pub rs2, set_rs2: 6, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CIType(u16);
+    impl Debug;
+    u16;
+    pub funct3, set_funct3: 15, 13;
+    pub imm12, set_imm12: 12, 12;
+    pub rd, set_rd: 11, 7;
+    pub imm6_2, set_imm6_2: 6, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CSSType(u16);
+    impl Debug;
+    u16;
+    pub funct3, set_funct3: 15, 13;
+    pub imm, set_imm: 12, 7;
+    pub rs2, set_rs2: 6, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CIWType(u16);
+    impl Debug;
+    u16;
+    pub funct3, set_funct3: 15, 13;
+    pub imm, set_imm: 12, 5;
+    pub rd, set_rd: 4, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CLType(u16);
+    impl Debug;
+    u16;
+    pub funct3, set_funct3: 15, 13;
+    pub imm12_10, set_imm12_10: 12, 10;
+    pub rs1, set_rs1: 9, 7;
+    pub imm6_5, set_imm6_5: 6, 5;
+    pub rd, set_rd: 4, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CSType(u16);
+    impl Debug;
+    u16;
+    pub funct3, set_funct3: 15, 13;
+    pub imm12_10, set_imm12_10: 12, 10;
+    pub rs1, set_rs1: 9, 7;
+    pub imm6_5, set_imm6_5: 6, 5;
+    pub rs2, set_rs2: 4, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CAType(u16);
+    impl Debug;
+    u16;
+    pub funct6, set_funct6: 15, 10;
+    pub rd, set_rd: 9, 7;
+    pub funct2, set_funct2: 6, 5;
+    pub rs2, set_rs2: 4, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CBType(u16);
+    impl Debug;
+    u16;
+    pub funct3, set_funct3: 15, 13;
+    pub offset12_10, set_offset12_10: 12, 10;
+    pub rs1, set_rs1: 9, 7;
+    pub offset6_2, set_offset6_2: 6, 2;
+    pub op, set_op: 1, 0;
+}
+
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CJType(u16);
+    impl Debug;
+    u16;
+    pub funct3, set_funct3: 15, 13;
+    pub target, set_target: 12, 2;
+    pub op, set_op: 1, 0;
+}
+
+// the Zcb byte and halfword loads, bit 6 is uimm[0] of c.lbu and tells c.lh from c.lhu
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CLBType(u16);
+    impl Debug;
+    u16;
+    pub funct6, set_funct6: 15, 10;
+    pub rs1, set_rs1: 9, 7;
+    pub imm, set_imm: 6, 5;
+    pub rd, set_rd: 4, 2;
+    pub op, set_op: 1, 0;
+}
+
+// the Zcb byte and halfword stores, laid out like the loads
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CSBType(u16);
+    impl Debug;
+    u16;
+    pub funct6, set_funct6: 15, 10;
+    pub rs1, set_rs1: 9, 7;
+    pub imm, set_imm: 6, 5;
+    pub rs2, set_rs2: 4, 2;
+    pub op, set_op: 1, 0;
+}
+
+// the Zcb unary ops on rd', funct5 picks the operation
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CUType(u16);
+    impl Debug;
+    u16;
+    pub funct6, set_funct6: 15, 10;
+    pub rd, set_rd: 9, 7;
+    pub funct5, set_funct5: 6, 2;
+    pub op, set_op: 1, 0;
+}
+
+// Zcmp push and pop: rlist picks ra and s0 up to s11, spimm adds 16-byte steps to the
+// stack adjustment the list needs
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CMPPType(u16);
+    impl Debug;
+    u16;
+    pub funct6, set_funct6: 15, 10;
+    pub funct2, set_funct2: 9, 8;
+    pub rlist, set_rlist: 7, 4;
+    pub spimm, set_spimm: 3, 2;
+    pub op, set_op: 1, 0;
+}
+
+// Zcmp moves between a0/a1 and two of s0-s7
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CMMVType(u16);
+    impl Debug;
+    u16;
+    pub funct6, set_funct6: 15, 10;
+    pub r1s, set_r1s: 9, 7;
+    pub funct2, set_funct2: 6, 5;
+    pub r2s, set_r2s: 4, 2;
+    pub op, set_op: 1, 0;
+}
+
+// Zcmt table jumps, entries 0-31 are cm.jt and 32-255 cm.jalt
+bitfield! {
+    #[derive(Clone, Copy)]
+    pub struct CMJTType(u16);
+    impl Debug;
+    u16;
+    pub funct6, set_funct6: 15, 10;
+    pub index, set_index: 9, 2;
+    pub op, set_op: 1, 0;
+}
+
+// an instruction of the custom-0 to custom-3 opcodes. It carries the field layout of its
+// definition, so it prints without the table it was decoded with
+#[derive(Debug, Clone, Copy)]
+pub struct CustomType {
+    pub word: u32,
+    // bit n is set where a field of the definition has its lowest bit
+    pub field_lsbs: u32,
+    // the lowest bit of the field of each operand, in assembly order
+    pub operand_lsbs: [u8; MAX_OPERANDS],
+}
+
+impl Instruction {
+    // the instruction word, compressed instructions sit in the low 16 bits
+    pub fn raw(&self) -> u32 {
+        match self {
+            Instruction::RType(r) => r.0,
+            Instruction::IType(i) => i.0,
+            Instruction::SType(s) => s.0,
+            Instruction::BType(b) => b.0,
+            Instruction::UType(u) => u.0,
+            Instruction::JType(j) => j.0,
+            Instruction::CSRType(c) => c.0,
+            Instruction::Fence(f) => f.0,
+            Instruction::Amo(a) => a.0,
+            Instruction::R4Type(r) => r.0,
+            Instruction::ByteSelect(b) => b.0,
+            Instruction::Vector(v) => v.0,
+            Instruction::VectorMem(v) => v.0,
+            Instruction::CRType(c) => c.0 as u32,
+            Instruction::CIType(c) => c.0 as u32,
+            Instruction::CSSType(c) => c.0 as u32,
+            Instruction::CIWType(c) => c.0 as u32,
+            Instruction::CLType(c) => c.0 as u32,
+            Instruction::CSType(c) => c.0 as u32,
+            Instruction::CAType(c) => c.0 as u32,
+            Instruction::CBType(c) => c.0 as u32,
+            Instruction::CJType(c) => c.0 as u32,
+            Instruction::CLBType(c) => c.0 as u32,
+            Instruction::CSBType(c) => c.0 as u32,
+            Instruction::CUType(c) => c.0 as u32,
+            Instruction::CMPPType(c) => c.0 as u32,
+            Instruction::CMMVType(c) => c.0 as u32,
+            Instruction::CMJTType(c) => c.0 as u32,
+            Instruction::Custom(c) => c.word,
+        }
+    }
+}
+
+// immediate bit order of the scrambled RVC immediate fields, msb first
+pub const CIW_ADDI4SPN: [u32; 8] = [5, 4, 9, 8, 7, 6, 2, 3];
+pub const CL_WORD_12_10: [u32; 3] = [5, 4, 3];
+pub const CL_WORD_6_5: [u32; 2] = [2, 6];
+pub const CL_DOUBLE_12_10: [u32; 3] = [5, 4, 3];
+pub const CL_DOUBLE_6_5: [u32; 2] = [7, 6];
+pub const CI_IMM_12: [u32; 1] = [5];
+pub const CI_IMM_6_2: [u32; 5] = [4, 3, 2, 1, 0];
+pub const CI_ADDI16SP_12: [u32; 1] = [9];
+pub const CI_ADDI16SP_6_2: [u32; 5] = [4, 6, 8, 7, 5];
+pub const CI_LWSP_6_2: [u32; 5] = [4, 3, 2, 7, 6];
+pub const CI_LDSP_6_2: [u32; 5] = [4, 3, 8, 7, 6];
+pub const CSS_SWSP: [u32; 6] = [5, 4, 3, 2, 7, 6];
+pub const CSS_SDSP: [u32; 6] = [5, 4, 3, 8, 7, 6];
+pub const CB_BRANCH_12_10: [u32; 3] = [8, 4, 3];
+pub const CB_BRANCH_6_2: [u32; 5] = [7, 6, 2, 1, 5];
+pub const CJ_TARGET: [u32; 11] = [11, 4, 9, 8, 10, 6, 7, 3, 2, 1, 5];
+
+// longest built-in mnemonics are the vector narrowing conversions, e.g. `vfncvt.rtz.xu.f.w`
+pub(crate) const MNEMONIC_LEN: usize = 24;
+pub(crate) const MAX_OPERANDS: usize = 6;
+
+// mnemonic stored inline, decoding never allocates
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mnemonic {
+    buf: [u8; MNEMONIC_LEN],
+    len: u8,
+}
+
+// operand list stored inline, in assembly order
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Operands {
+    ops: [Operand; MAX_OPERANDS],
+    len: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct EncodedInstruction {
     pub instr: Instruction,
-    pub mnemonic: String,
-    pub operands: Vec<String>,
-}
-
-fn format_r_type(d: &EncodedInstruction, r: &RType) -> (String, String, String, String) {
-    let operands = &d.operands;
-    let abi_operands = operands_to_abi(operands);
-    let instr = format!(
-        "{} {}, {}, {}",
-        d.mnemonic.red().bold(),
-        operands[0].green(),
-        operands[1].yellow(),
-        operands[2].blue(),
-    );
-    let abi_instr = format!(
-        "{} {}, {}, {}",
-        d.mnemonic.red().bold(),
-        abi_operands[0].green(),
-        abi_operands[1].yellow(),
-        abi_operands[2].blue(),
-    );
-    let fields = [
-        format!("{:07b}", r.funct7()).red().to_string(),
-        format!("{:05b}", r.rs2()).blue().to_string(),
-        format!("{:05b}", r.rs1()).yellow().to_string(),
-        format!("{:03b}", r.funct3()).red().to_string(),
-        format!("{:05b}", r.rd()).green().to_string(),
-        format!("{:07b}", r.opcode()).red().to_string(),
-    ];
-    let bits = fields.join(" ");
-    let hex = format!("0x{:08x}", r.0).bold().to_string();
-
-    return (instr, abi_instr, bits, hex);
-}
-
-fn format_i_type(d: &EncodedInstruction, i: &IType) -> (String, String, String, String) {
-    let operands = &d.operands;
-    let abi_operands = operands_to_abi(operands);
-    let hex = format!("0x{:08x}", i.0).bold().to_string();
-    let instr;
-    let abi_instr;
-    if ["lb", "lh", "lw", "lbu", "lhu", "jalr"].contains(&d.mnemonic.as_str()) {
-        instr = format!(
-            "{} {}, {}({})",
-            d.mnemonic.red().bold(),
-            operands[0].green(),
-            operands[1].blue(),
-            operands[2].yellow(),
-        );
-        abi_instr = format!(
-            "{} {}, {}({})",
-            d.mnemonic.red().bold(),
-            abi_operands[0].green(),
-            abi_operands[1].blue(),
-            abi_operands[2].yellow(),
-        );
-    } else {
-        instr = format!(
-            "{} {}, {}, {}",
-            d.mnemonic.red().bold(),
-            operands[0].green(),
-            operands[1].yellow(),
-            operands[2].blue(),
-        );
-        abi_instr = format!(
-            "{} {}, {}, {}",
-            d.mnemonic.red().bold(),
-            abi_operands[0].green(),
-            abi_operands[1].yellow(),
-            abi_operands[2].blue(),
-        );
+    pub mnemonic: Mnemonic,
+    pub operands: Operands,
+    // the XLEN the word was decoded for, some encodings mean different things per XLEN
+    pub xlen: Xlen,
+}
+
+impl Mnemonic {
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl From<&str> for Mnemonic {
+    fn from(s: &str) -> Self {
+        let mut m = Mnemonic::default();
+        fmt::Write::write_str(&mut m, s).expect("mnemonic too long");
+        m
+    }
+}
+
+// only whole strings are appended, so the buffer always holds valid UTF-8
+impl fmt::Write for Mnemonic {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let start = self.len as usize;
+        let end = start + s.len();
+        if end > MNEMONIC_LEN {
+            return Err(fmt::Error);
+        }
+        self.buf[start..end].copy_from_slice(s.as_bytes());
+        self.len = end as u8;
+        Ok(())
+    }
+}
+
+impl Deref for Mnemonic {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for Mnemonic {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Mnemonic {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Operands {
+    pub fn new() -> Self {
+        Operands {
+            ops: [Operand::imm(0); MAX_OPERANDS],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, op: Operand) {
+        self.ops[self.len as usize] = op;
+        self.len += 1;
+    }
+}
+
+impl Default for Operands {
+    fn default() -> Self {
+        Operands::new()
+    }
+}
+
+impl<const N: usize> From<[Operand; N]> for Operands {
+    fn from(ops: [Operand; N]) -> Self {
+        ops.into_iter().collect()
+    }
+}
+
+impl FromIterator<Operand> for Operands {
+    fn from_iter<I: IntoIterator<Item = Operand>>(iter: I) -> Self {
+        let mut ops = Operands::new();
+        for op in iter {
+            ops.push(op);
+        }
+        ops
+    }
+}
+
+impl Deref for Operands {
+    type Target = [Operand];
+
+    fn deref(&self) -> &[Operand] {
+        &self.ops[..self.len as usize]
+    }
+}
+
+impl fmt::Debug for Operands {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl EncodedInstruction {
+    pub fn is_compressed(&self) -> bool {
+        matches!(
+            self.instr,
+            Instruction::CRType(_)
+                | Instruction::CIType(_)
+                | Instruction::CSSType(_)
+                | Instruction::CIWType(_)
+                | Instruction::CLType(_)
+                | Instruction::CSType(_)
+                | Instruction::CAType(_)
+                | Instruction::CBType(_)
+                | Instruction::CJType(_)
+                | Instruction::CLBType(_)
+                | Instruction::CSBType(_)
+                | Instruction::CUType(_)
+                | Instruction::CMPPType(_)
+                | Instruction::CMMVType(_)
+                | Instruction::CMJTType(_)
+        )
+    }
+
+    // loads, stores and jalr write their address operands as `offset(base)`
+    pub(crate) fn is_memory_access(&self) -> bool {
+        match self.instr {
+            Instruction::IType(_) => {
+                self.mnemonic.starts_with('l')
+                    || is_fp_load_store(&self.mnemonic)
+                    || self.mnemonic == "jalr"
+            }
+            Instruction::CIType(_) => {
+                self.mnemonic.ends_with("sp") && self.mnemonic != "c.addi16sp"
+            }
+            Instruction::SType(_)
+            | Instruction::CSSType(_)
+            | Instruction::CLType(_)
+            | Instruction::CSType(_)
+            | Instruction::CLBType(_)
+            | Instruction::CSBType(_) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn is_hypervisor_access(&self) -> bool {
+        self.mnemonic.starts_with("hlv") || self.mnemonic.starts_with("hsv")
+    }
+
+    // the same assembly with ABI register names, e.g. `addi s0, s0, 1`
+    pub fn abi(&self) -> AbiInstruction<'_> {
+        AbiInstruction(self)
+    }
+
+    fn write_asm(&self, f: &mut fmt::Formatter<'_>, abi: bool) -> fmt::Result {
+        let write_op = |f: &mut fmt::Formatter<'_>, op: &Operand| {
+            if abi {
+                write!(f, "{}", op.abi())
+            } else {
+                write!(f, "{}", op)
+            }
+        };
+        f.write_str(&self.mnemonic)?;
+        let (ops, base) = match (&self.instr, &self.operands[..]) {
+            // atomics, hypervisor loads and stores and the cache-block ops take their address
+            // register alone in parentheses
+            (Instruction::Amo(_), [ops @ .., base]) => (ops, Some(base)),
+            (Instruction::RType(_), [ops @ .., base]) if self.is_hypervisor_access() => {
+                (ops, Some(base))
+            }
+            (Instruction::IType(_), [ops @ .., base]) if self.mnemonic.starts_with("cbo.") => {
+                (ops, Some(base))
+            }
+            (_, ops) => (ops, None),
+        };
+        match ops {
+            [] => {}
+            [reg, offset, base] if self.is_memory_access() => {
+                f.write_str(" ")?;
+                write_op(f, reg)?;
+                write!(f, ", {}(", offset)?;
+                write_op(f, base)?;
+                f.write_str(")")?;
+            }
+            // the prefetch hints have an address but no register to load into
+            [offset, base] if matches!(self.instr, Instruction::SType(_)) => {
+                write!(f, " {}(", offset)?;
+                write_op(f, base)?;
+                f.write_str(")")?;
+            }
+            // vector loads and stores have no offset, the stride, index or mask follow
+            [reg, base, rest @ ..] if matches!(self.instr, Instruction::VectorMem(_)) => {
+                f.write_str(" ")?;
+                write_op(f, reg)?;
+                f.write_str(", (")?;
+                write_op(f, base)?;
+                f.write_str(")")?;
+                for op in rest {
+                    f.write_str(", ")?;
+                    write_op(f, op)?;
+                }
+            }
+            [first, rest @ ..] => {
+                f.write_str(" ")?;
+                write_op(f, first)?;
+                for op in rest {
+                    f.write_str(", ")?;
+                    write_op(f, op)?;
+                }
+            }
+        }
+        if let Some(base) = base {
+            f.write_str(if ops.is_empty() { " (" } else { ", (" })?;
+            write_op(f, base)?;
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+// plain canonical assembly, `encode` reads it back to the same instruction word
+impl fmt::Display for EncodedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_asm(f, false)
+    }
+}
+
+pub struct AbiInstruction<'a>(&'a EncodedInstruction);
+
+impl fmt::Display for AbiInstruction<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write_asm(f, true)
     }
-    let fields = [
-        format!("{:012b}", i.imm()).blue().to_string(),
-        format!("{:05b}", i.rs1()).yellow().to_string(),
-        format!("{:03b}", i.funct3()).red().to_string(),
-        format!("{:05b}", i.rd()).green().to_string(),
-        format!("{:07b}", i.opcode()).red().to_string(),
-    ];
-    let bits = fields.join(" ");
-    return (instr, abi_instr, bits, hex);
-}
-
-fn format_s_type(d: &EncodedInstruction, s: &SType) -> (String, String, String, String) {
-    let operands = &d.operands;
-    let abi_operands = operands_to_abi(operands);
-    let instr = format!(
-        "{} {}, {}({})",
-        d.mnemonic.red().bold(),
-        operands[0].green(),
-        operands[1].blue(),
-        operands[2].yellow(),
-    );
-    let abi_instr = format!(
-        "{} {}, {}({})",
-        d.mnemonic.red().bold(),
-        abi_operands[0].green(),
-        abi_operands[1].blue(),
-        abi_operands[2].yellow(),
-    );
-    let fields = [
-        format!("{:07b}", s.imm11_5()).blue().to_string(),
-        format!("{:05b}", s.rs2()).green().to_string(),
-        format!("{:05b}", s.rs1()).yellow().to_string(),
-        format!("{:03b}", s.funct3()).red().to_string(),
-        format!("{:05b}", s.imm4_0()).blue().to_string(),
-        format!("{:07b}", s.opcode()).red().to_string(),
-    ];
-    let bits = fields.join(" ");
-    let hex = format!("0x{:08x}", s.0).bold().to_string();
-    return (instr, abi_instr, bits, hex);
-}
-
-fn format_b_type(d: &EncodedInstruction, b: &BType) -> (String, String, String, String) {
-    let operands = &d.operands;
-    let abi_operands = operands_to_abi(operands);
-    let instr = format!(
-        "{} {}, {}, {}",
-        d.mnemonic.red().bold(),
-        operands[0].green(),
-        operands[1].yellow(),
-        operands[2].blue(),
-    );
-    let abi_instr = format!(
-        "{} {}, {}, {}",
-        d.mnemonic.red().bold(),
-        abi_operands[0].green(),
-        abi_operands[1].yellow(),
-        abi_operands[2].blue(),
-    );
-    let fields = [
-        format!("{:01b}", b.imm12() as u32).blue().to_string(),
-        format!("{:06b}", b.imm10_5() as u32).blue().to_string(),
-        format!("{:05b}", b.rs2()).yellow().to_string(),
-        format!("{:05b}", b.rs1()).green().to_string(),
-        format!("{:03b}", b.funct3()).red().to_string(),
-        format!("{:04b}", b.imm4_1() as u32).blue().to_string(),
-        format!("{:01b}", b.imm11() as u32).blue().to_string(),
-        format!("{:07b}", b.opcode()).red().to_string(),
-    ];
-    let bits = fields.join(" ");
-    let hex = format!("0x{:08x}", b.0).bold().to_string();
-    return (instr, abi_instr, bits, hex);
-}
-
-fn format_u_type(d: &EncodedInstruction, u: &UType) -> (String, String, String, String) {
-    let operands = &d.operands;
-    let abi_operands = operands_to_abi(operands);
-    let instr = format!(
-        "{} {}, {}",
-        d.mnemonic.red().bold(),
-        operands[0].green(),
-        operands[1].blue(),
-    );
-    let abi_instr = format!(
-        "{} {}, {}",
-        d.mnemonic.red().bold(),
-        abi_operands[0].green(),
-        abi_operands[1].blue(),
-    );
-    let fields = [
-        format!("{:020b}", u.imm()).blue().to_string(),
-        format!("{:05b}", u.rd()).green().to_string(),
-        format!("{:07b}", u.opcode()).red().to_string(),
-    ];
-    let bits = fields.join(" ");
-    let hex = format!("0x{:08x}", u.0).bold().to_string();
-    return (instr, abi_instr, bits, hex);
-}
-
-fn format_j_type(d: &EncodedInstruction, j: &JType) -> (String, String, String, String) {
-    let operands = &d.operands;
-    let abi_operands = operands_to_abi(operands);
-    let instr = format!(
-        "{} {}, {}",
-        d.mnemonic.red().bold(),
-        operands[0].green(),
-        operands[1].blue(),
-    );
-    let abi_instr = format!(
-        "{} {}, {}",
-        d.mnemonic.red().bold(),
-        abi_operands[0].green(),
-        abi_operands[1].blue(),
-    );
-    let fields = [
-        format!("{:01b}", j.imm20() as u32).blue().to_string(),
-        format!("{:010b}", j.imm10_1() as u32).blue().to_string(),
-        format!("{:01b}", j.imm11() as u32).blue().to_string(),
-        format!("{:008b}", j.imm19_12() as u32).blue().to_string(),
-        format!("{:05b}", j.rd()).green().to_string(),
-        format!("{:07b}", j.opcode()).red().to_string(),
-    ];
-    let bits = fields.join(" ");
-    let hex = format!("0x{:08x}", j.0).bold().to_string();
-    return (instr, abi_instr, bits, hex);
-}
-
-fn format_csr_type(d: &EncodedInstruction, c: &CSRType) -> (String, String, String, String) {
-    let operands = &d.operands;
-    let abi_operands = operands_to_abi(operands);
-    let hex = format!("0x{:08x}", c.0).bold().to_string();
-    let instr = format!(
-        "{} {}, {}, {}",
-        d.mnemonic.red().bold(),
-        operands[0].green(),
-        operands[1].blue(),
-        operands[2].yellow(),
-    );
-    let abi_instr = format!(
-        "{} {}, {}, {}",
-        d.mnemonic.red().bold(),
-        abi_operands[0].green(),
-        abi_operands[1].blue(),
-        abi_operands[2].yellow(),
-    );
-    let fields = [
-        format!("{:012b}", c.csr()).blue().to_string(),
-        format!("{:05b}", c.rs1()).yellow().to_string(),
-        format!("{:03b}", c.funct3()).red().to_string(),
-        format!("{:05b}", c.rd()).green().to_string(),
-        format!("{:07b}", c.opcode()).red().to_string(),
-    ];
-    let bits = fields.join(" ");
-    (instr, abi_instr, bits, hex)
-}
-
-pub fn print_encoded_instruction(d: &EncodedInstruction) {
-    let out = match &d.instr {
-        Instruction::RType(r) => format_r_type(d, r),
-        Instruction::IType(i) => format_i_type(d, i),
-        Instruction::SType(s) => format_s_type(d, s),
-        Instruction::BType(b) => format_b_type(d, b),
-        Instruction::UType(u) => format_u_type(d, u),
-        Instruction::JType(j) => format_j_type(d, j),
-        Instruction::CSRType(c) => format_csr_type(d, c),
-    };
-
-    println!("ASM: {}", out.0);
-    println!("ABI: {}", out.1);
-    println!("BIN: {}", out.2);
-    println!("HEX: {}\n", out.3);
 }
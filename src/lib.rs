@@ -0,0 +1,44 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// formats a `Mnemonic` in place, like `format!` without the allocation
+macro_rules! mnemonic {
+    ($($arg:tt)*) => {{
+        let mut m = $crate::format::Mnemonic::default();
+        core::fmt::Write::write_fmt(&mut m, format_args!($($arg)*)).expect("mnemonic too long");
+        m
+    }};
+}
+
+pub mod alias;
+pub mod csr;
+#[cfg(feature = "std")]
+pub mod custom;
+pub mod decoder;
+#[cfg(feature = "std")]
+pub mod encoder;
+pub mod error;
+pub mod format;
+pub mod op;
+pub mod operand;
+#[cfg(feature = "std")]
+pub mod print;
+#[cfg(feature = "std")]
+pub mod pseudo;
+#[cfg(feature = "std")]
+pub mod rvc;
+mod util;
+mod vector;
+
+pub use alias::alias;
+pub use csr::{CsrName, Privilege};
+#[cfg(feature = "std")]
+pub use custom::CustomTable;
+pub use decoder::{decode, decode_zcmp};
+#[cfg(feature = "std")]
+pub use decoder::{decode_with, decode_zcmp_with};
+#[cfg(feature = "std")]
+pub use encoder::{assemble, assemble_with, encode, encode_with};
+pub use error::{Error, Result};
+pub use format::{EncodedInstruction, Instruction, Mnemonic, Operands, Xlen};
+pub use op::Op;
+pub use operand::{CsrAddr, FReg, FenceSet, Imm, Operand, Reg, RegList, RoundingMode, VReg, VType};
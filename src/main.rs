@@ -1,12 +1,72 @@
 use std::io::{self, Write};
 
-mod decoder;
-mod encoder;
-mod error;
-mod format;
-mod util;
+use rv_codec::{CustomTable, EncodedInstruction, Xlen, alias, decoder, encoder, print, rvc};
 
-pub fn run_cli() {
+struct Config {
+    xlen: Xlen,
+    // show pseudo-instructions like `ret` in place of the base instruction, as objdump does
+    aliases: bool,
+    // decode the c.fsdsp encodings as the Zcmp and Zcmt ops, for targets without Zcd
+    zcmp: bool,
+    // definition files of custom instructions
+    custom: Vec<String>,
+}
+
+fn parse_args() -> Config {
+    let mut config = Config {
+        xlen: Xlen::Rv32,
+        aliases: true,
+        zcmp: false,
+        custom: Vec::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rv32" => config.xlen = Xlen::Rv32,
+            "--rv64" => config.xlen = Xlen::Rv64,
+            "--aliases" => config.aliases = true,
+            "--no-aliases" => config.aliases = false,
+            "--zcmp" => config.zcmp = true,
+            "--custom" if args.len() > 0 => config.custom.extend(args.next()),
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                eprintln!(
+                    "Usage: rv-codec [--rv32 | --rv64] [--aliases | --no-aliases] [--zcmp] [--custom <file>]"
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    config
+}
+
+fn alias_of(config: &Config, instr: &EncodedInstruction) -> Option<EncodedInstruction> {
+    if config.aliases {
+        alias::alias(instr)
+    } else {
+        None
+    }
+}
+
+fn print_instruction(config: &Config, instr: &EncodedInstruction) {
+    print::print_encoded_instruction(instr, alias_of(config, instr).as_ref());
+    // the RVC line shows the other side of the compressed/base pair. The Zcmp and Zcmt ops
+    // stand for sequences and table jumps, they have no single base instruction
+    if instr.is_compressed() && !instr.mnemonic.starts_with("cm.") {
+        match rvc::expand(instr, config.xlen) {
+            Ok(expanded) => {
+                let alias = alias_of(config, &expanded);
+                print::print_related_instruction("RVC", &expanded, alias.as_ref())
+            }
+            Err(e) => println!("RVC: {:?}", e),
+        }
+    } else if let Some(compressed) = rvc::compress(instr, config.xlen) {
+        print::print_related_instruction("RVC", &compressed, None);
+    }
+    println!();
+}
+
+fn run_cli(config: &Config, custom: &CustomTable) {
     let mut input = String::new();
     loop {
         print!("Instruction: ");
@@ -25,25 +85,50 @@ pub fn run_cli() {
             break;
         }
 
-        if util::is_hex(clean_input) {
-            match u32::from_str_radix(&clean_input[2..], 16) {
-                Ok(hex) => match decoder::decode(hex) {
-                    Ok(instr) => {
-                        format::print_encoded_instruction(&instr);
+        if let Some(hex) = clean_input
+            .strip_prefix("0x")
+            .or_else(|| clean_input.strip_prefix("0X"))
+        {
+            match u32::from_str_radix(hex, 16) {
+                Ok(hex) => {
+                    let decoded = if config.zcmp {
+                        decoder::decode_zcmp_with(custom, hex, config.xlen)
+                    } else {
+                        decoder::decode_with(custom, hex, config.xlen)
+                    };
+                    match decoded {
+                        Ok(instr) => print_instruction(config, &instr),
+                        Err(e) => println!("Error decoding instruction: {:?}", e),
                     }
-                    Err(_) => println!("Error parsing hex input:"),
-                },
+                }
                 Err(_) => println!("Error parsing hex input:"),
             }
         } else {
-            match encoder::encode(clean_input) {
-                Ok(d) => format::print_encoded_instruction(&d),
+            match encoder::assemble_with(custom, clean_input, config.xlen) {
+                Ok(words) => words.iter().for_each(|d| print_instruction(config, d)),
                 Err(e) => println!("Error encoding instruction: {:?}", e),
             }
         }
     }
 }
 
+// the custom instructions of every definition file, loaded before any input is read
+fn load_custom(config: &Config) -> CustomTable {
+    let mut table = CustomTable::default();
+    for path in &config.custom {
+        let loaded = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| table.load(&text).map_err(|e| e.to_string()));
+        if let Err(e) = loaded {
+            eprintln!("Error loading {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+    table
+}
+
 fn main() {
-    run_cli()
+    let config = parse_args();
+    let custom = load_custom(&config);
+    run_cli(&config, &custom)
 }
@@ -0,0 +1,787 @@
+// the typed view of a decoded instruction: one variant per mnemonic with named operand
+// fields, e.g. `Op::Addi { rd: Reg(8), rs1: Reg(8), imm: 1 }`. Variants are named after
+// the mnemonic, `fcvt.s.w` is `FcvtSW` and `amoadd.w.aq` is `AmoaddWAq`
+use crate::format::{EncodedInstruction, Operands};
+use crate::operand::{
+    CsrAddr, FReg, FenceSet, Imm, Operand, Reg, RegList, RoundingMode, VReg, VType,
+};
+#[cfg(feature = "std")]
+use crate::{encoder::encode, error::Result, format::Xlen};
+
+// an operand field of a variant, taken from and put back into the operand list in
+// assembly order
+trait Field: Sized {
+    fn take(ops: &mut &[Operand]) -> Option<Self>;
+    fn put(self, ops: &mut Operands);
+}
+
+macro_rules! field {
+    ($ty:ty, $operand:ident) => {
+        impl Field for $ty {
+            fn take(ops: &mut &[Operand]) -> Option<Self> {
+                match *ops {
+                    [Operand::$operand(value), rest @ ..] => {
+                        *ops = rest;
+                        Some(*value)
+                    }
+                    _ => None,
+                }
+            }
+
+            fn put(self, ops: &mut Operands) {
+                ops.push(Operand::$operand(self))
+            }
+        }
+    };
+}
+
+field!(Reg, Reg);
+field!(FReg, FReg);
+field!(VReg, VReg);
+field!(Imm, Imm);
+field!(CsrAddr, Csr);
+field!(RoundingMode, RoundingMode);
+field!(FenceSet, FenceSet);
+field!(VType, VType);
+field!(RegList, RegList);
+
+impl Field for i32 {
+    fn take(ops: &mut &[Operand]) -> Option<Self> {
+        Imm::take(ops).map(|imm| imm.0)
+    }
+
+    fn put(self, ops: &mut Operands) {
+        Imm(self).put(ops)
+    }
+}
+
+// a rounding mode left out is the default of the instruction
+impl Field for Option<RoundingMode> {
+    fn take(ops: &mut &[Operand]) -> Option<Self> {
+        Some(RoundingMode::take(ops))
+    }
+
+    fn put(self, ops: &mut Operands) {
+        if let Some(rm) = self {
+            rm.put(ops)
+        }
+    }
+}
+
+// a trailing `v0.t`
+impl Field for bool {
+    fn take(ops: &mut &[Operand]) -> Option<Self> {
+        match *ops {
+            [Operand::VMask, rest @ ..] => {
+                *ops = rest;
+                Some(true)
+            }
+            _ => Some(false),
+        }
+    }
+
+    fn put(self, ops: &mut Operands) {
+        if self {
+            ops.push(Operand::VMask)
+        }
+    }
+}
+
+// builds a variant from the operand list, all of it has to be used
+macro_rules! take_fields {
+    ($ops:expr, $variant:ident { $($field:ident: $ty:ty),* }) => {{
+        let mut rest = $ops;
+        let op = Op::$variant { $($field: <$ty as Field>::take(&mut rest)?),* };
+        rest.is_empty().then_some(op)
+    }};
+}
+
+macro_rules! put_fields {
+    ($op:expr, $variant:ident { $($field:ident: $ty:ty),* }) => {{
+        let Op::$variant { $($field),* } = $op else {
+            unreachable!()
+        };
+        let mut ops = Operands::new();
+        $(Field::put($field, &mut ops);)*
+        ops
+    }};
+}
+
+macro_rules! ops {
+    (
+        () => [$($unit:ident = $unit_name:literal),* $(,)?]
+        $($fields:tt => [$($variant:ident = $name:literal),* $(,)?])*
+    ) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Op {
+            $($unit,)*
+            $($($variant $fields,)*)*
+        }
+
+        impl Op {
+            pub fn mnemonic(&self) -> &'static str {
+                match self {
+                    $(Op::$unit => $unit_name,)*
+                    $($(Op::$variant { .. } => $name,)*)*
+                }
+            }
+
+            // the operands in assembly order, as `decode` gives them
+            pub fn operands(&self) -> Operands {
+                match *self {
+                    $(Op::$unit => Operands::new(),)*
+                    $($(op @ Op::$variant { .. } => put_fields!(op, $variant $fields),)*)*
+                }
+            }
+
+            fn from_parts(mnemonic: &str, ops: &[Operand]) -> Option<Op> {
+                match mnemonic {
+                    $($unit_name => ops.is_empty().then_some(Op::$unit),)*
+                    $($($name => take_fields!(ops, $variant $fields),)*)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+ops! {
+    // no operands: the system instructions, fence variants and hints
+    () => [
+        CEbreak = "c.ebreak", CNop = "c.nop", CNtlAll = "c.ntl.all", CNtlP1 = "c.ntl.p1",
+        CNtlPall = "c.ntl.pall", CNtlS1 = "c.ntl.s1", CUnimp = "c.unimp", Dret = "dret", Ebreak = "ebreak",
+        Ecall = "ecall", FenceI = "fence.i", FenceTso = "fence.tso", Mnret = "mnret", Mret = "mret",
+        NtlAll = "ntl.all", NtlP1 = "ntl.p1", NtlPall = "ntl.pall", NtlS1 = "ntl.s1",
+        Pause = "pause", SfenceInvalIr = "sfence.inval.ir", SfenceWInval = "sfence.w.inval",
+        Sret = "sret", Uret = "uret", Wfi = "wfi", WrsNto = "wrs.nto", WrsSto = "wrs.sto",
+    ]
+    // rd, rs1, rs2: integer register ops of the base ISA, M, B, Zicond and scalar crypto
+    { rd: Reg, rs1: Reg, rs2: Reg } => [
+        Add = "add", AddUw = "add.uw", Addw = "addw", Aes64ds = "aes64ds", Aes64dsm = "aes64dsm",
+        Aes64es = "aes64es", Aes64esm = "aes64esm", Aes64ks2 = "aes64ks2", And = "and",
+        Andn = "andn", Bclr = "bclr", Bext = "bext", Binv = "binv", Bset = "bset", Clmul = "clmul",
+        Clmulh = "clmulh", Clmulr = "clmulr", CzeroEqz = "czero.eqz", CzeroNez = "czero.nez",
+        Div = "div", Divu = "divu", Divuw = "divuw", Divw = "divw", Max = "max", Maxu = "maxu",
+        Min = "min", Minu = "minu", Mul = "mul", Mulh = "mulh", Mulhsu = "mulhsu", Mulhu = "mulhu",
+        Mulw = "mulw", Or = "or", Orn = "orn", Pack = "pack", Packh = "packh", Packw = "packw",
+        Rem = "rem", Remu = "remu", Remuw = "remuw", Remw = "remw", Rol = "rol", Rolw = "rolw",
+        Ror = "ror", Rorw = "rorw", Sh1add = "sh1add", Sh1addUw = "sh1add.uw", Sh2add = "sh2add",
+        Sh2addUw = "sh2add.uw", Sh3add = "sh3add", Sh3addUw = "sh3add.uw",
+        Sha512sig0h = "sha512sig0h", Sha512sig0l = "sha512sig0l", Sha512sig1h = "sha512sig1h",
+        Sha512sig1l = "sha512sig1l", Sha512sum0r = "sha512sum0r", Sha512sum1r = "sha512sum1r",
+        Sll = "sll", Sllw = "sllw", Slt = "slt", Sltu = "sltu", Sra = "sra", Sraw = "sraw",
+        Srl = "srl", Srlw = "srlw", Sub = "sub", Subw = "subw", Vsetvl = "vsetvl", Xnor = "xnor",
+        Xor = "xor", Xperm4 = "xperm4", Xperm8 = "xperm8",
+    ]
+    // rd, rs1 and a 12-bit immediate
+    { rd: Reg, rs1: Reg, imm: i32 } => [
+        Addi = "addi", Addiw = "addiw", Andi = "andi", CAddi4spn = "c.addi4spn", Ori = "ori",
+        Slti = "slti", Sltiu = "sltiu", Xori = "xori",
+    ]
+    // shifts, rotates and single-bit ops by an immediate
+    { rd: Reg, rs1: Reg, shamt: i32 } => [
+        Bclri = "bclri", Bexti = "bexti", Binvi = "binvi", Bseti = "bseti", Rori = "rori",
+        Roriw = "roriw", Slli = "slli", SlliUw = "slli.uw", Slliw = "slliw", Srai = "srai",
+        Sraiw = "sraiw", Srli = "srli", Srliw = "srliw",
+    ]
+    { rd: Reg, rs1: Reg, rnum: i32 } => [
+        Aes64ks1i = "aes64ks1i",
+    ]
+    // the AES and SM4 rounds pick a byte of rs2
+    { rd: Reg, rs1: Reg, rs2: Reg, bs: i32 } => [
+        Aes32dsi = "aes32dsi", Aes32dsmi = "aes32dsmi", Aes32esi = "aes32esi",
+        Aes32esmi = "aes32esmi", Sm4ed = "sm4ed", Sm4ks = "sm4ks",
+    ]
+    // rd, rs1: bit-manipulation and hash functions, lr and the hypervisor loads
+    { rd: Reg, rs1: Reg } => [
+        Aes64im = "aes64im", Brev8 = "brev8", Clz = "clz", Clzw = "clzw", Cpop = "cpop",
+        Cpopw = "cpopw", Ctz = "ctz", Ctzw = "ctzw", HlvB = "hlv.b", HlvBu = "hlv.bu",
+        HlvD = "hlv.d", HlvH = "hlv.h", HlvHu = "hlv.hu", HlvW = "hlv.w", HlvWu = "hlv.wu",
+        HlvxHu = "hlvx.hu", HlvxWu = "hlvx.wu", LrD = "lr.d", LrDAq = "lr.d.aq",
+        LrDAqrl = "lr.d.aqrl", LrDRl = "lr.d.rl", LrW = "lr.w", LrWAq = "lr.w.aq",
+        LrWAqrl = "lr.w.aqrl", LrWRl = "lr.w.rl", OrcB = "orc.b", Rev8 = "rev8", SextB = "sext.b",
+        SextH = "sext.h", Sha256sig0 = "sha256sig0", Sha256sig1 = "sha256sig1",
+        Sha256sum0 = "sha256sum0", Sha256sum1 = "sha256sum1", Sha512sig0 = "sha512sig0",
+        Sha512sig1 = "sha512sig1", Sha512sum0 = "sha512sum0", Sha512sum1 = "sha512sum1",
+        Sm3p0 = "sm3p0", Sm3p1 = "sm3p1", Unzip = "unzip", ZextH = "zext.h", Zip = "zip",
+    ]
+    // rd and an immediate
+    { rd: Reg, imm: i32 } => [
+        Auipc = "auipc", CAddi = "c.addi", CAddi16sp = "c.addi16sp", CAddiw = "c.addiw",
+        CAndi = "c.andi", CLi = "c.li", CLui = "c.lui", Lui = "lui",
+    ]
+    { rd: Reg, offset: i32 } => [
+        Jal = "jal",
+    ]
+    { rs1: Reg, rs2: Reg, offset: i32 } => [
+        Beq = "beq", Bge = "bge", Bgeu = "bgeu", Blt = "blt", Bltu = "bltu", Bne = "bne",
+    ]
+    // loads and jalr, written `rd, offset(rs1)`
+    { rd: Reg, offset: i32, rs1: Reg } => [
+        CLbu = "c.lbu", CLd = "c.ld", CLdsp = "c.ldsp", CLh = "c.lh", CLhu = "c.lhu", CLw = "c.lw",
+        CLwsp = "c.lwsp", Jalr = "jalr", Lb = "lb", Lbu = "lbu", Ld = "ld", Lh = "lh", Lhu = "lhu",
+        Lw = "lw", Lwu = "lwu",
+    ]
+    // stores, written `rs2, offset(rs1)`
+    { rs2: Reg, offset: i32, rs1: Reg } => [
+        CSb = "c.sb", CSd = "c.sd", CSdsp = "c.sdsp", CSh = "c.sh", CSw = "c.sw", CSwsp = "c.swsp",
+        Sb = "sb", Sd = "sd", Sh = "sh", Sw = "sw",
+    ]
+    // atomics, written `rd, rs2, (rs1)`
+    { rd: Reg, rs2: Reg, rs1: Reg } => [
+        AmoaddB = "amoadd.b", AmoaddBAq = "amoadd.b.aq", AmoaddBAqrl = "amoadd.b.aqrl",
+        AmoaddBRl = "amoadd.b.rl", AmoaddD = "amoadd.d", AmoaddDAq = "amoadd.d.aq",
+        AmoaddDAqrl = "amoadd.d.aqrl", AmoaddDRl = "amoadd.d.rl", AmoaddH = "amoadd.h",
+        AmoaddHAq = "amoadd.h.aq", AmoaddHAqrl = "amoadd.h.aqrl", AmoaddHRl = "amoadd.h.rl",
+        AmoaddW = "amoadd.w", AmoaddWAq = "amoadd.w.aq", AmoaddWAqrl = "amoadd.w.aqrl",
+        AmoaddWRl = "amoadd.w.rl", AmoandB = "amoand.b", AmoandBAq = "amoand.b.aq",
+        AmoandBAqrl = "amoand.b.aqrl", AmoandBRl = "amoand.b.rl", AmoandD = "amoand.d",
+        AmoandDAq = "amoand.d.aq", AmoandDAqrl = "amoand.d.aqrl", AmoandDRl = "amoand.d.rl",
+        AmoandH = "amoand.h", AmoandHAq = "amoand.h.aq", AmoandHAqrl = "amoand.h.aqrl",
+        AmoandHRl = "amoand.h.rl", AmoandW = "amoand.w", AmoandWAq = "amoand.w.aq",
+        AmoandWAqrl = "amoand.w.aqrl", AmoandWRl = "amoand.w.rl", AmocasB = "amocas.b",
+        AmocasBAq = "amocas.b.aq", AmocasBAqrl = "amocas.b.aqrl", AmocasBRl = "amocas.b.rl",
+        AmocasD = "amocas.d", AmocasDAq = "amocas.d.aq", AmocasDAqrl = "amocas.d.aqrl",
+        AmocasDRl = "amocas.d.rl", AmocasH = "amocas.h", AmocasHAq = "amocas.h.aq",
+        AmocasHAqrl = "amocas.h.aqrl", AmocasHRl = "amocas.h.rl", AmocasQ = "amocas.q",
+        AmocasQAq = "amocas.q.aq", AmocasQAqrl = "amocas.q.aqrl", AmocasQRl = "amocas.q.rl",
+        AmocasW = "amocas.w", AmocasWAq = "amocas.w.aq", AmocasWAqrl = "amocas.w.aqrl",
+        AmocasWRl = "amocas.w.rl", AmomaxB = "amomax.b", AmomaxBAq = "amomax.b.aq",
+        AmomaxBAqrl = "amomax.b.aqrl", AmomaxBRl = "amomax.b.rl", AmomaxD = "amomax.d",
+        AmomaxDAq = "amomax.d.aq", AmomaxDAqrl = "amomax.d.aqrl", AmomaxDRl = "amomax.d.rl",
+        AmomaxH = "amomax.h", AmomaxHAq = "amomax.h.aq", AmomaxHAqrl = "amomax.h.aqrl",
+        AmomaxHRl = "amomax.h.rl", AmomaxW = "amomax.w", AmomaxWAq = "amomax.w.aq",
+        AmomaxWAqrl = "amomax.w.aqrl", AmomaxWRl = "amomax.w.rl", AmomaxuB = "amomaxu.b",
+        AmomaxuBAq = "amomaxu.b.aq", AmomaxuBAqrl = "amomaxu.b.aqrl", AmomaxuBRl = "amomaxu.b.rl",
+        AmomaxuD = "amomaxu.d", AmomaxuDAq = "amomaxu.d.aq", AmomaxuDAqrl = "amomaxu.d.aqrl",
+        AmomaxuDRl = "amomaxu.d.rl", AmomaxuH = "amomaxu.h", AmomaxuHAq = "amomaxu.h.aq",
+        AmomaxuHAqrl = "amomaxu.h.aqrl", AmomaxuHRl = "amomaxu.h.rl", AmomaxuW = "amomaxu.w",
+        AmomaxuWAq = "amomaxu.w.aq", AmomaxuWAqrl = "amomaxu.w.aqrl", AmomaxuWRl = "amomaxu.w.rl",
+        AmominB = "amomin.b", AmominBAq = "amomin.b.aq", AmominBAqrl = "amomin.b.aqrl",
+        AmominBRl = "amomin.b.rl", AmominD = "amomin.d", AmominDAq = "amomin.d.aq",
+        AmominDAqrl = "amomin.d.aqrl", AmominDRl = "amomin.d.rl", AmominH = "amomin.h",
+        AmominHAq = "amomin.h.aq", AmominHAqrl = "amomin.h.aqrl", AmominHRl = "amomin.h.rl",
+        AmominW = "amomin.w", AmominWAq = "amomin.w.aq", AmominWAqrl = "amomin.w.aqrl",
+        AmominWRl = "amomin.w.rl", AmominuB = "amominu.b", AmominuBAq = "amominu.b.aq",
+        AmominuBAqrl = "amominu.b.aqrl", AmominuBRl = "amominu.b.rl", AmominuD = "amominu.d",
+        AmominuDAq = "amominu.d.aq", AmominuDAqrl = "amominu.d.aqrl", AmominuDRl = "amominu.d.rl",
+        AmominuH = "amominu.h", AmominuHAq = "amominu.h.aq", AmominuHAqrl = "amominu.h.aqrl",
+        AmominuHRl = "amominu.h.rl", AmominuW = "amominu.w", AmominuWAq = "amominu.w.aq",
+        AmominuWAqrl = "amominu.w.aqrl", AmominuWRl = "amominu.w.rl", AmoorB = "amoor.b",
+        AmoorBAq = "amoor.b.aq", AmoorBAqrl = "amoor.b.aqrl", AmoorBRl = "amoor.b.rl",
+        AmoorD = "amoor.d", AmoorDAq = "amoor.d.aq", AmoorDAqrl = "amoor.d.aqrl",
+        AmoorDRl = "amoor.d.rl", AmoorH = "amoor.h", AmoorHAq = "amoor.h.aq",
+        AmoorHAqrl = "amoor.h.aqrl", AmoorHRl = "amoor.h.rl", AmoorW = "amoor.w",
+        AmoorWAq = "amoor.w.aq", AmoorWAqrl = "amoor.w.aqrl", AmoorWRl = "amoor.w.rl",
+        AmoswapB = "amoswap.b", AmoswapBAq = "amoswap.b.aq", AmoswapBAqrl = "amoswap.b.aqrl",
+        AmoswapBRl = "amoswap.b.rl", AmoswapD = "amoswap.d", AmoswapDAq = "amoswap.d.aq",
+        AmoswapDAqrl = "amoswap.d.aqrl", AmoswapDRl = "amoswap.d.rl", AmoswapH = "amoswap.h",
+        AmoswapHAq = "amoswap.h.aq", AmoswapHAqrl = "amoswap.h.aqrl", AmoswapHRl = "amoswap.h.rl",
+        AmoswapW = "amoswap.w", AmoswapWAq = "amoswap.w.aq", AmoswapWAqrl = "amoswap.w.aqrl",
+        AmoswapWRl = "amoswap.w.rl", AmoxorB = "amoxor.b", AmoxorBAq = "amoxor.b.aq",
+        AmoxorBAqrl = "amoxor.b.aqrl", AmoxorBRl = "amoxor.b.rl", AmoxorD = "amoxor.d",
+        AmoxorDAq = "amoxor.d.aq", AmoxorDAqrl = "amoxor.d.aqrl", AmoxorDRl = "amoxor.d.rl",
+        AmoxorH = "amoxor.h", AmoxorHAq = "amoxor.h.aq", AmoxorHAqrl = "amoxor.h.aqrl",
+        AmoxorHRl = "amoxor.h.rl", AmoxorW = "amoxor.w", AmoxorWAq = "amoxor.w.aq",
+        AmoxorWAqrl = "amoxor.w.aqrl", AmoxorWRl = "amoxor.w.rl", ScD = "sc.d", ScDAq = "sc.d.aq",
+        ScDAqrl = "sc.d.aqrl", ScDRl = "sc.d.rl", ScW = "sc.w", ScWAq = "sc.w.aq",
+        ScWAqrl = "sc.w.aqrl", ScWRl = "sc.w.rl",
+    ]
+    { rs2: Reg, rs1: Reg } => [
+        HsvB = "hsv.b", HsvD = "hsv.d", HsvH = "hsv.h", HsvW = "hsv.w",
+    ]
+    { pred: FenceSet, succ: FenceSet } => [
+        Fence = "fence",
+    ]
+    // address-translation fences, the address in rs1 and the ASID or VMID in rs2
+    { rs1: Reg, rs2: Reg } => [
+        HfenceGvma = "hfence.gvma", HfenceVvma = "hfence.vvma", HinvalGvma = "hinval.gvma",
+        HinvalVvma = "hinval.vvma", SfenceVma = "sfence.vma", SinvalVma = "sinval.vma",
+    ]
+    { offset: i32, rs1: Reg } => [
+        PrefetchI = "prefetch.i", PrefetchR = "prefetch.r", PrefetchW = "prefetch.w",
+    ]
+    // register only: cache-block ops and compressed jumps
+    { rs1: Reg } => [
+        CJalr = "c.jalr", CJr = "c.jr", CboClean = "cbo.clean", CboFlush = "cbo.flush",
+        CboInval = "cbo.inval", CboZero = "cbo.zero",
+    ]
+    { rd: Reg, csr: CsrAddr, rs1: Reg } => [
+        Csrrc = "csrrc", Csrrs = "csrrs", Csrrw = "csrrw",
+    ]
+    { rd: Reg, csr: CsrAddr, uimm: i32 } => [
+        Csrrci = "csrrci", Csrrsi = "csrrsi", Csrrwi = "csrrwi",
+    ]
+    // floating-point loads and stores
+    { rd: FReg, offset: i32, rs1: Reg } => [
+        CFld = "c.fld", CFldsp = "c.fldsp", CFlw = "c.flw", CFlwsp = "c.flwsp", Fld = "fld",
+        Flh = "flh", Flq = "flq", Flw = "flw",
+    ]
+    { rs2: FReg, offset: i32, rs1: Reg } => [
+        CFsd = "c.fsd", CFsdsp = "c.fsdsp", CFsw = "c.fsw", CFswsp = "c.fswsp", Fsd = "fsd",
+        Fsh = "fsh", Fsq = "fsq", Fsw = "fsw",
+    ]
+    // floating-point ops with an optional rounding mode, None for the default
+    { rd: FReg, rs1: FReg, rs2: FReg, rm: Option<RoundingMode> } => [
+        FaddD = "fadd.d", FaddH = "fadd.h", FaddQ = "fadd.q", FaddS = "fadd.s", FdivD = "fdiv.d",
+        FdivH = "fdiv.h", FdivQ = "fdiv.q", FdivS = "fdiv.s", FmulD = "fmul.d", FmulH = "fmul.h",
+        FmulQ = "fmul.q", FmulS = "fmul.s", FsubD = "fsub.d", FsubH = "fsub.h", FsubQ = "fsub.q",
+        FsubS = "fsub.s",
+    ]
+    { rd: FReg, rs1: FReg, rs2: FReg } => [
+        FmaxD = "fmax.d", FmaxH = "fmax.h", FmaxQ = "fmax.q", FmaxS = "fmax.s", FminD = "fmin.d",
+        FminH = "fmin.h", FminQ = "fmin.q", FminS = "fmin.s", FsgnjD = "fsgnj.d",
+        FsgnjH = "fsgnj.h", FsgnjQ = "fsgnj.q", FsgnjS = "fsgnj.s", FsgnjnD = "fsgnjn.d",
+        FsgnjnH = "fsgnjn.h", FsgnjnQ = "fsgnjn.q", FsgnjnS = "fsgnjn.s", FsgnjxD = "fsgnjx.d",
+        FsgnjxH = "fsgnjx.h", FsgnjxQ = "fsgnjx.q", FsgnjxS = "fsgnjx.s",
+    ]
+    { rd: FReg, rs1: FReg, rs2: FReg, rs3: FReg, rm: Option<RoundingMode> } => [
+        FmaddD = "fmadd.d", FmaddH = "fmadd.h", FmaddQ = "fmadd.q", FmaddS = "fmadd.s",
+        FmsubD = "fmsub.d", FmsubH = "fmsub.h", FmsubQ = "fmsub.q", FmsubS = "fmsub.s",
+        FnmaddD = "fnmadd.d", FnmaddH = "fnmadd.h", FnmaddQ = "fnmadd.q", FnmaddS = "fnmadd.s",
+        FnmsubD = "fnmsub.d", FnmsubH = "fnmsub.h", FnmsubQ = "fnmsub.q", FnmsubS = "fnmsub.s",
+    ]
+    { rd: FReg, rs1: FReg, rm: Option<RoundingMode> } => [
+        FcvtDH = "fcvt.d.h", FcvtDQ = "fcvt.d.q", FcvtDS = "fcvt.d.s", FcvtHD = "fcvt.h.d",
+        FcvtHQ = "fcvt.h.q", FcvtHS = "fcvt.h.s", FcvtQD = "fcvt.q.d", FcvtQH = "fcvt.q.h",
+        FcvtQS = "fcvt.q.s", FcvtSD = "fcvt.s.d", FcvtSH = "fcvt.s.h", FcvtSQ = "fcvt.s.q",
+        FsqrtD = "fsqrt.d", FsqrtH = "fsqrt.h", FsqrtQ = "fsqrt.q", FsqrtS = "fsqrt.s",
+    ]
+    { rd: FReg, rs1: Reg, rm: Option<RoundingMode> } => [
+        FcvtDL = "fcvt.d.l", FcvtDLu = "fcvt.d.lu", FcvtDW = "fcvt.d.w", FcvtDWu = "fcvt.d.wu",
+        FcvtHL = "fcvt.h.l", FcvtHLu = "fcvt.h.lu", FcvtHW = "fcvt.h.w", FcvtHWu = "fcvt.h.wu",
+        FcvtQL = "fcvt.q.l", FcvtQLu = "fcvt.q.lu", FcvtQW = "fcvt.q.w", FcvtQWu = "fcvt.q.wu",
+        FcvtSL = "fcvt.s.l", FcvtSLu = "fcvt.s.lu", FcvtSW = "fcvt.s.w", FcvtSWu = "fcvt.s.wu",
+    ]
+    { rd: Reg, rs1: FReg, rm: Option<RoundingMode> } => [
+        FcvtLD = "fcvt.l.d", FcvtLH = "fcvt.l.h", FcvtLQ = "fcvt.l.q", FcvtLS = "fcvt.l.s",
+        FcvtLuD = "fcvt.lu.d", FcvtLuH = "fcvt.lu.h", FcvtLuQ = "fcvt.lu.q", FcvtLuS = "fcvt.lu.s",
+        FcvtWD = "fcvt.w.d", FcvtWH = "fcvt.w.h", FcvtWQ = "fcvt.w.q", FcvtWS = "fcvt.w.s",
+        FcvtWuD = "fcvt.wu.d", FcvtWuH = "fcvt.wu.h", FcvtWuQ = "fcvt.wu.q", FcvtWuS = "fcvt.wu.s",
+    ]
+    { rd: FReg, rs1: Reg } => [
+        FmvDX = "fmv.d.x", FmvHX = "fmv.h.x", FmvWX = "fmv.w.x",
+    ]
+    { rd: Reg, rs1: FReg } => [
+        FclassD = "fclass.d", FclassH = "fclass.h", FclassQ = "fclass.q", FclassS = "fclass.s",
+        FmvXD = "fmv.x.d", FmvXH = "fmv.x.h", FmvXW = "fmv.x.w",
+    ]
+    { rd: Reg, rs1: FReg, rs2: FReg } => [
+        FeqD = "feq.d", FeqH = "feq.h", FeqQ = "feq.q", FeqS = "feq.s", FleD = "fle.d",
+        FleH = "fle.h", FleQ = "fle.q", FleS = "fle.s", FltD = "flt.d", FltH = "flt.h",
+        FltQ = "flt.q", FltS = "flt.s",
+    ]
+    // compressed instructions not covered above
+    { rd: Reg, rs2: Reg } => [
+        CAdd = "c.add", CAddw = "c.addw", CAnd = "c.and", CMul = "c.mul", CMv = "c.mv",
+        COr = "c.or", CSub = "c.sub", CSubw = "c.subw", CXor = "c.xor",
+    ]
+    { rd: Reg } => [
+        CNot = "c.not", CSextB = "c.sext.b", CSextH = "c.sext.h", CZextB = "c.zext.b",
+        CZextH = "c.zext.h", CZextW = "c.zext.w",
+    ]
+    { rd: Reg, shamt: i32 } => [
+        CSlli = "c.slli", CSrai = "c.srai", CSrli = "c.srli",
+    ]
+    { rs1: Reg, offset: i32 } => [
+        CBeqz = "c.beqz", CBnez = "c.bnez",
+    ]
+    { offset: i32 } => [
+        CJ = "c.j", CJal = "c.jal",
+    ]
+    // Zcmp and Zcmt
+    { rlist: RegList, stack_adj: i32 } => [
+        CmPop = "cm.pop", CmPopret = "cm.popret", CmPopretz = "cm.popretz", CmPush = "cm.push",
+    ]
+    { r1s: Reg, r2s: Reg } => [
+        CmMva01s = "cm.mva01s", CmMvsa01 = "cm.mvsa01",
+    ]
+    { index: i32 } => [
+        CmJalt = "cm.jalt", CmJt = "cm.jt",
+    ]
+    // vector configuration, vsetvl is among the rd, rs1, rs2 ops
+    { rd: Reg, rs1: Reg, vtype: VType } => [
+        Vsetvli = "vsetvli",
+    ]
+    { rd: Reg, uimm: i32, vtype: VType } => [
+        Vsetivli = "vsetivli",
+    ]
+    // vector arithmetic, `masked` is a trailing `v0.t` and the carry and merge forms name v0
+    { vd: VReg, vs2: VReg, vs1: VReg, masked: bool } => [
+        VaaddVv = "vaadd.vv", VaadduVv = "vaaddu.vv", VaddVv = "vadd.vv", VandVv = "vand.vv",
+        VandnVv = "vandn.vv", VasubVv = "vasub.vv", VasubuVv = "vasubu.vv", VclmulVv = "vclmul.vv",
+        VclmulhVv = "vclmulh.vv", VdivVv = "vdiv.vv", VdivuVv = "vdivu.vv", VfaddVv = "vfadd.vv",
+        VfdivVv = "vfdiv.vv", VfmaxVv = "vfmax.vv", VfminVv = "vfmin.vv", VfmulVv = "vfmul.vv",
+        VfredmaxVs = "vfredmax.vs", VfredminVs = "vfredmin.vs", VfredosumVs = "vfredosum.vs",
+        VfredusumVs = "vfredusum.vs", VfsgnjVv = "vfsgnj.vv", VfsgnjnVv = "vfsgnjn.vv",
+        VfsgnjxVv = "vfsgnjx.vv", VfsubVv = "vfsub.vv", VfwaddVv = "vfwadd.vv",
+        VfwaddWv = "vfwadd.wv", VfwmulVv = "vfwmul.vv", VfwredosumVs = "vfwredosum.vs",
+        VfwredusumVs = "vfwredusum.vs", VfwsubVv = "vfwsub.vv", VfwsubWv = "vfwsub.wv",
+        VmaxVv = "vmax.vv", VmaxuVv = "vmaxu.vv", VmfeqVv = "vmfeq.vv", VmfleVv = "vmfle.vv",
+        VmfltVv = "vmflt.vv", VmfneVv = "vmfne.vv", VminVv = "vmin.vv", VminuVv = "vminu.vv",
+        VmseqVv = "vmseq.vv", VmsleVv = "vmsle.vv", VmsleuVv = "vmsleu.vv", VmsltVv = "vmslt.vv",
+        VmsltuVv = "vmsltu.vv", VmsneVv = "vmsne.vv", VmulVv = "vmul.vv", VmulhVv = "vmulh.vv",
+        VmulhsuVv = "vmulhsu.vv", VmulhuVv = "vmulhu.vv", VnclipWv = "vnclip.wv",
+        VnclipuWv = "vnclipu.wv", VnsraWv = "vnsra.wv", VnsrlWv = "vnsrl.wv", VorVv = "vor.vv",
+        VredandVs = "vredand.vs", VredmaxVs = "vredmax.vs", VredmaxuVs = "vredmaxu.vs",
+        VredminVs = "vredmin.vs", VredminuVs = "vredminu.vs", VredorVs = "vredor.vs",
+        VredsumVs = "vredsum.vs", VredxorVs = "vredxor.vs", VremVv = "vrem.vv",
+        VremuVv = "vremu.vv", VrgatherVv = "vrgather.vv", Vrgatherei16Vv = "vrgatherei16.vv",
+        VrolVv = "vrol.vv", VrorVv = "vror.vv", VsaddVv = "vsadd.vv", VsadduVv = "vsaddu.vv",
+        VsllVv = "vsll.vv", VsmulVv = "vsmul.vv", VsraVv = "vsra.vv", VsrlVv = "vsrl.vv",
+        VssraVv = "vssra.vv", VssrlVv = "vssrl.vv", VssubVv = "vssub.vv", VssubuVv = "vssubu.vv",
+        VsubVv = "vsub.vv", VwaddVv = "vwadd.vv", VwaddWv = "vwadd.wv", VwadduVv = "vwaddu.vv",
+        VwadduWv = "vwaddu.wv", VwmulVv = "vwmul.vv", VwmulsuVv = "vwmulsu.vv",
+        VwmuluVv = "vwmulu.vv", VwredsumVs = "vwredsum.vs", VwredsumuVs = "vwredsumu.vs",
+        VwsllVv = "vwsll.vv", VwsubVv = "vwsub.vv", VwsubWv = "vwsub.wv", VwsubuVv = "vwsubu.vv",
+        VwsubuWv = "vwsubu.wv", VxorVv = "vxor.vv",
+    ]
+    { vd: VReg, vs2: VReg, vs1: VReg, v0: VReg } => [
+        VadcVvm = "vadc.vvm", VmadcVvm = "vmadc.vvm", VmergeVvm = "vmerge.vvm",
+        VmsbcVvm = "vmsbc.vvm", VsbcVvm = "vsbc.vvm",
+    ]
+    { vd: VReg, vs2: VReg, rs1: Reg, masked: bool } => [
+        VaaddVx = "vaadd.vx", VaadduVx = "vaaddu.vx", VaddVx = "vadd.vx", VandVx = "vand.vx",
+        VandnVx = "vandn.vx", VasubVx = "vasub.vx", VasubuVx = "vasubu.vx", VclmulVx = "vclmul.vx",
+        VclmulhVx = "vclmulh.vx", VdivVx = "vdiv.vx", VdivuVx = "vdivu.vx", VmaxVx = "vmax.vx",
+        VmaxuVx = "vmaxu.vx", VminVx = "vmin.vx", VminuVx = "vminu.vx", VmseqVx = "vmseq.vx",
+        VmsgtVx = "vmsgt.vx", VmsgtuVx = "vmsgtu.vx", VmsleVx = "vmsle.vx", VmsleuVx = "vmsleu.vx",
+        VmsltVx = "vmslt.vx", VmsltuVx = "vmsltu.vx", VmsneVx = "vmsne.vx", VmulVx = "vmul.vx",
+        VmulhVx = "vmulh.vx", VmulhsuVx = "vmulhsu.vx", VmulhuVx = "vmulhu.vx",
+        VnclipWx = "vnclip.wx", VnclipuWx = "vnclipu.wx", VnsraWx = "vnsra.wx",
+        VnsrlWx = "vnsrl.wx", VorVx = "vor.vx", VremVx = "vrem.vx", VremuVx = "vremu.vx",
+        VrgatherVx = "vrgather.vx", VrolVx = "vrol.vx", VrorVx = "vror.vx", VrsubVx = "vrsub.vx",
+        VsaddVx = "vsadd.vx", VsadduVx = "vsaddu.vx", Vslide1downVx = "vslide1down.vx",
+        Vslide1upVx = "vslide1up.vx", VslidedownVx = "vslidedown.vx", VslideupVx = "vslideup.vx",
+        VsllVx = "vsll.vx", VsmulVx = "vsmul.vx", VsraVx = "vsra.vx", VsrlVx = "vsrl.vx",
+        VssraVx = "vssra.vx", VssrlVx = "vssrl.vx", VssubVx = "vssub.vx", VssubuVx = "vssubu.vx",
+        VsubVx = "vsub.vx", VwaddVx = "vwadd.vx", VwaddWx = "vwadd.wx", VwadduVx = "vwaddu.vx",
+        VwadduWx = "vwaddu.wx", VwmulVx = "vwmul.vx", VwmulsuVx = "vwmulsu.vx",
+        VwmuluVx = "vwmulu.vx", VwsllVx = "vwsll.vx", VwsubVx = "vwsub.vx", VwsubWx = "vwsub.wx",
+        VwsubuVx = "vwsubu.vx", VwsubuWx = "vwsubu.wx", VxorVx = "vxor.vx",
+    ]
+    { vd: VReg, vs2: VReg, rs1: Reg, v0: VReg } => [
+        VadcVxm = "vadc.vxm", VmadcVxm = "vmadc.vxm", VmergeVxm = "vmerge.vxm",
+        VmsbcVxm = "vmsbc.vxm", VsbcVxm = "vsbc.vxm",
+    ]
+    { vd: VReg, vs2: VReg, imm: i32, v0: VReg } => [
+        VadcVim = "vadc.vim", VmadcVim = "vmadc.vim", VmergeVim = "vmerge.vim",
+    ]
+    { vd: VReg, vs2: VReg, imm: i32, masked: bool } => [
+        VaddVi = "vadd.vi", VandVi = "vand.vi", VmseqVi = "vmseq.vi", VmsgtVi = "vmsgt.vi",
+        VmsgtuVi = "vmsgtu.vi", VmsleVi = "vmsle.vi", VmsleuVi = "vmsleu.vi", VmsneVi = "vmsne.vi",
+        VnclipWi = "vnclip.wi", VnclipuWi = "vnclipu.wi", VnsraWi = "vnsra.wi",
+        VnsrlWi = "vnsrl.wi", VorVi = "vor.vi", VrgatherVi = "vrgather.vi", VrorVi = "vror.vi",
+        VrsubVi = "vrsub.vi", VsaddVi = "vsadd.vi", VsadduVi = "vsaddu.vi",
+        VslidedownVi = "vslidedown.vi", VslideupVi = "vslideup.vi", VsllVi = "vsll.vi",
+        VsraVi = "vsra.vi", VsrlVi = "vsrl.vi", VssraVi = "vssra.vi", VssrlVi = "vssrl.vi",
+        VwsllVi = "vwsll.vi", VxorVi = "vxor.vi",
+    ]
+    { vd: VReg, vs2: VReg, rs1: FReg, masked: bool } => [
+        VfaddVf = "vfadd.vf", VfdivVf = "vfdiv.vf", VfmaxVf = "vfmax.vf", VfminVf = "vfmin.vf",
+        VfmulVf = "vfmul.vf", VfrdivVf = "vfrdiv.vf", VfrsubVf = "vfrsub.vf",
+        VfsgnjVf = "vfsgnj.vf", VfsgnjnVf = "vfsgnjn.vf", VfsgnjxVf = "vfsgnjx.vf",
+        Vfslide1downVf = "vfslide1down.vf", Vfslide1upVf = "vfslide1up.vf", VfsubVf = "vfsub.vf",
+        VfwaddVf = "vfwadd.vf", VfwaddWf = "vfwadd.wf", VfwmulVf = "vfwmul.vf",
+        VfwsubVf = "vfwsub.vf", VfwsubWf = "vfwsub.wf", VmfeqVf = "vmfeq.vf", VmfgeVf = "vmfge.vf",
+        VmfgtVf = "vmfgt.vf", VmfleVf = "vmfle.vf", VmfltVf = "vmflt.vf", VmfneVf = "vmfne.vf",
+    ]
+    { vd: VReg, vs2: VReg, rs1: FReg, v0: VReg } => [
+        VfmergeVfm = "vfmerge.vfm",
+    ]
+    { vd: VReg, vs1: VReg, vs2: VReg, masked: bool } => [
+        VfmaccVv = "vfmacc.vv", VfmaddVv = "vfmadd.vv", VfmsacVv = "vfmsac.vv",
+        VfmsubVv = "vfmsub.vv", VfnmaccVv = "vfnmacc.vv", VfnmaddVv = "vfnmadd.vv",
+        VfnmsacVv = "vfnmsac.vv", VfnmsubVv = "vfnmsub.vv", VfwmaccVv = "vfwmacc.vv",
+        VfwmsacVv = "vfwmsac.vv", VfwnmaccVv = "vfwnmacc.vv", VfwnmsacVv = "vfwnmsac.vv",
+        VmaccVv = "vmacc.vv", VmaddVv = "vmadd.vv", VnmsacVv = "vnmsac.vv", VnmsubVv = "vnmsub.vv",
+        VwmaccVv = "vwmacc.vv", VwmaccsuVv = "vwmaccsu.vv", VwmaccuVv = "vwmaccu.vv",
+    ]
+    { vd: VReg, rs1: Reg, vs2: VReg, masked: bool } => [
+        VmaccVx = "vmacc.vx", VmaddVx = "vmadd.vx", VnmsacVx = "vnmsac.vx", VnmsubVx = "vnmsub.vx",
+        VwmaccVx = "vwmacc.vx", VwmaccsuVx = "vwmaccsu.vx", VwmaccuVx = "vwmaccu.vx",
+        VwmaccusVx = "vwmaccus.vx",
+    ]
+    { vd: VReg, rs1: FReg, vs2: VReg, masked: bool } => [
+        VfmaccVf = "vfmacc.vf", VfmaddVf = "vfmadd.vf", VfmsacVf = "vfmsac.vf",
+        VfmsubVf = "vfmsub.vf", VfnmaccVf = "vfnmacc.vf", VfnmaddVf = "vfnmadd.vf",
+        VfnmsacVf = "vfnmsac.vf", VfnmsubVf = "vfnmsub.vf", VfwmaccVf = "vfwmacc.vf",
+        VfwmsacVf = "vfwmsac.vf", VfwnmaccVf = "vfwnmacc.vf", VfwnmsacVf = "vfwnmsac.vf",
+    ]
+    { vd: VReg, vs2: VReg, vs1: VReg } => [
+        VcompressVm = "vcompress.vm", VghshVv = "vghsh.vv", VmadcVv = "vmadc.vv",
+        VmandMm = "vmand.mm", VmandnMm = "vmandn.mm", VmnandMm = "vmnand.mm", VmnorMm = "vmnor.mm",
+        VmorMm = "vmor.mm", VmornMm = "vmorn.mm", VmsbcVv = "vmsbc.vv", VmxnorMm = "vmxnor.mm",
+        VmxorMm = "vmxor.mm", Vsha2chVv = "vsha2ch.vv", Vsha2clVv = "vsha2cl.vv",
+        Vsha2msVv = "vsha2ms.vv", Vsm3meVv = "vsm3me.vv",
+    ]
+    { vd: VReg, vs2: VReg, rs1: Reg } => [
+        VmadcVx = "vmadc.vx", VmsbcVx = "vmsbc.vx",
+    ]
+    { vd: VReg, vs2: VReg, imm: i32 } => [
+        Vaeskf1Vi = "vaeskf1.vi", Vaeskf2Vi = "vaeskf2.vi", VmadcVi = "vmadc.vi",
+        Vsm3cVi = "vsm3c.vi", Vsm4kVi = "vsm4k.vi",
+    ]
+    { vd: VReg, vs2: VReg, masked: bool } => [
+        VbrevV = "vbrev.v", Vbrev8V = "vbrev8.v", VclzV = "vclz.v", VcpopV = "vcpop.v",
+        VctzV = "vctz.v", VfclassV = "vfclass.v", VfcvtFXV = "vfcvt.f.x.v",
+        VfcvtFXuV = "vfcvt.f.xu.v", VfcvtRtzXFV = "vfcvt.rtz.x.f.v",
+        VfcvtRtzXuFV = "vfcvt.rtz.xu.f.v", VfcvtXFV = "vfcvt.x.f.v", VfcvtXuFV = "vfcvt.xu.f.v",
+        VfncvtFFW = "vfncvt.f.f.w", VfncvtFXW = "vfncvt.f.x.w", VfncvtFXuW = "vfncvt.f.xu.w",
+        VfncvtRodFFW = "vfncvt.rod.f.f.w", VfncvtRtzXFW = "vfncvt.rtz.x.f.w",
+        VfncvtRtzXuFW = "vfncvt.rtz.xu.f.w", VfncvtXFW = "vfncvt.x.f.w",
+        VfncvtXuFW = "vfncvt.xu.f.w", Vfrec7V = "vfrec7.v", Vfrsqrt7V = "vfrsqrt7.v",
+        VfsqrtV = "vfsqrt.v", VfwcvtFFV = "vfwcvt.f.f.v", VfwcvtFXV = "vfwcvt.f.x.v",
+        VfwcvtFXuV = "vfwcvt.f.xu.v", VfwcvtRtzXFV = "vfwcvt.rtz.x.f.v",
+        VfwcvtRtzXuFV = "vfwcvt.rtz.xu.f.v", VfwcvtXFV = "vfwcvt.x.f.v",
+        VfwcvtXuFV = "vfwcvt.xu.f.v", ViotaM = "viota.m", VmsbfM = "vmsbf.m", VmsifM = "vmsif.m",
+        VmsofM = "vmsof.m", Vrev8V = "vrev8.v", VsextVf2 = "vsext.vf2", VsextVf4 = "vsext.vf4",
+        VsextVf8 = "vsext.vf8", VzextVf2 = "vzext.vf2", VzextVf4 = "vzext.vf4",
+        VzextVf8 = "vzext.vf8",
+    ]
+    { vd: VReg, vs2: VReg } => [
+        VaesdfVs = "vaesdf.vs", VaesdfVv = "vaesdf.vv", VaesdmVs = "vaesdm.vs",
+        VaesdmVv = "vaesdm.vv", VaesefVs = "vaesef.vs", VaesefVv = "vaesef.vv",
+        VaesemVs = "vaesem.vs", VaesemVv = "vaesem.vv", VaeszVs = "vaesz.vs", VgmulVv = "vgmul.vv",
+        Vmv1rV = "vmv1r.v", Vmv2rV = "vmv2r.v", Vmv4rV = "vmv4r.v", Vmv8rV = "vmv8r.v",
+        Vsm4rVs = "vsm4r.vs", Vsm4rVv = "vsm4r.vv",
+    ]
+    { vd: VReg, masked: bool } => [
+        VidV = "vid.v",
+    ]
+    { rd: Reg, vs2: VReg, masked: bool } => [
+        VcpopM = "vcpop.m", VfirstM = "vfirst.m",
+    ]
+    { rd: Reg, vs2: VReg } => [
+        VmvXS = "vmv.x.s",
+    ]
+    { rd: FReg, vs2: VReg } => [
+        VfmvFS = "vfmv.f.s",
+    ]
+    { vd: VReg, rs1: FReg } => [
+        VfmvSF = "vfmv.s.f", VfmvVF = "vfmv.v.f",
+    ]
+    { vd: VReg, rs1: Reg } => [
+        VmvSX = "vmv.s.x", VmvVX = "vmv.v.x",
+    ]
+    { vd: VReg, imm: i32 } => [
+        VmvVI = "vmv.v.i",
+    ]
+    { vd: VReg, vs1: VReg } => [
+        VmvVV = "vmv.v.v",
+    ]
+    // vector loads and stores, stores name their data register vs3
+    { vd: VReg, rs1: Reg, masked: bool } => [
+        Vle16V = "vle16.v", Vle16ffV = "vle16ff.v", Vle32V = "vle32.v", Vle32ffV = "vle32ff.v",
+        Vle64V = "vle64.v", Vle64ffV = "vle64ff.v", Vle8V = "vle8.v", Vle8ffV = "vle8ff.v",
+        Vlseg2e16V = "vlseg2e16.v", Vlseg2e16ffV = "vlseg2e16ff.v", Vlseg2e32V = "vlseg2e32.v",
+        Vlseg2e32ffV = "vlseg2e32ff.v", Vlseg2e64V = "vlseg2e64.v", Vlseg2e64ffV = "vlseg2e64ff.v",
+        Vlseg2e8V = "vlseg2e8.v", Vlseg2e8ffV = "vlseg2e8ff.v", Vlseg3e16V = "vlseg3e16.v",
+        Vlseg3e16ffV = "vlseg3e16ff.v", Vlseg3e32V = "vlseg3e32.v", Vlseg3e32ffV = "vlseg3e32ff.v",
+        Vlseg3e64V = "vlseg3e64.v", Vlseg3e64ffV = "vlseg3e64ff.v", Vlseg3e8V = "vlseg3e8.v",
+        Vlseg3e8ffV = "vlseg3e8ff.v", Vlseg4e16V = "vlseg4e16.v", Vlseg4e16ffV = "vlseg4e16ff.v",
+        Vlseg4e32V = "vlseg4e32.v", Vlseg4e32ffV = "vlseg4e32ff.v", Vlseg4e64V = "vlseg4e64.v",
+        Vlseg4e64ffV = "vlseg4e64ff.v", Vlseg4e8V = "vlseg4e8.v", Vlseg4e8ffV = "vlseg4e8ff.v",
+        Vlseg5e16V = "vlseg5e16.v", Vlseg5e16ffV = "vlseg5e16ff.v", Vlseg5e32V = "vlseg5e32.v",
+        Vlseg5e32ffV = "vlseg5e32ff.v", Vlseg5e64V = "vlseg5e64.v", Vlseg5e64ffV = "vlseg5e64ff.v",
+        Vlseg5e8V = "vlseg5e8.v", Vlseg5e8ffV = "vlseg5e8ff.v", Vlseg6e16V = "vlseg6e16.v",
+        Vlseg6e16ffV = "vlseg6e16ff.v", Vlseg6e32V = "vlseg6e32.v", Vlseg6e32ffV = "vlseg6e32ff.v",
+        Vlseg6e64V = "vlseg6e64.v", Vlseg6e64ffV = "vlseg6e64ff.v", Vlseg6e8V = "vlseg6e8.v",
+        Vlseg6e8ffV = "vlseg6e8ff.v", Vlseg7e16V = "vlseg7e16.v", Vlseg7e16ffV = "vlseg7e16ff.v",
+        Vlseg7e32V = "vlseg7e32.v", Vlseg7e32ffV = "vlseg7e32ff.v", Vlseg7e64V = "vlseg7e64.v",
+        Vlseg7e64ffV = "vlseg7e64ff.v", Vlseg7e8V = "vlseg7e8.v", Vlseg7e8ffV = "vlseg7e8ff.v",
+        Vlseg8e16V = "vlseg8e16.v", Vlseg8e16ffV = "vlseg8e16ff.v", Vlseg8e32V = "vlseg8e32.v",
+        Vlseg8e32ffV = "vlseg8e32ff.v", Vlseg8e64V = "vlseg8e64.v", Vlseg8e64ffV = "vlseg8e64ff.v",
+        Vlseg8e8V = "vlseg8e8.v", Vlseg8e8ffV = "vlseg8e8ff.v",
+    ]
+    { vs3: VReg, rs1: Reg, masked: bool } => [
+        Vse16V = "vse16.v", Vse32V = "vse32.v", Vse64V = "vse64.v", Vse8V = "vse8.v",
+        Vsseg2e16V = "vsseg2e16.v", Vsseg2e32V = "vsseg2e32.v", Vsseg2e64V = "vsseg2e64.v",
+        Vsseg2e8V = "vsseg2e8.v", Vsseg3e16V = "vsseg3e16.v", Vsseg3e32V = "vsseg3e32.v",
+        Vsseg3e64V = "vsseg3e64.v", Vsseg3e8V = "vsseg3e8.v", Vsseg4e16V = "vsseg4e16.v",
+        Vsseg4e32V = "vsseg4e32.v", Vsseg4e64V = "vsseg4e64.v", Vsseg4e8V = "vsseg4e8.v",
+        Vsseg5e16V = "vsseg5e16.v", Vsseg5e32V = "vsseg5e32.v", Vsseg5e64V = "vsseg5e64.v",
+        Vsseg5e8V = "vsseg5e8.v", Vsseg6e16V = "vsseg6e16.v", Vsseg6e32V = "vsseg6e32.v",
+        Vsseg6e64V = "vsseg6e64.v", Vsseg6e8V = "vsseg6e8.v", Vsseg7e16V = "vsseg7e16.v",
+        Vsseg7e32V = "vsseg7e32.v", Vsseg7e64V = "vsseg7e64.v", Vsseg7e8V = "vsseg7e8.v",
+        Vsseg8e16V = "vsseg8e16.v", Vsseg8e32V = "vsseg8e32.v", Vsseg8e64V = "vsseg8e64.v",
+        Vsseg8e8V = "vsseg8e8.v",
+    ]
+    { vd: VReg, rs1: Reg } => [
+        Vl1re16V = "vl1re16.v", Vl1re32V = "vl1re32.v", Vl1re64V = "vl1re64.v",
+        Vl1re8V = "vl1re8.v", Vl2re16V = "vl2re16.v", Vl2re32V = "vl2re32.v",
+        Vl2re64V = "vl2re64.v", Vl2re8V = "vl2re8.v", Vl4re16V = "vl4re16.v",
+        Vl4re32V = "vl4re32.v", Vl4re64V = "vl4re64.v", Vl4re8V = "vl4re8.v",
+        Vl8re16V = "vl8re16.v", Vl8re32V = "vl8re32.v", Vl8re64V = "vl8re64.v",
+        Vl8re8V = "vl8re8.v", VlmV = "vlm.v",
+    ]
+    { vs3: VReg, rs1: Reg } => [
+        Vs1rV = "vs1r.v", Vs2rV = "vs2r.v", Vs4rV = "vs4r.v", Vs8rV = "vs8r.v", VsmV = "vsm.v",
+    ]
+    { vd: VReg, rs1: Reg, rs2: Reg, masked: bool } => [
+        Vlse16V = "vlse16.v", Vlse32V = "vlse32.v", Vlse64V = "vlse64.v", Vlse8V = "vlse8.v",
+        Vlsseg2e16V = "vlsseg2e16.v", Vlsseg2e32V = "vlsseg2e32.v", Vlsseg2e64V = "vlsseg2e64.v",
+        Vlsseg2e8V = "vlsseg2e8.v", Vlsseg3e16V = "vlsseg3e16.v", Vlsseg3e32V = "vlsseg3e32.v",
+        Vlsseg3e64V = "vlsseg3e64.v", Vlsseg3e8V = "vlsseg3e8.v", Vlsseg4e16V = "vlsseg4e16.v",
+        Vlsseg4e32V = "vlsseg4e32.v", Vlsseg4e64V = "vlsseg4e64.v", Vlsseg4e8V = "vlsseg4e8.v",
+        Vlsseg5e16V = "vlsseg5e16.v", Vlsseg5e32V = "vlsseg5e32.v", Vlsseg5e64V = "vlsseg5e64.v",
+        Vlsseg5e8V = "vlsseg5e8.v", Vlsseg6e16V = "vlsseg6e16.v", Vlsseg6e32V = "vlsseg6e32.v",
+        Vlsseg6e64V = "vlsseg6e64.v", Vlsseg6e8V = "vlsseg6e8.v", Vlsseg7e16V = "vlsseg7e16.v",
+        Vlsseg7e32V = "vlsseg7e32.v", Vlsseg7e64V = "vlsseg7e64.v", Vlsseg7e8V = "vlsseg7e8.v",
+        Vlsseg8e16V = "vlsseg8e16.v", Vlsseg8e32V = "vlsseg8e32.v", Vlsseg8e64V = "vlsseg8e64.v",
+        Vlsseg8e8V = "vlsseg8e8.v",
+    ]
+    { vs3: VReg, rs1: Reg, rs2: Reg, masked: bool } => [
+        Vsse16V = "vsse16.v", Vsse32V = "vsse32.v", Vsse64V = "vsse64.v", Vsse8V = "vsse8.v",
+        Vssseg2e16V = "vssseg2e16.v", Vssseg2e32V = "vssseg2e32.v", Vssseg2e64V = "vssseg2e64.v",
+        Vssseg2e8V = "vssseg2e8.v", Vssseg3e16V = "vssseg3e16.v", Vssseg3e32V = "vssseg3e32.v",
+        Vssseg3e64V = "vssseg3e64.v", Vssseg3e8V = "vssseg3e8.v", Vssseg4e16V = "vssseg4e16.v",
+        Vssseg4e32V = "vssseg4e32.v", Vssseg4e64V = "vssseg4e64.v", Vssseg4e8V = "vssseg4e8.v",
+        Vssseg5e16V = "vssseg5e16.v", Vssseg5e32V = "vssseg5e32.v", Vssseg5e64V = "vssseg5e64.v",
+        Vssseg5e8V = "vssseg5e8.v", Vssseg6e16V = "vssseg6e16.v", Vssseg6e32V = "vssseg6e32.v",
+        Vssseg6e64V = "vssseg6e64.v", Vssseg6e8V = "vssseg6e8.v", Vssseg7e16V = "vssseg7e16.v",
+        Vssseg7e32V = "vssseg7e32.v", Vssseg7e64V = "vssseg7e64.v", Vssseg7e8V = "vssseg7e8.v",
+        Vssseg8e16V = "vssseg8e16.v", Vssseg8e32V = "vssseg8e32.v", Vssseg8e64V = "vssseg8e64.v",
+        Vssseg8e8V = "vssseg8e8.v",
+    ]
+    { vd: VReg, rs1: Reg, vs2: VReg, masked: bool } => [
+        Vloxei16V = "vloxei16.v", Vloxei32V = "vloxei32.v", Vloxei64V = "vloxei64.v",
+        Vloxei8V = "vloxei8.v", Vloxseg2ei16V = "vloxseg2ei16.v", Vloxseg2ei32V = "vloxseg2ei32.v",
+        Vloxseg2ei64V = "vloxseg2ei64.v", Vloxseg2ei8V = "vloxseg2ei8.v",
+        Vloxseg3ei16V = "vloxseg3ei16.v", Vloxseg3ei32V = "vloxseg3ei32.v",
+        Vloxseg3ei64V = "vloxseg3ei64.v", Vloxseg3ei8V = "vloxseg3ei8.v",
+        Vloxseg4ei16V = "vloxseg4ei16.v", Vloxseg4ei32V = "vloxseg4ei32.v",
+        Vloxseg4ei64V = "vloxseg4ei64.v", Vloxseg4ei8V = "vloxseg4ei8.v",
+        Vloxseg5ei16V = "vloxseg5ei16.v", Vloxseg5ei32V = "vloxseg5ei32.v",
+        Vloxseg5ei64V = "vloxseg5ei64.v", Vloxseg5ei8V = "vloxseg5ei8.v",
+        Vloxseg6ei16V = "vloxseg6ei16.v", Vloxseg6ei32V = "vloxseg6ei32.v",
+        Vloxseg6ei64V = "vloxseg6ei64.v", Vloxseg6ei8V = "vloxseg6ei8.v",
+        Vloxseg7ei16V = "vloxseg7ei16.v", Vloxseg7ei32V = "vloxseg7ei32.v",
+        Vloxseg7ei64V = "vloxseg7ei64.v", Vloxseg7ei8V = "vloxseg7ei8.v",
+        Vloxseg8ei16V = "vloxseg8ei16.v", Vloxseg8ei32V = "vloxseg8ei32.v",
+        Vloxseg8ei64V = "vloxseg8ei64.v", Vloxseg8ei8V = "vloxseg8ei8.v", Vluxei16V = "vluxei16.v",
+        Vluxei32V = "vluxei32.v", Vluxei64V = "vluxei64.v", Vluxei8V = "vluxei8.v",
+        Vluxseg2ei16V = "vluxseg2ei16.v", Vluxseg2ei32V = "vluxseg2ei32.v",
+        Vluxseg2ei64V = "vluxseg2ei64.v", Vluxseg2ei8V = "vluxseg2ei8.v",
+        Vluxseg3ei16V = "vluxseg3ei16.v", Vluxseg3ei32V = "vluxseg3ei32.v",
+        Vluxseg3ei64V = "vluxseg3ei64.v", Vluxseg3ei8V = "vluxseg3ei8.v",
+        Vluxseg4ei16V = "vluxseg4ei16.v", Vluxseg4ei32V = "vluxseg4ei32.v",
+        Vluxseg4ei64V = "vluxseg4ei64.v", Vluxseg4ei8V = "vluxseg4ei8.v",
+        Vluxseg5ei16V = "vluxseg5ei16.v", Vluxseg5ei32V = "vluxseg5ei32.v",
+        Vluxseg5ei64V = "vluxseg5ei64.v", Vluxseg5ei8V = "vluxseg5ei8.v",
+        Vluxseg6ei16V = "vluxseg6ei16.v", Vluxseg6ei32V = "vluxseg6ei32.v",
+        Vluxseg6ei64V = "vluxseg6ei64.v", Vluxseg6ei8V = "vluxseg6ei8.v",
+        Vluxseg7ei16V = "vluxseg7ei16.v", Vluxseg7ei32V = "vluxseg7ei32.v",
+        Vluxseg7ei64V = "vluxseg7ei64.v", Vluxseg7ei8V = "vluxseg7ei8.v",
+        Vluxseg8ei16V = "vluxseg8ei16.v", Vluxseg8ei32V = "vluxseg8ei32.v",
+        Vluxseg8ei64V = "vluxseg8ei64.v", Vluxseg8ei8V = "vluxseg8ei8.v",
+    ]
+    { vs3: VReg, rs1: Reg, vs2: VReg, masked: bool } => [
+        Vsoxei16V = "vsoxei16.v", Vsoxei32V = "vsoxei32.v", Vsoxei64V = "vsoxei64.v",
+        Vsoxei8V = "vsoxei8.v", Vsoxseg2ei16V = "vsoxseg2ei16.v", Vsoxseg2ei32V = "vsoxseg2ei32.v",
+        Vsoxseg2ei64V = "vsoxseg2ei64.v", Vsoxseg2ei8V = "vsoxseg2ei8.v",
+        Vsoxseg3ei16V = "vsoxseg3ei16.v", Vsoxseg3ei32V = "vsoxseg3ei32.v",
+        Vsoxseg3ei64V = "vsoxseg3ei64.v", Vsoxseg3ei8V = "vsoxseg3ei8.v",
+        Vsoxseg4ei16V = "vsoxseg4ei16.v", Vsoxseg4ei32V = "vsoxseg4ei32.v",
+        Vsoxseg4ei64V = "vsoxseg4ei64.v", Vsoxseg4ei8V = "vsoxseg4ei8.v",
+        Vsoxseg5ei16V = "vsoxseg5ei16.v", Vsoxseg5ei32V = "vsoxseg5ei32.v",
+        Vsoxseg5ei64V = "vsoxseg5ei64.v", Vsoxseg5ei8V = "vsoxseg5ei8.v",
+        Vsoxseg6ei16V = "vsoxseg6ei16.v", Vsoxseg6ei32V = "vsoxseg6ei32.v",
+        Vsoxseg6ei64V = "vsoxseg6ei64.v", Vsoxseg6ei8V = "vsoxseg6ei8.v",
+        Vsoxseg7ei16V = "vsoxseg7ei16.v", Vsoxseg7ei32V = "vsoxseg7ei32.v",
+        Vsoxseg7ei64V = "vsoxseg7ei64.v", Vsoxseg7ei8V = "vsoxseg7ei8.v",
+        Vsoxseg8ei16V = "vsoxseg8ei16.v", Vsoxseg8ei32V = "vsoxseg8ei32.v",
+        Vsoxseg8ei64V = "vsoxseg8ei64.v", Vsoxseg8ei8V = "vsoxseg8ei8.v", Vsuxei16V = "vsuxei16.v",
+        Vsuxei32V = "vsuxei32.v", Vsuxei64V = "vsuxei64.v", Vsuxei8V = "vsuxei8.v",
+        Vsuxseg2ei16V = "vsuxseg2ei16.v", Vsuxseg2ei32V = "vsuxseg2ei32.v",
+        Vsuxseg2ei64V = "vsuxseg2ei64.v", Vsuxseg2ei8V = "vsuxseg2ei8.v",
+        Vsuxseg3ei16V = "vsuxseg3ei16.v", Vsuxseg3ei32V = "vsuxseg3ei32.v",
+        Vsuxseg3ei64V = "vsuxseg3ei64.v", Vsuxseg3ei8V = "vsuxseg3ei8.v",
+        Vsuxseg4ei16V = "vsuxseg4ei16.v", Vsuxseg4ei32V = "vsuxseg4ei32.v",
+        Vsuxseg4ei64V = "vsuxseg4ei64.v", Vsuxseg4ei8V = "vsuxseg4ei8.v",
+        Vsuxseg5ei16V = "vsuxseg5ei16.v", Vsuxseg5ei32V = "vsuxseg5ei32.v",
+        Vsuxseg5ei64V = "vsuxseg5ei64.v", Vsuxseg5ei8V = "vsuxseg5ei8.v",
+        Vsuxseg6ei16V = "vsuxseg6ei16.v", Vsuxseg6ei32V = "vsuxseg6ei32.v",
+        Vsuxseg6ei64V = "vsuxseg6ei64.v", Vsuxseg6ei8V = "vsuxseg6ei8.v",
+        Vsuxseg7ei16V = "vsuxseg7ei16.v", Vsuxseg7ei32V = "vsuxseg7ei32.v",
+        Vsuxseg7ei64V = "vsuxseg7ei64.v", Vsuxseg7ei8V = "vsuxseg7ei8.v",
+        Vsuxseg8ei16V = "vsuxseg8ei16.v", Vsuxseg8ei32V = "vsuxseg8ei32.v",
+        Vsuxseg8ei64V = "vsuxseg8ei64.v", Vsuxseg8ei8V = "vsuxseg8ei8.v",
+    ]
+}
+
+impl EncodedInstruction {
+    // the typed instruction, None for the custom instructions of a loaded table
+    pub fn op(&self) -> Option<Op> {
+        Op::from_parts(&self.mnemonic, &self.operands)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Op {
+    // the instruction word through the encoder, which checks the operands like for text
+    pub fn encode(&self, xlen: Xlen) -> Result<EncodedInstruction> {
+        let mut text = self.mnemonic().to_string();
+        for (idx, op) in self.operands().iter().enumerate() {
+            text.push_str(if idx == 0 { " " } else { ", " });
+            text.push_str(&op.to_string());
+        }
+        encode(&text, xlen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{decode, decode_zcmp};
+    use crate::format::Xlen;
+
+    // every decoded word has a variant that gives the same operands and encodes back to
+    // the word: all 16-bit words and a spread of 32-bit ones
+    #[test]
+    fn typed_round_trip() {
+        let mut seed: u32 = 0x2545f491;
+        let mut words: Vec<u32> = (0..=0xffff).filter(|x| x & 0b11 != 0b11).collect();
+        words.extend((0..100_000).map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed | 0b11
+        }));
+        for xlen in [Xlen::Rv32, Xlen::Rv64] {
+            for &word in &words {
+                for decoded in [decode(word, xlen), decode_zcmp(word, xlen)] {
+                    let Ok(d) = decoded else { continue };
+                    let op = d.op().unwrap_or_else(|| panic!("no variant for {}", d));
+                    assert_eq!(op.mnemonic(), d.mnemonic.as_str());
+                    assert_eq!(op.operands(), d.operands);
+                    let encoded = op.encode(xlen).map(|e| e.instr.raw());
+                    assert_eq!(encoded, Ok(word), "{:?}", op);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn named_fields() {
+        let d = decode(0x00140413, Xlen::Rv32).unwrap();
+        let op = Op::Addi {
+            rd: Reg(8),
+            rs1: Reg(8),
+            imm: 1,
+        };
+        assert_eq!(d.op(), Some(op));
+        assert_eq!(op.encode(Xlen::Rv32).unwrap().instr.raw(), 0x00140413);
+
+        // vadd.vv v1, v2, v3, v0.t
+        let d = decode(0x002180d7, Xlen::Rv64).unwrap();
+        let op = Op::VaddVv {
+            vd: VReg(1),
+            vs2: VReg(2),
+            vs1: VReg(3),
+            masked: true,
+        };
+        assert_eq!(d.op(), Some(op));
+        assert_eq!(d.to_string(), "vadd.vv v1, v2, v3, v0.t");
+    }
+}
@@ -0,0 +1,207 @@
+use core::fmt;
+
+use crate::csr::{CsrName, Privilege, csr_name};
+use crate::util::{ABI_NAMES, FP_ABI_NAMES, rounding_mode_name, write_fence_set, write_reg_list};
+use crate::vector::write_vtype;
+
+// integer register x0-x31
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reg(pub u8);
+
+// floating-point register f0-f31
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FReg(pub u8);
+
+// vector register v0-v31
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VReg(pub u8);
+
+// 12-bit CSR address, displayed by name or in hex
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsrAddr(pub u16);
+
+// sign-extended immediate, offset or shift amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Imm(pub i32);
+
+// rm field of floating-point instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundingMode(pub u8);
+
+// fence predecessor/successor set, I O R W from bit 3 to 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FenceSet(pub u8);
+
+// vtype immediate of vsetvli and vsetivli, vlmul in bits 2:0, vsew in 5:3, vta and vma
+// in bits 6 and 7
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VType(pub u16);
+
+// register list of cm.push and cm.pop by its rlist encoding: 4 is {ra}, 5 to 14 add s0
+// up to s9 and 15 is {ra, s0-s11}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegList(pub u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Reg(Reg),
+    FReg(FReg),
+    VReg(VReg),
+    Imm(Imm),
+    Csr(CsrAddr),
+    RoundingMode(RoundingMode),
+    FenceSet(FenceSet),
+    VType(VType),
+    RegList(RegList),
+    // `v0.t`, the vm bit cleared
+    VMask,
+}
+
+impl Reg {
+    pub fn abi_name(self) -> &'static str {
+        ABI_NAMES[self.0 as usize]
+    }
+}
+
+impl FReg {
+    pub fn abi_name(self) -> &'static str {
+        FP_ABI_NAMES[self.0 as usize]
+    }
+}
+
+// the access bits of the address: csr[11:10] is 0b11 for read-only CSRs, csr[9:8] is
+// the privilege level, and 0x7b0-0x7bf are only accessible in debug mode
+impl CsrAddr {
+    pub fn name(self) -> Option<CsrName> {
+        csr_name(self.0)
+    }
+
+    pub fn is_read_only(self) -> bool {
+        (self.0 >> 10) & 0b11 == 0b11
+    }
+
+    pub fn privilege(self) -> Privilege {
+        match (self.0 >> 8) & 0b11 {
+            0b00 => Privilege::User,
+            0b01 => Privilege::Supervisor,
+            0b10 => Privilege::Hypervisor,
+            _ => Privilege::Machine,
+        }
+    }
+
+    pub fn is_debug_only(self) -> bool {
+        (0x7b0..=0x7bf).contains(&self.0)
+    }
+}
+
+impl Operand {
+    // field values come straight out of the bitfields, which are u32 or u16
+    pub fn reg(n: impl Into<u32>) -> Self {
+        Operand::Reg(Reg(n.into() as u8))
+    }
+
+    pub fn freg(n: impl Into<u32>) -> Self {
+        Operand::FReg(FReg(n.into() as u8))
+    }
+
+    pub fn vreg(n: impl Into<u32>) -> Self {
+        Operand::VReg(VReg(n.into() as u8))
+    }
+
+    pub fn imm(value: i32) -> Self {
+        Operand::Imm(Imm(value))
+    }
+
+    // displays registers by their ABI name, everything else as usual
+    pub fn abi(&self) -> AbiOperand<'_> {
+        AbiOperand(self)
+    }
+}
+
+pub struct AbiOperand<'a>(&'a Operand);
+
+impl fmt::Display for AbiOperand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Operand::Reg(r) => f.write_str(r.abi_name()),
+            Operand::FReg(r) => f.write_str(r.abi_name()),
+            Operand::RegList(l) => write_reg_list(l.0 as u32, true, f),
+            op => op.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "x{}", self.0)
+    }
+}
+
+impl fmt::Display for FReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "f{}", self.0)
+    }
+}
+
+impl fmt::Display for VReg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl fmt::Display for CsrAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => name.fmt(f),
+            None => write!(f, "0x{:03x}", self.0),
+        }
+    }
+}
+
+impl fmt::Display for Imm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for RoundingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = rounding_mode_name(self.0 as u32).map_err(|_| fmt::Error)?;
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for FenceSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_fence_set(self.0 as u32, f)
+    }
+}
+
+impl fmt::Display for VType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_vtype(self.0 as u32, f)
+    }
+}
+
+impl fmt::Display for RegList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_reg_list(self.0 as u32, false, f)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Reg(r) => r.fmt(f),
+            Operand::FReg(r) => r.fmt(f),
+            Operand::VReg(r) => r.fmt(f),
+            Operand::Imm(i) => i.fmt(f),
+            Operand::Csr(c) => c.fmt(f),
+            Operand::RoundingMode(rm) => rm.fmt(f),
+            Operand::FenceSet(s) => s.fmt(f),
+            Operand::VType(t) => t.fmt(f),
+            Operand::RegList(l) => l.fmt(f),
+            Operand::VMask => f.write_str("v0.t"),
+        }
+    }
+}
@@ -0,0 +1,986 @@
+use colored::*;
+
+use crate::format::*;
+use crate::operand::{CsrAddr, Operand};
+use crate::util::{is_rounding_mode, parse_immediate};
+use crate::vector::{Slot, instruction_layout};
+
+fn operands_to_strings(ops: &[Operand]) -> Vec<String> {
+    ops.iter().map(|o| o.to_string()).collect()
+}
+
+fn operands_to_abi(ops: &[Operand]) -> Vec<String> {
+    ops.iter().map(|o| o.abi().to_string()).collect()
+}
+
+// rd, rs1, rs2, rs3 in operand order, a trailing rounding mode is cyan like its rm field
+fn format_register_operands(mnemonic: &str, operands: &[String]) -> String {
+    let colors = [Color::Green, Color::Yellow, Color::Blue, Color::Magenta];
+    let operands = operands
+        .iter()
+        .enumerate()
+        .map(|(idx, op)| match colors.get(idx) {
+            Some(&color) if !is_rounding_mode(op) => op.color(color).to_string(),
+            _ => op.cyan().to_string(),
+        })
+        .collect::<Vec<_>>();
+    // the ntl hints have no operands
+    match operands.as_slice() {
+        [] => format!("{}", mnemonic.red().bold()),
+        ops => format!("{} {}", mnemonic.red().bold(), ops.join(", ")),
+    }
+}
+
+fn format_r_type(d: &EncodedInstruction, r: &RType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let instr = format_register_operands(&d.mnemonic, &operands);
+    let abi_instr = format_register_operands(&d.mnemonic, &abi_operands);
+    let fields = if r.opcode() == 0b1010011 {
+        // OP-FP splits funct7 into funct5 and fmt, funct3 is the rounding mode for
+        // arithmetic and conversions
+        let funct5 = r.funct7() >> 2;
+        let funct3 = format!("{:03b}", r.funct3());
+        let funct3 = match funct5 {
+            0b00000 | 0b00001 | 0b00010 | 0b00011 | 0b01011 | 0b01000 | 0b11000 | 0b11010 => {
+                funct3.cyan()
+            }
+            _ => funct3.red(),
+        };
+        vec![
+            format!("{:05b}", funct5).red().to_string(),
+            format!("{:02b}", r.funct7() & 0x3).bright_red().to_string(),
+            format!("{:05b}", r.rs2()).blue().to_string(),
+            format!("{:05b}", r.rs1()).yellow().to_string(),
+            funct3.to_string(),
+            format!("{:05b}", r.rd()).green().to_string(),
+            format!("{:07b}", r.opcode()).red().to_string(),
+        ]
+    } else {
+        vec![
+            format!("{:07b}", r.funct7()).red().to_string(),
+            format!("{:05b}", r.rs2()).blue().to_string(),
+            format!("{:05b}", r.rs1()).yellow().to_string(),
+            format!("{:03b}", r.funct3()).red().to_string(),
+            format!("{:05b}", r.rd()).green().to_string(),
+            format!("{:07b}", r.opcode()).red().to_string(),
+        ]
+    };
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", r.0).bold().to_string();
+
+    (instr, abi_instr, bits, hex)
+}
+
+// SYSTEM instructions in R-type layout: rs2 is part of the opcode for the trap returns,
+// wfi and hypervisor loads, a register for the fences and hypervisor stores
+fn format_privileged(d: &EncodedInstruction, r: &RType) -> (String, String, String, String) {
+    let is_store = d.mnemonic.starts_with("hsv");
+    let rs2_is_reg = is_store || d.operands.len() == 2 && !d.is_hypervisor_access();
+    let format = |operands: Vec<String>| match operands.as_slice() {
+        [] => format!("{}", d.mnemonic.red().bold()),
+        [reg, base] if d.is_hypervisor_access() => {
+            let reg = if is_store { reg.blue() } else { reg.green() };
+            format!("{} {}, ({})", d.mnemonic.red().bold(), reg, base.yellow())
+        }
+        [rs1, rs2] => format!(
+            "{} {}, {}",
+            d.mnemonic.red().bold(),
+            rs1.yellow(),
+            rs2.blue()
+        ),
+        _ => unreachable!(),
+    };
+    let instr = format(operands_to_strings(&d.operands));
+    let abi_instr = format(operands_to_abi(&d.operands));
+    let rs2 = format!("{:05b}", r.rs2());
+    let fields = [
+        format!("{:07b}", r.funct7()).red().to_string(),
+        if rs2_is_reg { rs2.blue() } else { rs2.red() }.to_string(),
+        format!("{:05b}", r.rs1()).yellow().to_string(),
+        format!("{:03b}", r.funct3()).red().to_string(),
+        format!("{:05b}", r.rd()).green().to_string(),
+        format!("{:07b}", r.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", r.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_i_type(d: &EncodedInstruction, i: &IType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let hex = format!("0x{:08x}", i.0).bold().to_string();
+    let instr;
+    let abi_instr;
+    if d.is_memory_access() {
+        instr = format!(
+            "{} {}, {}({})",
+            d.mnemonic.red().bold(),
+            operands[0].green(),
+            operands[1].blue(),
+            operands[2].yellow(),
+        );
+        abi_instr = format!(
+            "{} {}, {}({})",
+            d.mnemonic.red().bold(),
+            abi_operands[0].green(),
+            abi_operands[1].blue(),
+            abi_operands[2].yellow(),
+        );
+    } else if operands.len() == 1 {
+        // the cache-block ops take the address register alone
+        instr = format!("{} ({})", d.mnemonic.red().bold(), operands[0].yellow());
+        abi_instr = format!("{} ({})", d.mnemonic.red().bold(), abi_operands[0].yellow());
+    } else if operands.len() == 2 {
+        // the unary bit-manipulation ops have a function code for an immediate
+        instr = format!(
+            "{} {}, {}",
+            d.mnemonic.red().bold(),
+            operands[0].green(),
+            operands[1].yellow(),
+        );
+        abi_instr = format!(
+            "{} {}, {}",
+            d.mnemonic.red().bold(),
+            abi_operands[0].green(),
+            abi_operands[1].yellow(),
+        );
+    } else {
+        instr = format!(
+            "{} {}, {}, {}",
+            d.mnemonic.red().bold(),
+            operands[0].green(),
+            operands[1].yellow(),
+            operands[2].blue(),
+        );
+        abi_instr = format!(
+            "{} {}, {}, {}",
+            d.mnemonic.red().bold(),
+            abi_operands[0].green(),
+            abi_operands[1].yellow(),
+            abi_operands[2].blue(),
+        );
+    }
+    // aes64ks1i keeps its round number in the low four bits of a function code
+    let imm = if d.mnemonic == "aes64ks1i" {
+        let funct = format!("{:08b}", i.imm() >> 4).red();
+        format!("{} {}", funct, format!("{:04b}", i.imm() & 0xf).blue())
+    } else if operands.len() < 3 {
+        format!("{:012b}", i.imm()).red().to_string()
+    } else {
+        format!("{:012b}", i.imm()).blue().to_string()
+    };
+    let fields = [
+        imm,
+        format!("{:05b}", i.rs1()).yellow().to_string(),
+        format!("{:03b}", i.funct3()).red().to_string(),
+        format!("{:05b}", i.rd()).green().to_string(),
+        format!("{:07b}", i.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_s_type(d: &EncodedInstruction, s: &SType) -> (String, String, String, String) {
+    let format = |operands: Vec<String>| match operands.as_slice() {
+        [offset, base] => format!(
+            "{} {}({})",
+            d.mnemonic.red().bold(),
+            offset.blue(),
+            base.yellow()
+        ),
+        [rs2, offset, base] => format!(
+            "{} {}, {}({})",
+            d.mnemonic.red().bold(),
+            rs2.green(),
+            offset.blue(),
+            base.yellow(),
+        ),
+        _ => unreachable!(),
+    };
+    let instr = format(operands_to_strings(&d.operands));
+    let abi_instr = format(operands_to_abi(&d.operands));
+    // the prefetch hints keep a function code in rs2 and x0 in the low offset bits
+    let prefetch = d.operands.len() == 2;
+    let rs2 = format!("{:05b}", s.rs2());
+    let imm4_0 = format!("{:05b}", s.imm4_0());
+    let fields = [
+        format!("{:07b}", s.imm11_5()).blue().to_string(),
+        if prefetch { rs2.red() } else { rs2.green() }.to_string(),
+        format!("{:05b}", s.rs1()).yellow().to_string(),
+        format!("{:03b}", s.funct3()).red().to_string(),
+        if prefetch {
+            imm4_0
+        } else {
+            imm4_0.blue().to_string()
+        },
+        format!("{:07b}", s.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", s.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_b_type(d: &EncodedInstruction, b: &BType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let instr = format!(
+        "{} {}, {}, {}",
+        d.mnemonic.red().bold(),
+        operands[0].green(),
+        operands[1].yellow(),
+        operands[2].blue(),
+    );
+    let abi_instr = format!(
+        "{} {}, {}, {}",
+        d.mnemonic.red().bold(),
+        abi_operands[0].green(),
+        abi_operands[1].yellow(),
+        abi_operands[2].blue(),
+    );
+    let fields = [
+        format!("{:01b}", b.imm12() as u32).blue().to_string(),
+        format!("{:06b}", b.imm10_5()).blue().to_string(),
+        format!("{:05b}", b.rs2()).yellow().to_string(),
+        format!("{:05b}", b.rs1()).green().to_string(),
+        format!("{:03b}", b.funct3()).red().to_string(),
+        format!("{:04b}", b.imm4_1()).blue().to_string(),
+        format!("{:01b}", b.imm11() as u32).blue().to_string(),
+        format!("{:07b}", b.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", b.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_u_type(d: &EncodedInstruction, u: &UType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let instr = format!(
+        "{} {}, {}",
+        d.mnemonic.red().bold(),
+        operands[0].green(),
+        operands[1].blue(),
+    );
+    let abi_instr = format!(
+        "{} {}, {}",
+        d.mnemonic.red().bold(),
+        abi_operands[0].green(),
+        abi_operands[1].blue(),
+    );
+    let fields = [
+        format!("{:020b}", u.imm()).blue().to_string(),
+        format!("{:05b}", u.rd()).green().to_string(),
+        format!("{:07b}", u.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", u.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_j_type(d: &EncodedInstruction, j: &JType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let instr = format!(
+        "{} {}, {}",
+        d.mnemonic.red().bold(),
+        operands[0].green(),
+        operands[1].blue(),
+    );
+    let abi_instr = format!(
+        "{} {}, {}",
+        d.mnemonic.red().bold(),
+        abi_operands[0].green(),
+        abi_operands[1].blue(),
+    );
+    let fields = [
+        format!("{:01b}", j.imm20() as u32).blue().to_string(),
+        format!("{:010b}", j.imm10_1()).blue().to_string(),
+        format!("{:01b}", j.imm11() as u32).blue().to_string(),
+        format!("{:008b}", j.imm19_12()).blue().to_string(),
+        format!("{:05b}", j.rd()).green().to_string(),
+        format!("{:07b}", j.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", j.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_csr_type(d: &EncodedInstruction, c: &CSRType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let hex = format!("0x{:08x}", c.0).bold().to_string();
+    let (instr, abi_instr);
+    // ecall and ebreak have no operands
+    if operands.is_empty() {
+        instr = format!("{}", d.mnemonic.red().bold());
+        abi_instr = instr.clone();
+    } else {
+        instr = format!(
+            "{} {}, {}, {}",
+            d.mnemonic.red().bold(),
+            operands[0].green(),
+            operands[1].blue(),
+            operands[2].yellow(),
+        );
+        abi_instr = format!(
+            "{} {}, {}, {}",
+            d.mnemonic.red().bold(),
+            abi_operands[0].green(),
+            abi_operands[1].blue(),
+            abi_operands[2].yellow(),
+        );
+    }
+    let fields = [
+        format!("{:012b}", c.csr()).blue().to_string(),
+        format!("{:05b}", c.rs1()).yellow().to_string(),
+        format!("{:03b}", c.funct3()).red().to_string(),
+        format!("{:05b}", c.rd()).green().to_string(),
+        format!("{:07b}", c.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_fence_type(d: &EncodedInstruction, f: &FenceType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let hex = format!("0x{:08x}", f.0).bold().to_string();
+    // fence.i, fence.tso and pause carry no operands, the ABI view is identical
+    let instr = if operands.is_empty() {
+        format!("{}", d.mnemonic.red().bold())
+    } else {
+        format!(
+            "{} {}, {}",
+            d.mnemonic.red().bold(),
+            operands[0].yellow(),
+            operands[1].blue(),
+        )
+    };
+    let abi_instr = instr.clone();
+    let fields = [
+        format!("{:04b}", f.fm()).red().to_string(),
+        format!("{:04b}", f.pred()).yellow().to_string(),
+        format!("{:04b}", f.succ()).blue().to_string(),
+        format!("{:05b}", f.rs1()),
+        format!("{:03b}", f.funct3()).red().to_string(),
+        format!("{:05b}", f.rd()),
+        format!("{:07b}", f.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_amo_type(d: &EncodedInstruction, a: &AMOType) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let hex = format!("0x{:08x}", a.0).bold().to_string();
+    let (instr, abi_instr);
+    if d.mnemonic.starts_with("lr.") {
+        instr = format!(
+            "{} {}, ({})",
+            d.mnemonic.red().bold(),
+            operands[0].green(),
+            operands[1].yellow(),
+        );
+        abi_instr = format!(
+            "{} {}, ({})",
+            d.mnemonic.red().bold(),
+            abi_operands[0].green(),
+            abi_operands[1].yellow(),
+        );
+    } else {
+        instr = format!(
+            "{} {}, {}, ({})",
+            d.mnemonic.red().bold(),
+            operands[0].green(),
+            operands[1].blue(),
+            operands[2].yellow(),
+        );
+        abi_instr = format!(
+            "{} {}, {}, ({})",
+            d.mnemonic.red().bold(),
+            abi_operands[0].green(),
+            abi_operands[1].blue(),
+            abi_operands[2].yellow(),
+        );
+    }
+    let fields = [
+        format!("{:05b}", a.funct5()).red().to_string(),
+        format!("{:01b}", a.aq() as u32).cyan().to_string(),
+        format!("{:01b}", a.rl() as u32).magenta().to_string(),
+        format!("{:05b}", a.rs2()).blue().to_string(),
+        format!("{:05b}", a.rs1()).yellow().to_string(),
+        format!("{:03b}", a.funct3()).red().to_string(),
+        format!("{:05b}", a.rd()).green().to_string(),
+        format!("{:07b}", a.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_r4_type(d: &EncodedInstruction, r: &R4Type) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let instr = format_register_operands(&d.mnemonic, &operands);
+    let abi_instr = format_register_operands(&d.mnemonic, &abi_operands);
+    let fields = [
+        format!("{:05b}", r.rs3()).magenta().to_string(),
+        format!("{:02b}", r.fmt()).bright_red().to_string(),
+        format!("{:05b}", r.rs2()).blue().to_string(),
+        format!("{:05b}", r.rs1()).yellow().to_string(),
+        format!("{:03b}", r.rm()).cyan().to_string(),
+        format!("{:05b}", r.rd()).green().to_string(),
+        format!("{:07b}", r.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", r.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+// the byte select is cyan like the other small selector fields
+fn format_byte_select_type(
+    d: &EncodedInstruction,
+    b: &ByteSelectType,
+) -> (String, String, String, String) {
+    let format = |operands: Vec<String>| {
+        let (regs, bs) = operands.split_at(3);
+        let regs = format_register_operands(&d.mnemonic, regs);
+        format!("{}, {}", regs, bs[0].cyan())
+    };
+    let instr = format(operands_to_strings(&d.operands));
+    let abi_instr = format(operands_to_abi(&d.operands));
+    let fields = [
+        format!("{:02b}", b.bs()).cyan().to_string(),
+        format!("{:05b}", b.funct5()).red().to_string(),
+        format!("{:05b}", b.rs2()).blue().to_string(),
+        format!("{:05b}", b.rs1()).yellow().to_string(),
+        format!("{:03b}", b.funct3()).red().to_string(),
+        format!("{:05b}", b.rd()).green().to_string(),
+        format!("{:07b}", b.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", b.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+fn slot_color(slot: Slot) -> Color {
+    match slot {
+        Slot::Vd | Slot::Rd | Slot::Fd => Color::Green,
+        Slot::Vs1 | Slot::Rs1 | Slot::Fs1 | Slot::Base => Color::Yellow,
+        Slot::Carry | Slot::Mask => Color::Cyan,
+        _ => Color::Blue,
+    }
+}
+
+// vector operands are coloured by the field they come from, vset{i}vl{i} by position
+fn vector_colors(d: &EncodedInstruction) -> Vec<Color> {
+    match instruction_layout(&d.instr) {
+        Some(layout) => layout.iter().map(|&slot| slot_color(slot)).collect(),
+        None => vec![Color::Green, Color::Yellow, Color::Blue],
+    }
+}
+
+// loads and stores put their base register in parentheses, it always comes second
+fn format_vector_operands(d: &EncodedInstruction, operands: Vec<String>) -> String {
+    let memory = matches!(d.instr, Instruction::VectorMem(_));
+    let operands = operands
+        .iter()
+        .zip(vector_colors(d))
+        .enumerate()
+        .map(|(idx, (op, color))| match idx {
+            1 if memory => format!("({})", op.color(color)),
+            _ => op.color(color).to_string(),
+        })
+        .collect::<Vec<_>>();
+    format!("{} {}", d.mnemonic.red().bold(), operands.join(", "))
+}
+
+// vm is cyan like the mask operand, vs1 and vs2 are red when they hold a function code.
+// vset{i}vl{i} split off the bits that pick the instruction and show the vtype immediate
+fn format_vector_type(d: &EncodedInstruction, v: &VectorType) -> (String, String, String, String) {
+    let instr = format_vector_operands(d, operands_to_strings(&d.operands));
+    let abi_instr = format_vector_operands(d, operands_to_abi(&d.operands));
+    let vs2 = format!("{:05b}", v.vs2());
+    let vs1 = format!("{:05b}", v.vs1());
+    let mut fields = match instruction_layout(&d.instr) {
+        Some(layout) => {
+            let vs1_color = layout
+                .iter()
+                .find(|slot| {
+                    matches!(
+                        slot,
+                        Slot::Vs1 | Slot::Rs1 | Slot::Fs1 | Slot::Simm | Slot::Uimm | Slot::Uimm6
+                    )
+                })
+                .map_or(Color::Red, |&slot| slot_color(slot));
+            let vs2_color = match layout.contains(&Slot::Vs2) {
+                true => Color::Blue,
+                false => Color::Red,
+            };
+            let funct6 = format!("{:06b}", v.funct6());
+            let funct6 = match layout.contains(&Slot::Uimm6) {
+                // the top bit of the vror.vi shift amount
+                true => format!("{} {}", funct6[..5].red(), funct6[5..].blue()),
+                false => funct6.red().to_string(),
+            };
+            vec![
+                funct6,
+                format!("{:01b}", v.vm() as u32).cyan().to_string(),
+                vs2.color(vs2_color).to_string(),
+                vs1.color(vs1_color).to_string(),
+            ]
+        }
+        None if v.0 >> 30 == 0b11 => vec![
+            "11".red().to_string(),
+            format!("{:010b}", (v.0 >> 20) & 0x3ff).blue().to_string(),
+            vs1.yellow().to_string(),
+        ],
+        None if v.0 >> 31 == 0 => vec![
+            "0".red().to_string(),
+            format!("{:011b}", (v.0 >> 20) & 0x7ff).blue().to_string(),
+            vs1.yellow().to_string(),
+        ],
+        None => vec![
+            format!("{:07b}", v.0 >> 25).red().to_string(),
+            vs2.blue().to_string(),
+            vs1.yellow().to_string(),
+        ],
+    };
+    fields.extend([
+        format!("{:03b}", v.funct3()).red().to_string(),
+        format!("{:05b}", v.vd()).green().to_string(),
+        format!("{:07b}", v.opcode()).red().to_string(),
+    ]);
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", v.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+// nf, mew and mop pick the instruction with the lumop/sumop in rs2 of unit-stride accesses
+fn format_vector_mem_type(
+    d: &EncodedInstruction,
+    v: &VectorMemType,
+) -> (String, String, String, String) {
+    let instr = format_vector_operands(d, operands_to_strings(&d.operands));
+    let abi_instr = format_vector_operands(d, operands_to_abi(&d.operands));
+    let rs2 = format!("{:05b}", v.rs2());
+    let rs2 = match v.mop() {
+        0b00 => rs2.red(),
+        _ => rs2.blue(),
+    };
+    let fields = [
+        format!("{:03b}", v.nf()).red().to_string(),
+        format!("{:01b}", v.mew() as u32).red().to_string(),
+        format!("{:02b}", v.mop()).red().to_string(),
+        format!("{:01b}", v.vm() as u32).cyan().to_string(),
+        rs2.to_string(),
+        format!("{:05b}", v.rs1()).yellow().to_string(),
+        format!("{:03b}", v.width()).red().to_string(),
+        format!("{:05b}", v.vd()).green().to_string(),
+        format!("{:07b}", v.opcode()).red().to_string(),
+    ];
+    let bits = fields.join(" ");
+    let hex = format!("0x{:08x}", v.0).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+// compressed operands are coloured by kind: the leading register green, other
+// registers yellow and immediates blue
+fn format_compressed_operands(mnemonic: &str, operands: &[String], memory: bool) -> String {
+    let colored = operands
+        .iter()
+        .enumerate()
+        .map(|(idx, op)| {
+            if parse_immediate(op).is_ok() {
+                op.blue().to_string()
+            } else if idx == 0 {
+                op.green().to_string()
+            } else {
+                op.yellow().to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    match colored.as_slice() {
+        [] => format!("{}", mnemonic.red().bold()),
+        [reg, offset, base] if memory => {
+            format!("{} {}, {}({})", mnemonic.red().bold(), reg, offset, base)
+        }
+        ops => format!("{} {}", mnemonic.red().bold(), ops.join(", ")),
+    }
+}
+
+fn format_compressed(
+    d: &EncodedInstruction,
+    raw: u16,
+    fields: &[String],
+    memory: bool,
+) -> (String, String, String, String) {
+    let operands = operands_to_strings(&d.operands);
+    let abi_operands = operands_to_abi(&d.operands);
+    let instr = format_compressed_operands(&d.mnemonic, &operands, memory);
+    let abi_instr = format_compressed_operands(&d.mnemonic, &abi_operands, memory);
+    let bits = fields.join(" ");
+    let hex = format!("0x{:04x}", raw).bold().to_string();
+    (instr, abi_instr, bits, hex)
+}
+
+fn format_cr_type(d: &EncodedInstruction, c: &CRType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:04b}", c.funct4()).red().to_string(),
+        format!("{:05b}", c.rd()).green().to_string(),
+        format!("{:05b}", c.rs2()).yellow().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_ci_type(d: &EncodedInstruction, c: &CIType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:03b}", c.funct3()).red().to_string(),
+        format!("{:01b}", c.imm12()).blue().to_string(),
+        format!("{:05b}", c.rd()).green().to_string(),
+        format!("{:05b}", c.imm6_2()).blue().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, d.is_memory_access())
+}
+
+fn format_css_type(d: &EncodedInstruction, c: &CSSType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:03b}", c.funct3()).red().to_string(),
+        format!("{:06b}", c.imm()).blue().to_string(),
+        format!("{:05b}", c.rs2()).green().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, true)
+}
+
+fn format_ciw_type(d: &EncodedInstruction, c: &CIWType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:03b}", c.funct3()).red().to_string(),
+        format!("{:08b}", c.imm()).blue().to_string(),
+        format!("{:03b}", c.rd()).green().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_cl_type(d: &EncodedInstruction, c: &CLType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:03b}", c.funct3()).red().to_string(),
+        format!("{:03b}", c.imm12_10()).blue().to_string(),
+        format!("{:03b}", c.rs1()).yellow().to_string(),
+        format!("{:02b}", c.imm6_5()).blue().to_string(),
+        format!("{:03b}", c.rd()).green().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, true)
+}
+
+fn format_cs_type(d: &EncodedInstruction, c: &CSType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:03b}", c.funct3()).red().to_string(),
+        format!("{:03b}", c.imm12_10()).blue().to_string(),
+        format!("{:03b}", c.rs1()).yellow().to_string(),
+        format!("{:02b}", c.imm6_5()).blue().to_string(),
+        format!("{:03b}", c.rs2()).green().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, true)
+}
+
+fn format_ca_type(d: &EncodedInstruction, c: &CAType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:06b}", c.funct6()).red().to_string(),
+        format!("{:03b}", c.rd()).green().to_string(),
+        format!("{:02b}", c.funct2()).red().to_string(),
+        format!("{:03b}", c.rs2()).yellow().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_cb_type(d: &EncodedInstruction, c: &CBType) -> (String, String, String, String) {
+    let fields = if d.mnemonic.starts_with("c.b") {
+        vec![
+            format!("{:03b}", c.funct3()).red().to_string(),
+            format!("{:03b}", c.offset12_10()).blue().to_string(),
+            format!("{:03b}", c.rs1()).green().to_string(),
+            format!("{:05b}", c.offset6_2()).blue().to_string(),
+            format!("{:02b}", c.op()).red().to_string(),
+        ]
+    } else {
+        // c.srli, c.srai and c.andi keep a funct2 inside the offset bits
+        vec![
+            format!("{:03b}", c.funct3()).red().to_string(),
+            format!("{:01b}", c.offset12_10() >> 2).blue().to_string(),
+            format!("{:02b}", c.offset12_10() & 0x3).red().to_string(),
+            format!("{:03b}", c.rs1()).green().to_string(),
+            format!("{:05b}", c.offset6_2()).blue().to_string(),
+            format!("{:02b}", c.op()).red().to_string(),
+        ]
+    };
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_cj_type(d: &EncodedInstruction, c: &CJType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:03b}", c.funct3()).red().to_string(),
+        format!("{:011b}", c.target()).blue().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_clb_type(d: &EncodedInstruction, c: &CLBType) -> (String, String, String, String) {
+    // bit 6 is uimm[0] of c.lbu, it tells c.lh from c.lhu
+    let bit6 = format!("{:01b}", c.imm() >> 1);
+    let fields = [
+        format!("{:06b}", c.funct6()).red().to_string(),
+        format!("{:03b}", c.rs1()).yellow().to_string(),
+        if d.mnemonic == "c.lbu" {
+            bit6.blue()
+        } else {
+            bit6.red()
+        }
+        .to_string(),
+        format!("{:01b}", c.imm() & 1).blue().to_string(),
+        format!("{:03b}", c.rd()).green().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, true)
+}
+
+fn format_csb_type(d: &EncodedInstruction, c: &CSBType) -> (String, String, String, String) {
+    let bit6 = format!("{:01b}", c.imm() >> 1);
+    let fields = [
+        format!("{:06b}", c.funct6()).red().to_string(),
+        format!("{:03b}", c.rs1()).yellow().to_string(),
+        if d.mnemonic == "c.sb" {
+            bit6.blue()
+        } else {
+            bit6.red()
+        }
+        .to_string(),
+        format!("{:01b}", c.imm() & 1).blue().to_string(),
+        format!("{:03b}", c.rs2()).green().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, true)
+}
+
+fn format_cu_type(d: &EncodedInstruction, c: &CUType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:06b}", c.funct6()).red().to_string(),
+        format!("{:03b}", c.rd()).green().to_string(),
+        format!("{:05b}", c.funct5()).red().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_cmpp_type(d: &EncodedInstruction, c: &CMPPType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:06b}", c.funct6()).red().to_string(),
+        format!("{:02b}", c.funct2()).red().to_string(),
+        format!("{:04b}", c.rlist()).green().to_string(),
+        format!("{:02b}", c.spimm()).blue().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_cmmv_type(d: &EncodedInstruction, c: &CMMVType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:06b}", c.funct6()).red().to_string(),
+        format!("{:03b}", c.r1s()).green().to_string(),
+        format!("{:02b}", c.funct2()).red().to_string(),
+        format!("{:03b}", c.r2s()).yellow().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+fn format_cmjt_type(d: &EncodedInstruction, c: &CMJTType) -> (String, String, String, String) {
+    let fields = [
+        format!("{:06b}", c.funct6()).red().to_string(),
+        format!("{:08b}", c.index()).blue().to_string(),
+        format!("{:02b}", c.op()).red().to_string(),
+    ];
+    format_compressed(d, c.0, &fields, false)
+}
+
+// custom instructions split their BIN line at the fields of their definition, an operand
+// field has the colour of its operand and the fixed fields are red
+fn format_custom_type(d: &EncodedInstruction, c: &CustomType) -> (String, String, String, String) {
+    let colors = [
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::BrightGreen,
+    ];
+    let format = |operands: Vec<String>| {
+        let operands = operands
+            .iter()
+            .zip(colors)
+            .map(|(op, color)| op.color(color).to_string())
+            .collect::<Vec<_>>();
+        match operands.as_slice() {
+            [] => format!("{}", d.mnemonic.red().bold()),
+            ops => format!("{} {}", d.mnemonic.red().bold(), ops.join(", ")),
+        }
+    };
+    // the fields from bit 31 down, each ends at the next lowest bit of the layout
+    let mut bits = Vec::new();
+    let mut hi = 32;
+    for lo in (0..32).rev().filter(|lo| c.field_lsbs & 1 << lo != 0) {
+        let width = hi - lo;
+        let value = (c.word >> lo) & (u32::MAX >> (32 - width));
+        let field = format!("{:0width$b}", value, width = width as usize);
+        let operand = c.operand_lsbs[..d.operands.len()]
+            .iter()
+            .position(|&lsb| lsb as u32 == lo);
+        bits.push(match operand {
+            Some(idx) => field.color(colors[idx]).to_string(),
+            None => field.red().to_string(),
+        });
+        hi = lo;
+    }
+    let bits = bits.join(" ");
+    let hex = format!("0x{:08x}", c.word).bold().to_string();
+
+    (
+        format(operands_to_strings(&d.operands)),
+        format(operands_to_abi(&d.operands)),
+        bits,
+        hex,
+    )
+}
+
+fn format_instruction(d: &EncodedInstruction) -> (String, String, String, String) {
+    match &d.instr {
+        Instruction::RType(r) if r.opcode() == 0b1110011 => format_privileged(d, r),
+        Instruction::RType(r) => format_r_type(d, r),
+        Instruction::IType(i) => format_i_type(d, i),
+        Instruction::SType(s) => format_s_type(d, s),
+        Instruction::BType(b) => format_b_type(d, b),
+        Instruction::UType(u) => format_u_type(d, u),
+        Instruction::JType(j) => format_j_type(d, j),
+        Instruction::CSRType(c) => format_csr_type(d, c),
+        Instruction::Fence(f) => format_fence_type(d, f),
+        Instruction::Amo(a) => format_amo_type(d, a),
+        Instruction::R4Type(r) => format_r4_type(d, r),
+        Instruction::ByteSelect(b) => format_byte_select_type(d, b),
+        Instruction::Vector(v) => format_vector_type(d, v),
+        Instruction::VectorMem(v) => format_vector_mem_type(d, v),
+        Instruction::CRType(c) => format_cr_type(d, c),
+        Instruction::CIType(c) => format_ci_type(d, c),
+        Instruction::CSSType(c) => format_css_type(d, c),
+        Instruction::CIWType(c) => format_ciw_type(d, c),
+        Instruction::CLType(c) => format_cl_type(d, c),
+        Instruction::CSType(c) => format_cs_type(d, c),
+        Instruction::CAType(c) => format_ca_type(d, c),
+        Instruction::CBType(c) => format_cb_type(d, c),
+        Instruction::CJType(c) => format_cj_type(d, c),
+        Instruction::CLBType(c) => format_clb_type(d, c),
+        Instruction::CSBType(c) => format_csb_type(d, c),
+        Instruction::CUType(c) => format_cu_type(d, c),
+        Instruction::CMPPType(c) => format_cmpp_type(d, c),
+        Instruction::CMMVType(c) => format_cmmv_type(d, c),
+        Instruction::CMJTType(c) => format_cmjt_type(d, c),
+        Instruction::Custom(c) => format_custom_type(d, c),
+    }
+}
+
+// an alias keeps a subset of the operands in order, each keeps the colour of the field
+// it came from
+fn format_alias(d: &EncodedInstruction, alias: &EncodedInstruction) -> (String, String) {
+    let colors = match d.instr {
+        Instruction::CSRType(_) => vec![Color::Green, Color::Blue, Color::Yellow],
+        Instruction::RType(r) if r.opcode() == 0b1110011 => {
+            vec![Color::Yellow, Color::Blue, Color::Blue]
+        }
+        Instruction::IType(_) if d.is_memory_access() => {
+            vec![Color::Green, Color::Blue, Color::Yellow]
+        }
+        Instruction::Vector(_) | Instruction::VectorMem(_) => vector_colors(d),
+        _ => vec![Color::Green, Color::Yellow, Color::Blue],
+    };
+    let mut fields = d.operands.iter().zip(colors);
+    let colored = alias
+        .operands
+        .iter()
+        .map(|op| {
+            let color = fields.find(|(field, _)| *field == op).map(|(_, c)| c);
+            (op, color.unwrap_or(Color::Blue))
+        })
+        .collect::<Vec<_>>();
+    let format = |abi: bool| {
+        let ops = colored
+            .iter()
+            .map(|(op, color)| match abi {
+                true => op.abi().to_string().color(*color).to_string(),
+                false => op.to_string().color(*color).to_string(),
+            })
+            .collect::<Vec<_>>();
+        match ops.as_slice() {
+            [] => format!("{}", alias.mnemonic.red().bold()),
+            ops => format!("{} {}", alias.mnemonic.red().bold(), ops.join(", ")),
+        }
+    };
+    (format(false), format(true))
+}
+
+// the ASM and ABI lines show the alias when there is one
+pub fn print_encoded_instruction(d: &EncodedInstruction, alias: Option<&EncodedInstruction>) {
+    let mut out = format_instruction(d);
+    if let Some(alias) = alias {
+        (out.0, out.1) = format_alias(d, alias);
+    }
+
+    println!("ASM: {}", out.0);
+    println!("ABI: {}", out.1);
+    println!("BIN: {}", out.2);
+    println!("HEX: {}", out.3);
+    if let Some(csr) = d.operands.iter().find_map(|op| match op {
+        Operand::Csr(csr) => Some(*csr),
+        _ => None,
+    }) {
+        println!("CSR: {}", format_csr_access(csr));
+    }
+}
+
+// e.g. `mstatus 0x300, machine, read/write`, unknown CSRs only have the address
+fn format_csr_access(csr: CsrAddr) -> String {
+    let addr = format!("0x{:03x}", csr.0);
+    let mut out = match csr.name() {
+        Some(name) => format!("{} {}", name.to_string().blue(), addr),
+        None => addr.blue().to_string(),
+    };
+    out.push_str(&format!(", {}", csr.privilege()));
+    if csr.is_debug_only() {
+        out.push_str(" (debug mode)");
+    }
+    if csr.is_read_only() {
+        out.push_str(", read-only");
+    } else {
+        out.push_str(", read/write");
+    }
+    out
+}
+
+// one-line summary of a related encoding, e.g. the expansion of a compressed instruction
+pub fn print_related_instruction(
+    label: &str,
+    d: &EncodedInstruction,
+    alias: Option<&EncodedInstruction>,
+) {
+    let mut out = format_instruction(d);
+    if let Some(alias) = alias {
+        out.0 = format_alias(d, alias).0;
+    }
+    println!("{}: {} ({})", label, out.0, out.3);
+}
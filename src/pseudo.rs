@@ -0,0 +1,388 @@
+use crate::error::{Error, Result};
+use crate::format::Xlen;
+use crate::util::{fp_fmt_code, parse_immediate, parse_immediate64, sign_extend};
+
+// expands a pseudo-instruction into the text of the base instructions it stands for,
+// `None` when the mnemonic and operand count are not a pseudo-instruction. Offsets of
+// j, call, tail and la are pc-relative numbers, there are no symbols
+pub fn expand(mnemonic: &str, operands: &[String], xlen: Xlen) -> Result<Option<Vec<String>>> {
+    let base = match (mnemonic, operands) {
+        ("nop", []) => vec!["addi x0, x0, 0".to_string()],
+        ("unimp", []) => vec!["csrrw x0, cycle, x0".to_string()],
+        ("li", [rd, imm]) => load_immediate(rd, parse_immediate64(imm)?, xlen)?,
+        ("la" | "lla", [rd, offset]) => {
+            let (hi, lo) = split_pc_offset(parse_immediate(offset)?, xlen)?;
+            vec![
+                format!("auipc {}, {}", rd, hi),
+                format!("addi {}, {}, {}", rd, rd, lo),
+            ]
+        }
+        ("mv", [rd, rs]) => vec![format!("addi {}, {}, 0", rd, rs)],
+        ("not", [rd, rs]) => vec![format!("xori {}, {}, -1", rd, rs)],
+        ("neg", [rd, rs]) => vec![format!("sub {}, x0, {}", rd, rs)],
+        ("negw", [rd, rs]) => vec![format!("subw {}, x0, {}", rd, rs)],
+        ("sext.w", [rd, rs]) => vec![format!("addiw {}, {}, 0", rd, rs)],
+        ("zext.w", [rd, rs]) => vec![format!("add.uw {}, {}, x0", rd, rs)],
+        ("seqz", [rd, rs]) => vec![format!("sltiu {}, {}, 1", rd, rs)],
+        ("snez", [rd, rs]) => vec![format!("sltu {}, x0, {}", rd, rs)],
+        ("sltz", [rd, rs]) => vec![format!("slt {}, {}, x0", rd, rs)],
+        ("sgtz", [rd, rs]) => vec![format!("slt {}, x0, {}", rd, rs)],
+        ("beqz", [rs, offset]) => vec![format!("beq {}, x0, {}", rs, offset)],
+        ("bnez", [rs, offset]) => vec![format!("bne {}, x0, {}", rs, offset)],
+        ("blez", [rs, offset]) => vec![format!("bge x0, {}, {}", rs, offset)],
+        ("bgez", [rs, offset]) => vec![format!("bge {}, x0, {}", rs, offset)],
+        ("bltz", [rs, offset]) => vec![format!("blt {}, x0, {}", rs, offset)],
+        ("bgtz", [rs, offset]) => vec![format!("blt x0, {}, {}", rs, offset)],
+        // the swapped comparisons
+        ("bgt", [rs, rt, offset]) => vec![format!("blt {}, {}, {}", rt, rs, offset)],
+        ("ble", [rs, rt, offset]) => vec![format!("bge {}, {}, {}", rt, rs, offset)],
+        ("bgtu", [rs, rt, offset]) => vec![format!("bltu {}, {}, {}", rt, rs, offset)],
+        ("bleu", [rs, rt, offset]) => vec![format!("bgeu {}, {}, {}", rt, rs, offset)],
+        ("j", [offset]) => vec![format!("jal x0, {}", offset)],
+        ("jal", [offset]) => vec![format!("jal x1, {}", offset)],
+        ("jr", [rs]) => vec![format!("jalr x0, 0({})", rs)],
+        ("jalr", [rs]) => vec![format!("jalr x1, 0({})", rs)],
+        ("ret", []) => vec!["jalr x0, 0(x1)".to_string()],
+        ("call", [offset]) => far_jump("x1", "x1", parse_immediate(offset)?, xlen)?,
+        ("tail", [offset]) => far_jump("x0", "x6", parse_immediate(offset)?, xlen)?,
+        ("csrr", [rd, csr]) => vec![format!("csrrs {}, {}, x0", rd, csr)],
+        ("csrw", [csr, rs]) => vec![format!("csrrw x0, {}, {}", csr, rs)],
+        ("csrs", [csr, rs]) => vec![format!("csrrs x0, {}, {}", csr, rs)],
+        ("csrc", [csr, rs]) => vec![format!("csrrc x0, {}, {}", csr, rs)],
+        ("csrwi", [csr, imm]) => vec![format!("csrrwi x0, {}, {}", csr, imm)],
+        ("csrsi", [csr, imm]) => vec![format!("csrrsi x0, {}, {}", csr, imm)],
+        ("csrci", [csr, imm]) => vec![format!("csrrci x0, {}, {}", csr, imm)],
+        ("rdinstret", [rd]) => vec![format!("csrrs {}, instret, x0", rd)],
+        ("rdcycle", [rd]) => vec![format!("csrrs {}, cycle, x0", rd)],
+        ("rdtime", [rd]) => vec![format!("csrrs {}, time, x0", rd)],
+        ("rdinstreth", [rd]) => vec![format!("csrrs {}, instreth, x0", rd)],
+        ("rdcycleh", [rd]) => vec![format!("csrrs {}, cycleh, x0", rd)],
+        ("rdtimeh", [rd]) => vec![format!("csrrs {}, timeh, x0", rd)],
+        ("frcsr", [rd]) => vec![format!("csrrs {}, fcsr, x0", rd)],
+        ("fscsr", [rs]) => vec![format!("csrrw x0, fcsr, {}", rs)],
+        ("fscsr", [rd, rs]) => vec![format!("csrrw {}, fcsr, {}", rd, rs)],
+        ("frrm", [rd]) => vec![format!("csrrs {}, frm, x0", rd)],
+        ("fsrm", [rs]) => vec![format!("csrrw x0, frm, {}", rs)],
+        ("fsrm", [rd, rs]) => vec![format!("csrrw {}, frm, {}", rd, rs)],
+        ("frflags", [rd]) => vec![format!("csrrs {}, fflags, x0", rd)],
+        ("fsflags", [rs]) => vec![format!("csrrw x0, fflags, {}", rs)],
+        ("fsflags", [rd, rs]) => vec![format!("csrrw {}, fflags, {}", rd, rs)],
+        // the vector pseudo-instructions pass an optional `v0.t` through
+        ("vneg.v", [vd, vs, mask @ ..]) => {
+            vec![masked(format!("vrsub.vx {}, {}, x0", vd, vs), mask)]
+        }
+        ("vnot.v", [vd, vs, mask @ ..]) => {
+            vec![masked(format!("vxor.vi {}, {}, -1", vd, vs), mask)]
+        }
+        ("vwcvt.x.x.v", [vd, vs, mask @ ..]) => {
+            vec![masked(format!("vwadd.vx {}, {}, x0", vd, vs), mask)]
+        }
+        ("vwcvtu.x.x.v", [vd, vs, mask @ ..]) => {
+            vec![masked(format!("vwaddu.vx {}, {}, x0", vd, vs), mask)]
+        }
+        ("vncvt.x.x.w", [vd, vs, mask @ ..]) => {
+            vec![masked(format!("vnsrl.wx {}, {}, x0", vd, vs), mask)]
+        }
+        ("vfneg.v", [vd, vs, mask @ ..]) => {
+            vec![masked(format!("vfsgnjn.vv {}, {}, {}", vd, vs, vs), mask)]
+        }
+        ("vfabs.v", [vd, vs, mask @ ..]) => {
+            vec![masked(format!("vfsgnjx.vv {}, {}, {}", vd, vs, vs), mask)]
+        }
+        ("vmmv.m", [vd, vs]) => vec![format!("vmand.mm {}, {}, {}", vd, vs, vs)],
+        ("vmnot.m", [vd, vs]) => vec![format!("vmnand.mm {}, {}, {}", vd, vs, vs)],
+        ("vmclr.m", [vd]) => vec![format!("vmxor.mm {}, {}, {}", vd, vd, vd)],
+        ("vmset.m", [vd]) => vec![format!("vmxnor.mm {}, {}, {}", vd, vd, vd)],
+        // the swapped vector comparisons
+        (m, [vd, va, vb, mask @ ..]) if swapped_vector_compare(m).is_some() => {
+            let base = swapped_vector_compare(m).unwrap();
+            vec![masked(format!("{} {}, {}, {}", base, vd, vb, va), mask)]
+        }
+        (m, []) if is_vma_fence(m) => vec![format!("{} x0, x0", m)],
+        (m, [rs]) if is_vma_fence(m) => vec![format!("{} {}, x0", m, rs)],
+        (m, [rd, rs]) if is_fp_sign_pseudo(m) => {
+            let (name, fmt) = m.split_once('.').unwrap();
+            let base = match name {
+                "fmv" => "fsgnj",
+                "fneg" => "fsgnjn",
+                _ => "fsgnjx",
+            };
+            vec![format!("{}.{} {}, {}, {}", base, fmt, rd, rs, rs)]
+        }
+        (m, _) if is_pseudo(m) => return Err(Error::InvalidFormat),
+        _ => return Ok(None),
+    };
+    Ok(Some(base))
+}
+
+// mnemonics that only exist as pseudo-instructions, for reporting a wrong operand count
+fn is_pseudo(mnemonic: &str) -> bool {
+    #[rustfmt::skip]
+    const PSEUDOS: &[&str] = &[
+        "nop", "unimp", "li", "la", "lla", "mv", "not", "neg", "negw", "sext.w", "zext.w", "seqz",
+        "snez", "sltz", "sgtz", "beqz", "bnez", "blez", "bgez", "bltz", "bgtz", "bgt", "ble",
+        "bgtu", "bleu", "j", "jr", "ret", "call", "tail", "csrr", "csrw", "csrs", "csrc", "csrwi",
+        "csrsi", "csrci", "rdinstret", "rdcycle", "rdtime", "rdinstreth", "rdcycleh", "rdtimeh",
+        "frcsr", "fscsr", "frrm", "fsrm", "frflags", "fsflags", "vneg.v", "vnot.v", "vwcvt.x.x.v",
+        "vwcvtu.x.x.v", "vncvt.x.x.w", "vfneg.v", "vfabs.v", "vmmv.m", "vmnot.m", "vmclr.m",
+        "vmset.m",
+    ];
+    PSEUDOS.contains(&mnemonic)
+        || is_fp_sign_pseudo(mnemonic)
+        || swapped_vector_compare(mnemonic).is_some()
+}
+
+// vmsgt.vv and friends are the opposite comparison with the sources swapped
+fn swapped_vector_compare(mnemonic: &str) -> Option<&'static str> {
+    match mnemonic {
+        "vmsgt.vv" => Some("vmslt.vv"),
+        "vmsgtu.vv" => Some("vmsltu.vv"),
+        "vmsge.vv" => Some("vmsle.vv"),
+        "vmsgeu.vv" => Some("vmsleu.vv"),
+        "vmfgt.vv" => Some("vmflt.vv"),
+        "vmfge.vv" => Some("vmfle.vv"),
+        _ => None,
+    }
+}
+
+// appends the optional `v0.t` of a masked vector pseudo-instruction
+fn masked(base: String, mask: &[String]) -> String {
+    mask.iter()
+        .fold(base, |line, op| format!("{}, {}", line, op))
+}
+
+// fmv, fneg and fabs are sign injections of a register with itself
+fn is_fp_sign_pseudo(mnemonic: &str) -> bool {
+    match mnemonic.split_once('.') {
+        Some(("fmv" | "fneg" | "fabs", fmt)) => fp_fmt_code(fmt).is_some(),
+        _ => false,
+    }
+}
+
+// the address and address-space operands of the translation fences default to x0
+fn is_vma_fence(mnemonic: &str) -> bool {
+    matches!(
+        mnemonic,
+        "sfence.vma" | "sinval.vma" | "hfence.vvma" | "hfence.gvma" | "hinval.vvma" | "hinval.gvma"
+    )
+}
+
+// auipc takes the upper 20 bits rounded so the sign-extended low 12 bits add back up.
+// RV32 addresses wrap at 32 bits, on RV64 auipc sign-extends and the last 2 KiB below
+// +2 GiB would need a carry into bit 31
+fn split_pc_offset(offset: i32, xlen: Xlen) -> Result<(u32, i32)> {
+    if xlen == Xlen::Rv64 && offset.checked_add(0x800).is_none() {
+        return Err(Error::ImmediateOutOfRange);
+    }
+    let hi = offset.wrapping_add(0x800) >> 12;
+    let lo = offset.wrapping_sub(hi << 12);
+    Ok(((hi as u32) & 0xfffff, lo))
+}
+
+fn far_jump(rd: &str, tmp: &str, offset: i32, xlen: Xlen) -> Result<Vec<String>> {
+    let (hi, lo) = split_pc_offset(offset, xlen)?;
+    Ok(vec![
+        format!("auipc {}, {}", tmp, hi),
+        format!("jalr {}, {}({})", rd, lo, tmp),
+    ])
+}
+
+// the lui/addi(w)/slli sequence the GNU and LLVM assemblers emit for li
+fn load_immediate(rd: &str, value: i64, xlen: Xlen) -> Result<Vec<String>> {
+    let value = match xlen {
+        // RV32 takes any 32-bit pattern, signed or unsigned
+        Xlen::Rv32 if (i32::MIN as i64..=u32::MAX as i64).contains(&value) => value as i32 as i64,
+        Xlen::Rv32 => return Err(Error::ImmediateOutOfRange),
+        Xlen::Rv64 => value,
+    };
+    let mut words = Vec::new();
+    load_immediate_into(&mut words, rd, value, xlen);
+    // a positive value may be shorter built without its leading zeros, restored by a
+    // final srli, with the shifted-in low bits set to ones or zeros
+    if xlen == Xlen::Rv64 && value > 0 && words.len() > 2 {
+        let zeros = value.leading_zeros();
+        let ones = (1u64 << zeros) - 1;
+        for shifted in [
+            (value << zeros) as u64 | ones,
+            (value << zeros) as u64 & !ones,
+        ] {
+            let mut candidate = Vec::new();
+            load_immediate_into(&mut candidate, rd, shifted as i64, xlen);
+            candidate.push(format!("srli {}, {}, {}", rd, rd, zeros));
+            if candidate.len() < words.len() {
+                words = candidate;
+            }
+        }
+    }
+    Ok(words)
+}
+
+fn load_immediate_into(words: &mut Vec<String>, rd: &str, value: i64, xlen: Xlen) {
+    let lo12 = sign_extend(value as u32 & 0xfff, 12);
+    if i32::try_from(value).is_ok() {
+        let hi20 = ((value as i32).wrapping_add(0x800) >> 12) & 0xfffff;
+        if hi20 != 0 {
+            words.push(format!("lui {}, {}", rd, hi20));
+        }
+        // on RV64 the sum must wrap at 32 bits like lui's sign extension
+        let add = if xlen == Xlen::Rv64 && hi20 != 0 {
+            "addiw"
+        } else {
+            "addi"
+        };
+        let src = if hi20 != 0 { rd } else { "x0" };
+        if lo12 != 0 || hi20 == 0 {
+            words.push(format!("{} {}, {}, {}", add, rd, src, lo12));
+        }
+        return;
+    }
+    // build the upper bits with the trailing zeros stripped, then shift them into place
+    let hi52 = (value.wrapping_add(0x800) as u64) >> 12;
+    let mut shift = 12 + hi52.trailing_zeros();
+    let mut upper = ((hi52 >> (shift - 12)) << shift) as i64 >> shift;
+    // a lui alone can stand in for the upper bits when 12 of the zeros are left in
+    if shift > 12 && !(-2048..=2047).contains(&upper) && i32::try_from(upper << 12).is_ok() {
+        shift -= 12;
+        upper <<= 12;
+    }
+    load_immediate_into(words, rd, upper, xlen);
+    words.push(format!("slli {}, {}, {}", rd, rd, shift));
+    if lo12 != 0 {
+        words.push(format!("addi {}, {}, {}", rd, rd, lo12));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &[&str]) -> Vec<String> {
+        text.iter().map(|word| word.to_string()).collect()
+    }
+
+    // the sequences llvm-mc emits for `li a0, <value>`
+    #[test]
+    fn load_immediate_edges() {
+        for (value, xlen, expected) in [
+            (
+                0x7ffff800,
+                Xlen::Rv32,
+                &["lui x10, 524288", "addi x10, x10, -2048"][..],
+            ),
+            (
+                0x7ffff800,
+                Xlen::Rv64,
+                &["lui x10, 524288", "addiw x10, x10, -2048"],
+            ),
+            (
+                0x7fffffff,
+                Xlen::Rv32,
+                &["lui x10, 524288", "addi x10, x10, -1"],
+            ),
+            (
+                0x7fffffff,
+                Xlen::Rv64,
+                &["lui x10, 524288", "addiw x10, x10, -1"],
+            ),
+            (0x80000000, Xlen::Rv32, &["lui x10, 524288"]),
+            (
+                0x80000000,
+                Xlen::Rv64,
+                &["addi x10, x0, 1", "slli x10, x10, 31"],
+            ),
+            (-0x80000000, Xlen::Rv64, &["lui x10, 524288"]),
+            (0xffffffff, Xlen::Rv32, &["addi x10, x0, -1"]),
+            (
+                0xffffffff,
+                Xlen::Rv64,
+                &["addi x10, x0, -1", "srli x10, x10, 32"],
+            ),
+            (-1, Xlen::Rv64, &["addi x10, x0, -1"]),
+            (
+                i64::MIN,
+                Xlen::Rv64,
+                &["addi x10, x0, -1", "slli x10, x10, 63"],
+            ),
+            (
+                0xfffffffffff,
+                Xlen::Rv64,
+                &["addi x10, x0, -1", "srli x10, x10, 20"],
+            ),
+        ] {
+            assert_eq!(
+                load_immediate("x10", value, xlen).unwrap(),
+                words(expected),
+                "{:#x}",
+                value
+            );
+        }
+        for value in [i64::MIN, 0xfffffffffff, 0x100000000, -0x80000001] {
+            assert_eq!(
+                load_immediate("x10", value, Xlen::Rv32),
+                Err(Error::ImmediateOutOfRange)
+            );
+        }
+    }
+
+    // without the srli shortcut, a 64-bit value is built 12 bits at a time from the top
+    #[test]
+    fn load_immediate_into_chains() {
+        let mut words = Vec::new();
+        load_immediate_into(&mut words, "x10", 0x123456789abcdef0, Xlen::Rv64);
+        assert_eq!(
+            words,
+            [
+                "lui x10, 583",
+                "addiw x10, x10, -1875",
+                "slli x10, x10, 14",
+                "addi x10, x10, -947",
+                "slli x10, x10, 12",
+                "addi x10, x10, 1511",
+                "slli x10, x10, 13",
+                "addi x10, x10, -272"
+            ]
+        );
+    }
+
+    // auipc and the low 12 bits reach -2 GiB to 2 GiB - 2049 on RV64, RV32 wraps around
+    #[test]
+    fn pc_relative_edges() {
+        let la = |offset: &str, xlen| expand("la", &words(&["x10", offset]), xlen);
+        let call = |offset: &str, xlen| expand("call", &words(&[offset]), xlen);
+        for xlen in [Xlen::Rv32, Xlen::Rv64] {
+            assert_eq!(
+                la("2147481599", xlen).unwrap(),
+                Some(words(&["auipc x10, 524287", "addi x10, x10, 2047"]))
+            );
+            assert_eq!(
+                call("-2147483648", xlen).unwrap(),
+                Some(words(&["auipc x1, 524288", "jalr x1, 0(x1)"]))
+            );
+            assert_eq!(
+                la("-2147481601", xlen).unwrap(),
+                Some(words(&["auipc x10, 524288", "addi x10, x10, 2047"]))
+            );
+            assert_eq!(
+                call("-2048", xlen).unwrap(),
+                Some(words(&["auipc x1, 0", "jalr x1, -2048(x1)"]))
+            );
+        }
+        assert_eq!(
+            la("2147483647", Xlen::Rv32).unwrap(),
+            Some(words(&["auipc x10, 524288", "addi x10, x10, -1"]))
+        );
+        assert_eq!(
+            call("2147481600", Xlen::Rv32).unwrap(),
+            Some(words(&["auipc x1, 524288", "jalr x1, -2048(x1)"]))
+        );
+        assert_eq!(
+            la("2147483647", Xlen::Rv64),
+            Err(Error::ImmediateOutOfRange)
+        );
+        assert_eq!(
+            call("2147481600", Xlen::Rv64),
+            Err(Error::ImmediateOutOfRange)
+        );
+        assert_eq!(call("2147483648", Xlen::Rv32), Err(Error::InvalidImmediate));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::encoder::encode;
+use crate::error::{Error, Result};
+use crate::format::{EncodedInstruction, Instruction, Xlen};
+use crate::operand::{Imm, Operand, Reg};
+use crate::util::sign_extend;
+
+// expands a compressed instruction into the base instruction it stands for
+pub fn expand(d: &EncodedInstruction, xlen: Xlen) -> Result<EncodedInstruction> {
+    let ops = &d.operands;
+    let base = match d.mnemonic.as_str() {
+        "c.addi4spn" => format!("addi {}, {}, {}", ops[0], ops[1], ops[2]),
+        "c.lw" | "c.ld" | "c.flw" | "c.fld" | "c.sw" | "c.sd" | "c.fsw" | "c.fsd" | "c.lwsp"
+        | "c.ldsp" | "c.flwsp" | "c.fldsp" | "c.swsp" | "c.sdsp" | "c.fswsp" | "c.fsdsp"
+        | "c.lbu" | "c.lhu" | "c.lh" | "c.sb" | "c.sh" => {
+            let base = d.mnemonic.trim_start_matches("c.").trim_end_matches("sp");
+            format!("{} {}, {}({})", base, ops[0], ops[1], ops[2])
+        }
+        "c.unimp" => "csrrw x0, cycle, x0".to_string(),
+        "c.nop" => "addi x0, x0, 0".to_string(),
+        "c.addi" | "c.addiw" | "c.addi16sp" | "c.slli" | "c.srli" | "c.srai" | "c.andi" => {
+            let base = d.mnemonic.trim_start_matches("c.").trim_end_matches("16sp");
+            format!("{} {}, {}, {}", base, ops[0], ops[0], ops[1])
+        }
+        "c.li" => format!("addi {}, x0, {}", ops[0], ops[1]),
+        "c.lui" => format!("lui {}, {}", ops[0], ops[1]),
+        "c.sub" | "c.xor" | "c.or" | "c.and" | "c.subw" | "c.addw" | "c.add" | "c.mul" => {
+            let base = d.mnemonic.trim_start_matches("c.");
+            format!("{} {}, {}, {}", base, ops[0], ops[0], ops[1])
+        }
+        "c.mv" => format!("add {}, x0, {}", ops[0], ops[1]),
+        "c.zext.b" => format!("andi {}, {}, 255", ops[0], ops[0]),
+        "c.sext.b" | "c.zext.h" | "c.sext.h" => {
+            format!("{} {}, {}", &d.mnemonic[2..], ops[0], ops[0])
+        }
+        "c.zext.w" => format!("add.uw {}, {}, x0", ops[0], ops[0]),
+        "c.not" => format!("xori {}, {}, -1", ops[0], ops[0]),
+        "c.jal" => format!("jal x1, {}", ops[0]),
+        "c.j" => format!("jal x0, {}", ops[0]),
+        "c.beqz" => format!("beq {}, x0, {}", ops[0], ops[1]),
+        "c.bnez" => format!("bne {}, x0, {}", ops[0], ops[1]),
+        "c.jr" => format!("jalr x0, 0({})", ops[0]),
+        "c.jalr" => format!("jalr x1, 0({})", ops[0]),
+        "c.ebreak" => "ebreak".to_string(),
+        "c.ntl.p1" | "c.ntl.pall" | "c.ntl.s1" | "c.ntl.all" => {
+            d.mnemonic.trim_start_matches("c.").to_string()
+        }
+        _ => return Err(Error::UnknownInstruction),
+    };
+    encode(&base, xlen)
+}
+
+// finds the compressed encoding of a base instruction, if it has one.
+// the candidates only check the register pairings, the encoder rejects registers
+// outside x8-x15 and immediates out of range for the compressed form
+pub fn compress(d: &EncodedInstruction, xlen: Xlen) -> Option<EncodedInstruction> {
+    if d.is_compressed() {
+        return None;
+    }
+    let ops = &d.operands;
+    let reg = |idx: usize| match ops.get(idx) {
+        Some(Operand::Reg(Reg(r))) => Some(*r),
+        _ => None,
+    };
+    let imm = |idx: usize| match ops.get(idx) {
+        Some(Operand::Imm(Imm(i))) => Some(*i),
+        _ => None,
+    };
+
+    let mut candidates = Vec::new();
+    match d.mnemonic.as_str() {
+        "addi" => {
+            let (rd, rs1, imm) = (reg(0)?, reg(1)?, imm(2)?);
+            if rd == 0 && rs1 == 0 && imm == 0 {
+                candidates.push("c.nop".to_string());
+            }
+            if rd == 2 && rs1 == 2 {
+                candidates.push(format!("c.addi16sp x2, {}", imm));
+            } else if rs1 == 2 {
+                candidates.push(format!("c.addi4spn x{}, x2, {}", rd, imm));
+            }
+            // rd = x0 or a zero immediate are hints, not the instruction itself
+            if rd != 0 && rd == rs1 && imm != 0 {
+                candidates.push(format!("c.addi x{}, {}", rd, imm));
+            }
+            if rd != 0 && rs1 == 0 {
+                candidates.push(format!("c.li x{}, {}", rd, imm));
+            }
+            if rd != 0 && rs1 != 0 && imm == 0 {
+                candidates.push(format!("c.mv x{}, x{}", rd, rs1));
+            }
+        }
+        "addiw" => {
+            let (rd, rs1, imm) = (reg(0)?, reg(1)?, imm(2)?);
+            if rd != 0 && rd == rs1 {
+                candidates.push(format!("c.addiw x{}, {}", rd, imm));
+            }
+            if rd != 0 && rs1 == 0 {
+                candidates.push(format!("c.li x{}, {}", rd, imm));
+            }
+        }
+        "lui" => {
+            let Instruction::UType(u) = &d.instr else {
+                return None;
+            };
+            let rd = u.rd();
+            let imm = sign_extend(u.imm(), 20);
+            if rd != 0 && rd != 2 && imm != 0 {
+                candidates.push(format!("c.lui x{}, {}", rd, imm));
+            }
+        }
+        "slli" | "srli" | "srai" | "andi" => {
+            let (rd, rs1, imm) = (reg(0)?, reg(1)?, imm(2)?);
+            let hint = d.mnemonic != "andi" && imm == 0;
+            if rd != 0 && rd == rs1 && !hint {
+                candidates.push(format!("c.{} x{}, {}", d.mnemonic, rd, imm));
+            }
+            if d.mnemonic == "andi" && rd == rs1 && imm == 255 {
+                candidates.push(format!("c.zext.b x{}", rd));
+            }
+        }
+        "xori" => {
+            let (rd, rs1, imm) = (reg(0)?, reg(1)?, imm(2)?);
+            if rd == rs1 && imm == -1 {
+                candidates.push(format!("c.not x{}", rd));
+            }
+        }
+        "sext.b" | "zext.h" | "sext.h" => {
+            let (rd, rs1) = (reg(0)?, reg(1)?);
+            if rd == rs1 {
+                candidates.push(format!("c.{} x{}", d.mnemonic, rd));
+            }
+        }
+        "add.uw" => {
+            let (rd, rs1, rs2) = (reg(0)?, reg(1)?, reg(2)?);
+            if rd == rs1 && rs2 == 0 {
+                candidates.push(format!("c.zext.w x{}", rd));
+            }
+        }
+        "mul" => {
+            let (rd, rs1, rs2) = (reg(0)?, reg(1)?, reg(2)?);
+            if rd == rs1 {
+                candidates.push(format!("c.mul x{}, x{}", rd, rs2));
+            }
+            if rd == rs2 {
+                candidates.push(format!("c.mul x{}, x{}", rd, rs1));
+            }
+        }
+        "lbu" | "lhu" | "lh" | "sb" | "sh" => {
+            candidates.push(format!(
+                "c.{} {}, {}({})",
+                d.mnemonic, ops[0], ops[1], ops[2]
+            ));
+        }
+        "add" => {
+            let (rd, rs1, rs2) = (reg(0)?, reg(1)?, reg(2)?);
+            if rd != 0 && rs1 == 0 && rs2 != 0 {
+                candidates.push(format!("c.mv x{}, x{}", rd, rs2));
+            }
+            if rd != 0 && rs2 == 0 && rs1 != 0 {
+                candidates.push(format!("c.mv x{}, x{}", rd, rs1));
+            }
+            if rd != 0 && rd == rs1 && rs2 != 0 {
+                candidates.push(format!("c.add x{}, x{}", rd, rs2));
+            }
+            if rd != 0 && rd == rs2 && rs1 != 0 {
+                candidates.push(format!("c.add x{}, x{}", rd, rs1));
+            }
+        }
+        "sub" | "subw" | "xor" | "or" | "and" | "addw" => {
+            let (rd, rs1, rs2) = (reg(0)?, reg(1)?, reg(2)?);
+            if rd == rs1 {
+                candidates.push(format!("c.{} x{}, x{}", d.mnemonic, rd, rs2));
+            }
+            let commutative = !d.mnemonic.starts_with("sub");
+            if commutative && rd == rs2 {
+                candidates.push(format!("c.{} x{}, x{}", d.mnemonic, rd, rs1));
+            }
+        }
+        "lw" | "ld" | "flw" | "fld" | "sw" | "sd" | "fsw" | "fsd" => {
+            let rs1 = reg(2)?;
+            let sp = if rs1 == 2 { "sp" } else { "" };
+            candidates.push(format!(
+                "c.{}{} {}, {}(x{})",
+                d.mnemonic, sp, ops[0], ops[1], rs1
+            ));
+        }
+        "jal" => match reg(0)? {
+            0 => candidates.push(format!("c.j {}", ops[1])),
+            1 => candidates.push(format!("c.jal {}", ops[1])),
+            _ => {}
+        },
+        "jalr" => {
+            let (rd, imm, rs1) = (reg(0)?, imm(1)?, reg(2)?);
+            if imm == 0 && rs1 != 0 {
+                match rd {
+                    0 => candidates.push(format!("c.jr x{}", rs1)),
+                    1 => candidates.push(format!("c.jalr x{}", rs1)),
+                    _ => {}
+                }
+            }
+        }
+        "beq" | "bne" => {
+            let (rs1, rs2) = (reg(0)?, reg(1)?);
+            let compare = match (rs1, rs2) {
+                (rs1, 0) => rs1,
+                (0, rs2) => rs2,
+                _ => return None,
+            };
+            candidates.push(format!("c.{}z x{}, {}", d.mnemonic, compare, ops[2]));
+        }
+        "ebreak" => candidates.push("c.ebreak".to_string()),
+        // unimp, the illegal instruction of both sizes
+        "csrrw" if d.instr.raw() == 0xc0001073 => candidates.push("c.unimp".to_string()),
+        "ntl.p1" | "ntl.pall" | "ntl.s1" | "ntl.all" => {
+            candidates.push(format!("c.{}", d.mnemonic))
+        }
+        _ => {}
+    }
+    candidates
+        .iter()
+        .find_map(|candidate| encode(candidate, xlen).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the compressed word of a base instruction, None when it has no compressed form
+    fn compressed(text: &str, xlen: Xlen) -> Option<(String, u32)> {
+        let d = encode(text, xlen).unwrap();
+        compress(&d, xlen).map(|c| (c.mnemonic.to_string(), c.instr.raw()))
+    }
+
+    // the words llvm-mc gives for the compressed forms
+    #[test]
+    fn compress_known_answers() {
+        let c = |mnemonic: &str, word| Some((mnemonic.to_string(), word));
+        assert_eq!(
+            compressed("addi a0, a0, 1", Xlen::Rv32),
+            c("c.addi", 0x0505)
+        );
+        // CI takes any register, only the CIW/CL/CS/CA/CB forms are limited to x8-x15
+        assert_eq!(
+            compressed("addi s8, s8, 1", Xlen::Rv32),
+            c("c.addi", 0x0c05)
+        );
+        assert_eq!(
+            compressed("addi sp, sp, -512", Xlen::Rv32),
+            c("c.addi16sp", 0x7101)
+        );
+        assert_eq!(
+            compressed("add a0, zero, a1", Xlen::Rv32),
+            c("c.mv", 0x852e)
+        );
+        assert_eq!(compressed("lw a0, 124(a1)", Xlen::Rv32), c("c.lw", 0x5de8));
+        assert_eq!(compressed("jal ra, 256", Xlen::Rv32), c("c.jal", 0x2201));
+        // rd outside x8-x15, an offset past 124, and c.jal is c.addiw on RV64
+        assert_eq!(compressed("lw a6, 0(a0)", Xlen::Rv32), None);
+        assert_eq!(compressed("lw a0, 128(a1)", Xlen::Rv32), None);
+        assert_eq!(compressed("jal ra, 256", Xlen::Rv64), None);
+        // rd = x0 makes it a hint, not c.addi
+        assert_eq!(compressed("addi zero, zero, 1", Xlen::Rv32), None);
+    }
+
+    #[test]
+    fn expand_known_answers() {
+        for (word, text, base) in [
+            (0x852e, "c.mv x10, x11", 0x00b00533),
+            (0x0505, "c.addi x10, 1", 0x00150513),
+            (0x7101, "c.addi16sp x2, -512", 0xe0010113),
+            (0x5de8, "c.lw x10, 124(x11)", 0x07c5a503),
+        ] {
+            let d = crate::decoder::decode(word, Xlen::Rv32).unwrap();
+            assert_eq!(d.to_string(), text);
+            assert_eq!(
+                expand(&d, Xlen::Rv32).unwrap().instr.raw(),
+                base,
+                "{}",
+                text
+            );
+        }
+        let mv = crate::decoder::decode(0x852e, Xlen::Rv32).unwrap();
+        assert_eq!(
+            expand(&mv, Xlen::Rv32).unwrap().to_string(),
+            "add x10, x0, x11"
+        );
+    }
+}
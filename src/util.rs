@@ -1,15 +1,77 @@
+use core::fmt;
+
+#[cfg(feature = "std")]
+use crate::csr::csr_address;
 use crate::error::{Error, Result};
+use crate::format::Xlen;
 
-const ABI_NAMES: [&str; 32] = [
+pub const ABI_NAMES: [&str; 32] = [
     "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
     "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
     "t5", "t6",
 ];
 
+// fence predecessor/successor bits, msb first: I, O, R, W
+const FENCE_SET: [char; 4] = ['i', 'o', 'r', 'w'];
+
+// instructions that only exist when XLEN is 64
+#[rustfmt::skip]
+const RV64_ONLY: &[&str] = &[
+    "ld", "lwu", "sd", "addiw", "slliw", "srliw", "sraiw", "addw", "subw", "sllw", "srlw", "sraw",
+    "mulw", "divw", "divuw", "remw", "remuw",
+    "lr.d", "sc.d", "amoswap.d", "amoadd.d", "amoxor.d", "amoand.d", "amoor.d", "amomin.d",
+    "amomax.d", "amominu.d", "amomaxu.d", "amocas.q",
+    "fcvt.l.s", "fcvt.lu.s", "fcvt.s.l", "fcvt.s.lu",
+    "fcvt.l.d", "fcvt.lu.d", "fcvt.d.l", "fcvt.d.lu", "fmv.x.d", "fmv.d.x",
+    "fcvt.l.q", "fcvt.lu.q", "fcvt.q.l", "fcvt.q.lu",
+    "fcvt.l.h", "fcvt.lu.h", "fcvt.h.l", "fcvt.h.lu",
+    "hlv.wu", "hlv.d", "hsv.d",
+    "add.uw", "sh1add.uw", "sh2add.uw", "sh3add.uw", "slli.uw", "clzw", "ctzw", "cpopw", "rolw",
+    "rorw", "roriw",
+    "packw", "aes64ds", "aes64dsm", "aes64es", "aes64esm", "aes64im", "aes64ks1i", "aes64ks2",
+    "sha512sig0", "sha512sig1", "sha512sum0", "sha512sum1",
+    "c.ld", "c.sd", "c.ldsp", "c.sdsp", "c.addiw", "c.subw", "c.addw", "c.zext.w",
+];
+
+// compressed encodings that RV64 reassigns, and the scalar crypto ops that work on
+// 32-bit halves
+#[rustfmt::skip]
+const RV32_ONLY: &[&str] = &[
+    "c.jal", "c.flw", "c.fsw", "c.flwsp", "c.fswsp",
+    "zip", "unzip", "aes32esi", "aes32esmi", "aes32dsi", "aes32dsmi", "sha512sig0h",
+    "sha512sig0l", "sha512sig1h", "sha512sig1l", "sha512sum0r", "sha512sum1r",
+];
+
+// conversions that are always exact, the assemblers default their rm to rne instead of dyn
+#[rustfmt::skip]
+const FP_EXACT_CONVERSIONS: &[&str] = &[
+    "fcvt.s.h", "fcvt.d.s", "fcvt.d.h", "fcvt.q.s", "fcvt.q.d", "fcvt.q.h",
+    "fcvt.d.w", "fcvt.d.wu", "fcvt.q.w", "fcvt.q.wu", "fcvt.q.l", "fcvt.q.lu",
+];
+
+const FP_LOADS_STORES: [&str; 8] = ["flh", "flw", "fld", "flq", "fsh", "fsw", "fsd", "fsq"];
+
+pub const FP_ABI_NAMES: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+    "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+    "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+// rounding modes by their rm encoding, 5 and 6 are reserved
+const ROUNDING_MODES: [&str; 8] = ["rne", "rtz", "rdn", "rup", "rmm", "", "", "dyn"];
+
+// floating-point formats by their fmt encoding
+const FP_FORMATS: [&str; 4] = ["s", "d", "h", "q"];
+
+// integer operand types of fcvt, indexed by the rs2 field
+const FP_INT_TYPES: [&str; 4] = ["w", "wu", "l", "lu"];
+
+#[cfg(feature = "std")]
 pub fn is_hex(s: &str) -> bool {
     s.starts_with("0x") || s.starts_with("0X")
 }
 
+#[cfg(feature = "std")]
 pub fn parse_immediate(s: &str) -> Result<i32> {
     let s = s.trim();
     if is_hex(s) {
@@ -19,8 +81,24 @@ pub fn parse_immediate(s: &str) -> Result<i32> {
     }
 }
 
-pub fn parse_reg(reg: &str) -> Result<u32> {
-    if let Some(stripped) = reg.strip_prefix('x') {
+// li takes full XLEN-wide values, hex may also spell out the unsigned bit pattern
+#[cfg(feature = "std")]
+pub fn parse_immediate64(s: &str) -> Result<i64> {
+    let s = s.trim();
+    if is_hex(s) {
+        u64::from_str_radix(&s[2..], 16)
+            .map(|v| v as i64)
+            .map_err(|_| Error::InvalidImmediate)
+    } else {
+        s.parse::<i64>()
+            .or_else(|_| s.parse::<u64>().map(|v| v as i64))
+            .map_err(|_| Error::InvalidImmediate)
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn parse_freg(reg: &str) -> Result<u32> {
+    if let Some(stripped) = reg.strip_prefix('f') {
         let num = stripped
             .parse::<u32>()
             .map_err(|_| Error::InvalidRegister)?;
@@ -34,29 +112,296 @@ pub fn parse_reg(reg: &str) -> Result<u32> {
     }
 }
 
-fn numeric_to_abi(op: &str) -> String {
-    if let Some(stripped) = op.strip_prefix('x') {
-        if let Ok(idx) = stripped.parse::<usize>() {
-            if idx < ABI_NAMES.len() {
-                return ABI_NAMES[idx].to_string();
-            }
+#[cfg(feature = "std")]
+pub fn parse_reg(reg: &str) -> Result<u32> {
+    if let Some(stripped) = reg.strip_prefix('x') {
+        let num = stripped
+            .parse::<u32>()
+            .map_err(|_| Error::InvalidRegister)?;
+        if num <= 31 {
+            Ok(num)
+        } else {
+            Err(Error::InvalidRegister)
         }
+    } else {
+        Err(Error::InvalidRegister)
     }
-    op.to_string()
 }
 
-pub fn operands_to_abi(ops: &[String]) -> Vec<String> {
-    ops.iter().map(|o| numeric_to_abi(o)).collect()
+#[cfg(feature = "std")]
+pub fn parse_vreg(reg: &str) -> Result<u32> {
+    match reg.strip_prefix('v').map(str::parse::<u32>) {
+        Some(Ok(num)) if num <= 31 => Ok(num),
+        _ => Err(Error::InvalidRegister),
+    }
 }
 
+#[cfg(feature = "std")]
 fn abi_to_numeric(abi: &str) -> String {
     if let Some(idx) = ABI_NAMES.iter().position(|&name| name == abi) {
         format!("x{}", idx)
+    } else if let Some(idx) = FP_ABI_NAMES.iter().position(|&name| name == abi) {
+        format!("f{}", idx)
     } else {
         abi.to_string()
     }
 }
 
+#[cfg(feature = "std")]
 pub fn abis_to_operands(abis: &[String]) -> Vec<String> {
     abis.iter().map(|a| abi_to_numeric(a)).collect()
 }
+
+// a CSR by name or by its 12-bit address
+#[cfg(feature = "std")]
+pub fn parse_csr(s: &str) -> Result<u32> {
+    if let Some(addr) = csr_address(s) {
+        return Ok(addr as u32);
+    }
+    match parse_immediate(s)? {
+        csr @ 0..=0xfff => Ok(csr as u32),
+        _ => Err(Error::ImmediateOutOfRange),
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn parse_fence_set(s: &str) -> Result<u32> {
+    if s == "0" {
+        return Ok(0);
+    }
+    let mut set = 0;
+    for c in s.chars() {
+        let idx = FENCE_SET
+            .iter()
+            .position(|&f| f == c.to_ascii_lowercase())
+            .ok_or(Error::InvalidFormat)?;
+        let bit = 1 << (3 - idx);
+        if set & bit != 0 {
+            return Err(Error::InvalidFormat);
+        }
+        set |= bit;
+    }
+    if set == 0 {
+        return Err(Error::InvalidFormat);
+    }
+    Ok(set)
+}
+
+pub fn write_fence_set(set: u32, f: &mut impl fmt::Write) -> fmt::Result {
+    if set & 0xf == 0 {
+        return f.write_char('0');
+    }
+    for (idx, c) in FENCE_SET.iter().enumerate() {
+        if set & (1 << (3 - idx)) != 0 {
+            f.write_char(*c)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn requires_rv64(mnemonic: &str) -> bool {
+    let (base, _, _) = split_amo_ordering(mnemonic);
+    // the vector indexed loads and stores take no 64-bit indices on RV32
+    RV64_ONLY.contains(&base) || mnemonic.ends_with("ei64.v")
+}
+
+pub fn requires_rv32(mnemonic: &str) -> bool {
+    RV32_ONLY.contains(&mnemonic)
+}
+
+// amocas twice as wide as XLEN compares and swaps an even/odd register pair in rd and rs2
+pub fn is_register_pair_cas(name: &str, width: &str, xlen: Xlen) -> bool {
+    name == "amocas" && matches!((width, xlen), ("d", Xlen::Rv32) | ("q", Xlen::Rv64))
+}
+
+// s0 and s1 are x8 and x9, s2-s11 are x18-x27
+pub fn saved_reg(idx: u32) -> u32 {
+    if idx < 2 { 8 + idx } else { 16 + idx }
+}
+
+#[cfg(feature = "std")]
+pub fn saved_reg_index(reg: u32) -> Option<u32> {
+    match reg {
+        8 | 9 => Some(reg - 8),
+        18..=27 => Some(reg - 16),
+        _ => None,
+    }
+}
+
+// the number of s registers in a cm.push or cm.pop register list, s10 never ends one
+pub fn reg_list_saved(rlist: u32) -> u32 {
+    if rlist == 15 { 12 } else { rlist - 4 }
+}
+
+// the smallest stack adjustment of cm.push and cm.pop, the list's registers rounded up to
+// 16 bytes. spimm adds to it in 16-byte steps
+pub fn stack_adj_base(rlist: u32, xlen: Xlen) -> u32 {
+    let width = if xlen == Xlen::Rv64 { 8 } else { 4 };
+    ((reg_list_saved(rlist) + 1) * width).div_ceil(16) * 16
+}
+
+// `{x1, x8-x9, x18-x19}`, or `{ra, s0-s3}` with ABI names
+pub fn write_reg_list(rlist: u32, abi: bool, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let saved = reg_list_saved(rlist);
+    if abi {
+        f.write_str("{ra")?;
+        match saved {
+            0 => {}
+            1 => f.write_str(", s0")?,
+            n => write!(f, ", s0-s{}", n - 1)?,
+        }
+    } else {
+        f.write_str("{x1")?;
+        match saved {
+            0 => {}
+            1 => f.write_str(", x8")?,
+            _ => f.write_str(", x8-x9")?,
+        }
+        match saved {
+            0..=2 => {}
+            3 => f.write_str(", x18")?,
+            n => write!(f, ", x18-x{}", saved_reg(n - 1))?,
+        }
+    }
+    f.write_str("}")
+}
+
+// a register list with ABI or numeric names, ranges may span s1 and s2 or stop at x9.
+// The list is a single operand, the tokenizer keeps its commas
+#[cfg(feature = "std")]
+pub fn parse_reg_list(s: &str) -> Result<u32> {
+    let list = s
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .ok_or(Error::InvalidFormat)?;
+    let mut parts = list.split(',').map(str::trim);
+    if parts.next().map(|ra| parse_reg(&abi_to_numeric(ra))) != Some(Ok(1)) {
+        return Err(Error::InvalidRegister);
+    }
+    // each range has to continue the s registers where the previous one stopped
+    let mut saved = 0;
+    for part in parts {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let index = |name: &str| {
+            let reg = parse_reg(&abi_to_numeric(name.trim()))?;
+            saved_reg_index(reg).ok_or(Error::InvalidRegister)
+        };
+        let (first, last) = (index(first)?, index(last)?);
+        if first != saved || last < first {
+            return Err(Error::InvalidRegister);
+        }
+        saved = last + 1;
+    }
+    match saved {
+        11 => Err(Error::InvalidRegister),
+        12 => Ok(15),
+        n => Ok(n + 4),
+    }
+}
+
+// splits the `.aq`, `.rl` or `.aqrl` ordering suffix off an atomic mnemonic
+pub fn split_amo_ordering(mnemonic: &str) -> (&str, bool, bool) {
+    if let Some(base) = mnemonic.strip_suffix(".aqrl") {
+        (base, true, true)
+    } else if let Some(base) = mnemonic.strip_suffix(".aq") {
+        (base, true, false)
+    } else if let Some(base) = mnemonic.strip_suffix(".rl") {
+        (base, false, true)
+    } else {
+        (mnemonic, false, false)
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn parse_rounding_mode(s: &str) -> Result<u32> {
+    ROUNDING_MODES
+        .iter()
+        .position(|&rm| !rm.is_empty() && rm == s)
+        .map(|rm| rm as u32)
+        .ok_or(Error::InvalidRoundingMode)
+}
+
+pub fn rounding_mode_name(rm: u32) -> Result<&'static str> {
+    match ROUNDING_MODES.get(rm as usize) {
+        Some(name) if !name.is_empty() => Ok(name),
+        _ => Err(Error::InvalidRoundingMode),
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn is_rounding_mode(s: &str) -> bool {
+    parse_rounding_mode(s).is_ok()
+}
+
+#[cfg(feature = "std")]
+pub fn fp_fmt_code(suffix: &str) -> Option<u32> {
+    FP_FORMATS
+        .iter()
+        .position(|&f| f == suffix)
+        .map(|f| f as u32)
+}
+
+pub fn fp_fmt_suffix(fmt: u32) -> Option<&'static str> {
+    FP_FORMATS.get(fmt as usize).copied()
+}
+
+#[cfg(feature = "std")]
+pub fn fp_int_code(suffix: &str) -> Option<u32> {
+    FP_INT_TYPES
+        .iter()
+        .position(|&t| t == suffix)
+        .map(|t| t as u32)
+}
+
+pub fn fp_int_suffix(code: u32) -> Option<&'static str> {
+    FP_INT_TYPES.get(code as usize).copied()
+}
+
+// fmv.x.w / fmv.w.x name the single-precision format after the integer word,
+// there is no quad move below RV128
+pub fn fp_move_suffix(fmt: u32) -> Option<&'static str> {
+    match fp_fmt_suffix(fmt)? {
+        "s" => Some("w"),
+        "q" => None,
+        suffix => Some(suffix),
+    }
+}
+
+#[cfg(feature = "std")]
+pub fn fp_move_code(suffix: &str) -> Option<u32> {
+    match suffix {
+        "w" => fp_fmt_code("s"),
+        "s" | "q" => None,
+        suffix => fp_fmt_code(suffix),
+    }
+}
+
+pub fn fp_default_rm(mnemonic: &str) -> u32 {
+    if FP_EXACT_CONVERSIONS.contains(&mnemonic) {
+        0b000 // rne
+    } else {
+        0b111 // dyn
+    }
+}
+
+pub fn is_fp_load_store(mnemonic: &str) -> bool {
+    FP_LOADS_STORES.contains(&mnemonic)
+}
+
+// gathers the listed immediate bits (msb first) into a packed field value
+#[cfg(feature = "std")]
+pub fn pack_imm(imm: u32, bits: &[u32]) -> u16 {
+    bits.iter()
+        .fold(0, |field, &bit| (field << 1) | ((imm >> bit) & 1) as u16)
+}
+
+// scatters a packed field value back to the listed immediate bits (msb first)
+pub fn unpack_imm(field: u16, bits: &[u32]) -> u32 {
+    bits.iter().rev().enumerate().fold(0, |imm, (idx, &bit)| {
+        imm | ((((field >> idx) & 1) as u32) << bit)
+    })
+}
+
+pub fn sign_extend(value: u32, bits: u32) -> i32 {
+    ((value << (32 - bits)) as i32) >> (32 - bits)
+}
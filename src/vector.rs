@@ -0,0 +1,716 @@
+use core::fmt;
+use core::ops::Deref;
+
+#[cfg(feature = "std")]
+use crate::error::{Error, Result};
+#[cfg(feature = "std")]
+use crate::format::Instruction;
+use crate::format::{Mnemonic, VectorType};
+use crate::operand::Operand;
+#[cfg(feature = "std")]
+use crate::util::parse_immediate;
+use crate::util::sign_extend;
+
+pub const OP_V: u32 = 0b1010111;
+// the vector crypto ops
+pub const OP_VE: u32 = 0b1110111;
+
+// funct3 of OP-V: the operand category of the arithmetic ops, and vset{i}vl{i}
+pub const OPIVV: u32 = 0b000;
+pub const OPFVV: u32 = 0b001;
+pub const OPMVV: u32 = 0b010;
+pub const OPIVI: u32 = 0b011;
+pub const OPIVX: u32 = 0b100;
+pub const OPFVF: u32 = 0b101;
+pub const OPMVX: u32 = 0b110;
+pub const OPCFG: u32 = 0b111;
+
+// vlmul by its encoding, 4 is reserved
+const LMULS: [&str; 8] = ["m1", "m2", "m4", "m8", "", "mf8", "mf4", "mf2"];
+
+// an operand of a vector instruction and the field it comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    // vd, or vs3 for stores, and the scalar results of the moves and mask queries
+    Vd,
+    Rd,
+    Fd,
+    Vs2,
+    // vs1 and the scalar or 5-bit immediate operands in its place
+    Vs1,
+    Rs1,
+    Fs1,
+    Simm,
+    Uimm,
+    // vror.vi keeps the sixth immediate bit in the low bit of funct6
+    Uimm6,
+    // the explicit `v0` of the add-with-carry and merge ops, vm is 0
+    Carry,
+    // the optional `v0.t`
+    Mask,
+    // `(rs1)`, then the stride register or index vector in the rs2 field of loads and stores
+    Base,
+    Stride,
+    Index,
+}
+
+// operand slots in assembly order
+#[derive(Clone, Copy)]
+pub struct Layout {
+    slots: [Slot; 4],
+    len: u8,
+}
+
+type Table = &'static [(&'static str, u32, &'static [&'static str])];
+
+// OPIVV/OPIVX/OPIVI, OPMVV/OPMVX and OPFVV/OPFVF ops with a vector in vs2: the mnemonic
+// without its suffix, funct6, and the suffixes it comes with. The second letter of a
+// suffix is the kind of the vs1 operand, `vs` and `mm` take a vector there
+#[rustfmt::skip]
+const OPI: Table = &[
+    ("vadd", 0b000000, &["vv", "vx", "vi"]),
+    ("vandn", 0b000001, &["vv", "vx"]),
+    ("vsub", 0b000010, &["vv", "vx"]),
+    ("vrsub", 0b000011, &["vx", "vi"]),
+    ("vminu", 0b000100, &["vv", "vx"]),
+    ("vmin", 0b000101, &["vv", "vx"]),
+    ("vmaxu", 0b000110, &["vv", "vx"]),
+    ("vmax", 0b000111, &["vv", "vx"]),
+    ("vand", 0b001001, &["vv", "vx", "vi"]),
+    ("vor", 0b001010, &["vv", "vx", "vi"]),
+    ("vxor", 0b001011, &["vv", "vx", "vi"]),
+    ("vrgather", 0b001100, &["vv", "vx", "vi"]),
+    ("vrgatherei16", 0b001110, &["vv"]),
+    ("vslideup", 0b001110, &["vx", "vi"]),
+    ("vslidedown", 0b001111, &["vx", "vi"]),
+    ("vadc", 0b010000, &["vvm", "vxm", "vim"]),
+    ("vmadc", 0b010001, &["vvm", "vxm", "vim", "vv", "vx", "vi"]),
+    ("vsbc", 0b010010, &["vvm", "vxm"]),
+    ("vmsbc", 0b010011, &["vvm", "vxm", "vv", "vx"]),
+    ("vror", 0b010100, &["vv", "vx", "vi"]),
+    ("vrol", 0b010101, &["vv", "vx"]),
+    ("vmerge", 0b010111, &["vvm", "vxm", "vim"]),
+    ("vmseq", 0b011000, &["vv", "vx", "vi"]),
+    ("vmsne", 0b011001, &["vv", "vx", "vi"]),
+    ("vmsltu", 0b011010, &["vv", "vx"]),
+    ("vmslt", 0b011011, &["vv", "vx"]),
+    ("vmsleu", 0b011100, &["vv", "vx", "vi"]),
+    ("vmsle", 0b011101, &["vv", "vx", "vi"]),
+    ("vmsgtu", 0b011110, &["vx", "vi"]),
+    ("vmsgt", 0b011111, &["vx", "vi"]),
+    ("vsaddu", 0b100000, &["vv", "vx", "vi"]),
+    ("vsadd", 0b100001, &["vv", "vx", "vi"]),
+    ("vssubu", 0b100010, &["vv", "vx"]),
+    ("vssub", 0b100011, &["vv", "vx"]),
+    ("vsll", 0b100101, &["vv", "vx", "vi"]),
+    ("vsmul", 0b100111, &["vv", "vx"]),
+    ("vsrl", 0b101000, &["vv", "vx", "vi"]),
+    ("vsra", 0b101001, &["vv", "vx", "vi"]),
+    ("vssrl", 0b101010, &["vv", "vx", "vi"]),
+    ("vssra", 0b101011, &["vv", "vx", "vi"]),
+    ("vnsrl", 0b101100, &["wv", "wx", "wi"]),
+    ("vnsra", 0b101101, &["wv", "wx", "wi"]),
+    ("vnclipu", 0b101110, &["wv", "wx", "wi"]),
+    ("vnclip", 0b101111, &["wv", "wx", "wi"]),
+    ("vwredsumu", 0b110000, &["vs"]),
+    ("vwredsum", 0b110001, &["vs"]),
+    ("vwsll", 0b110101, &["vv", "vx", "vi"]),
+];
+
+#[rustfmt::skip]
+const OPM: Table = &[
+    ("vredsum", 0b000000, &["vs"]),
+    ("vredand", 0b000001, &["vs"]),
+    ("vredor", 0b000010, &["vs"]),
+    ("vredxor", 0b000011, &["vs"]),
+    ("vredminu", 0b000100, &["vs"]),
+    ("vredmin", 0b000101, &["vs"]),
+    ("vredmaxu", 0b000110, &["vs"]),
+    ("vredmax", 0b000111, &["vs"]),
+    ("vaaddu", 0b001000, &["vv", "vx"]),
+    ("vaadd", 0b001001, &["vv", "vx"]),
+    ("vasubu", 0b001010, &["vv", "vx"]),
+    ("vasub", 0b001011, &["vv", "vx"]),
+    ("vclmul", 0b001100, &["vv", "vx"]),
+    ("vclmulh", 0b001101, &["vv", "vx"]),
+    ("vslide1up", 0b001110, &["vx"]),
+    ("vslide1down", 0b001111, &["vx"]),
+    ("vcompress", 0b010111, &["vm"]),
+    ("vmandn", 0b011000, &["mm"]),
+    ("vmand", 0b011001, &["mm"]),
+    ("vmor", 0b011010, &["mm"]),
+    ("vmxor", 0b011011, &["mm"]),
+    ("vmorn", 0b011100, &["mm"]),
+    ("vmnand", 0b011101, &["mm"]),
+    ("vmnor", 0b011110, &["mm"]),
+    ("vmxnor", 0b011111, &["mm"]),
+    ("vdivu", 0b100000, &["vv", "vx"]),
+    ("vdiv", 0b100001, &["vv", "vx"]),
+    ("vremu", 0b100010, &["vv", "vx"]),
+    ("vrem", 0b100011, &["vv", "vx"]),
+    ("vmulhu", 0b100100, &["vv", "vx"]),
+    ("vmul", 0b100101, &["vv", "vx"]),
+    ("vmulhsu", 0b100110, &["vv", "vx"]),
+    ("vmulh", 0b100111, &["vv", "vx"]),
+    ("vmadd", 0b101001, &["vv", "vx"]),
+    ("vnmsub", 0b101011, &["vv", "vx"]),
+    ("vmacc", 0b101101, &["vv", "vx"]),
+    ("vnmsac", 0b101111, &["vv", "vx"]),
+    ("vwaddu", 0b110000, &["vv", "vx"]),
+    ("vwadd", 0b110001, &["vv", "vx"]),
+    ("vwsubu", 0b110010, &["vv", "vx"]),
+    ("vwsub", 0b110011, &["vv", "vx"]),
+    ("vwaddu", 0b110100, &["wv", "wx"]),
+    ("vwadd", 0b110101, &["wv", "wx"]),
+    ("vwsubu", 0b110110, &["wv", "wx"]),
+    ("vwsub", 0b110111, &["wv", "wx"]),
+    ("vwmulu", 0b111000, &["vv", "vx"]),
+    ("vwmulsu", 0b111010, &["vv", "vx"]),
+    ("vwmul", 0b111011, &["vv", "vx"]),
+    ("vwmaccu", 0b111100, &["vv", "vx"]),
+    ("vwmacc", 0b111101, &["vv", "vx"]),
+    ("vwmaccus", 0b111110, &["vx"]),
+    ("vwmaccsu", 0b111111, &["vv", "vx"]),
+];
+
+#[rustfmt::skip]
+const OPF: Table = &[
+    ("vfadd", 0b000000, &["vv", "vf"]),
+    ("vfredusum", 0b000001, &["vs"]),
+    ("vfsub", 0b000010, &["vv", "vf"]),
+    ("vfredosum", 0b000011, &["vs"]),
+    ("vfmin", 0b000100, &["vv", "vf"]),
+    ("vfredmin", 0b000101, &["vs"]),
+    ("vfmax", 0b000110, &["vv", "vf"]),
+    ("vfredmax", 0b000111, &["vs"]),
+    ("vfsgnj", 0b001000, &["vv", "vf"]),
+    ("vfsgnjn", 0b001001, &["vv", "vf"]),
+    ("vfsgnjx", 0b001010, &["vv", "vf"]),
+    ("vfslide1up", 0b001110, &["vf"]),
+    ("vfslide1down", 0b001111, &["vf"]),
+    ("vfmerge", 0b010111, &["vfm"]),
+    ("vmfeq", 0b011000, &["vv", "vf"]),
+    ("vmfle", 0b011001, &["vv", "vf"]),
+    ("vmflt", 0b011011, &["vv", "vf"]),
+    ("vmfne", 0b011100, &["vv", "vf"]),
+    ("vmfgt", 0b011101, &["vf"]),
+    ("vmfge", 0b011111, &["vf"]),
+    ("vfdiv", 0b100000, &["vv", "vf"]),
+    ("vfrdiv", 0b100001, &["vf"]),
+    ("vfmul", 0b100100, &["vv", "vf"]),
+    ("vfrsub", 0b100111, &["vf"]),
+    ("vfmadd", 0b101000, &["vv", "vf"]),
+    ("vfnmadd", 0b101001, &["vv", "vf"]),
+    ("vfmsub", 0b101010, &["vv", "vf"]),
+    ("vfnmsub", 0b101011, &["vv", "vf"]),
+    ("vfmacc", 0b101100, &["vv", "vf"]),
+    ("vfnmacc", 0b101101, &["vv", "vf"]),
+    ("vfmsac", 0b101110, &["vv", "vf"]),
+    ("vfnmsac", 0b101111, &["vv", "vf"]),
+    ("vfwadd", 0b110000, &["vv", "vf"]),
+    ("vfwredusum", 0b110001, &["vs"]),
+    ("vfwsub", 0b110010, &["vv", "vf"]),
+    ("vfwredosum", 0b110011, &["vs"]),
+    ("vfwadd", 0b110100, &["wv", "wf"]),
+    ("vfwsub", 0b110110, &["wv", "wf"]),
+    ("vfwmul", 0b111000, &["vv", "vf"]),
+    ("vfwmacc", 0b111100, &["vv", "vf"]),
+    ("vfwnmacc", 0b111101, &["vv", "vf"]),
+    ("vfwmsac", 0b111110, &["vv", "vf"]),
+    ("vfwnmsac", 0b111111, &["vv", "vf"]),
+];
+
+// each table with the funct3 of its vector and scalar forms
+const GROUPS: [(Table, u32, u32); 3] = [
+    (OPI, OPIVV, OPIVX),
+    (OPM, OPMVV, OPMVX),
+    (OPF, OPFVV, OPFVF),
+];
+
+// the unary ops and moves: mnemonic, funct3, funct6, the function code in vs1 when no
+// operand goes there, and the operands. A field without an operand is fixed, vs2 to zero
+#[rustfmt::skip]
+const UNARY: &[(&str, u32, u32, u32, &[Slot])] = &[
+    ("vmv.x.s", OPMVV, 0b010000, 0b00000, &[Slot::Rd, Slot::Vs2]),
+    ("vcpop.m", OPMVV, 0b010000, 0b10000, &[Slot::Rd, Slot::Vs2, Slot::Mask]),
+    ("vfirst.m", OPMVV, 0b010000, 0b10001, &[Slot::Rd, Slot::Vs2, Slot::Mask]),
+    ("vmv.s.x", OPMVX, 0b010000, 0, &[Slot::Vd, Slot::Rs1]),
+    ("vzext.vf8", OPMVV, 0b010010, 0b00010, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vsext.vf8", OPMVV, 0b010010, 0b00011, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vzext.vf4", OPMVV, 0b010010, 0b00100, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vsext.vf4", OPMVV, 0b010010, 0b00101, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vzext.vf2", OPMVV, 0b010010, 0b00110, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vsext.vf2", OPMVV, 0b010010, 0b00111, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vbrev8.v", OPMVV, 0b010010, 0b01000, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vrev8.v", OPMVV, 0b010010, 0b01001, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vbrev.v", OPMVV, 0b010010, 0b01010, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vclz.v", OPMVV, 0b010010, 0b01100, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vctz.v", OPMVV, 0b010010, 0b01101, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vcpop.v", OPMVV, 0b010010, 0b01110, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vmsbf.m", OPMVV, 0b010100, 0b00001, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vmsof.m", OPMVV, 0b010100, 0b00010, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vmsif.m", OPMVV, 0b010100, 0b00011, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("viota.m", OPMVV, 0b010100, 0b10000, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vid.v", OPMVV, 0b010100, 0b10001, &[Slot::Vd, Slot::Mask]),
+    ("vfmv.f.s", OPFVV, 0b010000, 0b00000, &[Slot::Fd, Slot::Vs2]),
+    ("vfmv.s.f", OPFVF, 0b010000, 0, &[Slot::Vd, Slot::Fs1]),
+    ("vfcvt.xu.f.v", OPFVV, 0b010010, 0b00000, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfcvt.x.f.v", OPFVV, 0b010010, 0b00001, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfcvt.f.xu.v", OPFVV, 0b010010, 0b00010, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfcvt.f.x.v", OPFVV, 0b010010, 0b00011, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfcvt.rtz.xu.f.v", OPFVV, 0b010010, 0b00110, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfcvt.rtz.x.f.v", OPFVV, 0b010010, 0b00111, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfwcvt.xu.f.v", OPFVV, 0b010010, 0b01000, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfwcvt.x.f.v", OPFVV, 0b010010, 0b01001, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfwcvt.f.xu.v", OPFVV, 0b010010, 0b01010, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfwcvt.f.x.v", OPFVV, 0b010010, 0b01011, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfwcvt.f.f.v", OPFVV, 0b010010, 0b01100, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfwcvt.rtz.xu.f.v", OPFVV, 0b010010, 0b01110, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfwcvt.rtz.x.f.v", OPFVV, 0b010010, 0b01111, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.xu.f.w", OPFVV, 0b010010, 0b10000, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.x.f.w", OPFVV, 0b010010, 0b10001, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.f.xu.w", OPFVV, 0b010010, 0b10010, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.f.x.w", OPFVV, 0b010010, 0b10011, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.f.f.w", OPFVV, 0b010010, 0b10100, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.rod.f.f.w", OPFVV, 0b010010, 0b10101, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.rtz.xu.f.w", OPFVV, 0b010010, 0b10110, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfncvt.rtz.x.f.w", OPFVV, 0b010010, 0b10111, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfsqrt.v", OPFVV, 0b010011, 0b00000, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfrsqrt7.v", OPFVV, 0b010011, 0b00100, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfrec7.v", OPFVV, 0b010011, 0b00101, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vfclass.v", OPFVV, 0b010011, 0b10000, &[Slot::Vd, Slot::Vs2, Slot::Mask]),
+    ("vmv.v.v", OPIVV, 0b010111, 0, &[Slot::Vd, Slot::Vs1]),
+    ("vmv.v.x", OPIVX, 0b010111, 0, &[Slot::Vd, Slot::Rs1]),
+    ("vmv.v.i", OPIVI, 0b010111, 0, &[Slot::Vd, Slot::Simm]),
+    ("vfmv.v.f", OPFVF, 0b010111, 0, &[Slot::Vd, Slot::Fs1]),
+    // the register count minus one sits in the immediate
+    ("vmv1r.v", OPIVI, 0b100111, 0, &[Slot::Vd, Slot::Vs2]),
+    ("vmv2r.v", OPIVI, 0b100111, 1, &[Slot::Vd, Slot::Vs2]),
+    ("vmv4r.v", OPIVI, 0b100111, 3, &[Slot::Vd, Slot::Vs2]),
+    ("vmv8r.v", OPIVI, 0b100111, 7, &[Slot::Vd, Slot::Vs2]),
+];
+
+// the AES, SHA-2, GHASH, SM4 and SM3 ops under OP-VE, all OPMVV and never masked: mnemonic,
+// funct6, the function code in vs1 and the operands
+#[rustfmt::skip]
+const CRYPTO: &[(&str, u32, u32, &[Slot])] = &[
+    ("vsm3me.vv", 0b100000, 0, &[Slot::Vd, Slot::Vs2, Slot::Vs1]),
+    ("vsm4k.vi", 0b100001, 0, &[Slot::Vd, Slot::Vs2, Slot::Uimm]),
+    ("vaeskf1.vi", 0b100010, 0, &[Slot::Vd, Slot::Vs2, Slot::Uimm]),
+    ("vaesdm.vv", 0b101000, 0b00000, &[Slot::Vd, Slot::Vs2]),
+    ("vaesdf.vv", 0b101000, 0b00001, &[Slot::Vd, Slot::Vs2]),
+    ("vaesem.vv", 0b101000, 0b00010, &[Slot::Vd, Slot::Vs2]),
+    ("vaesef.vv", 0b101000, 0b00011, &[Slot::Vd, Slot::Vs2]),
+    ("vsm4r.vv", 0b101000, 0b10000, &[Slot::Vd, Slot::Vs2]),
+    ("vgmul.vv", 0b101000, 0b10001, &[Slot::Vd, Slot::Vs2]),
+    ("vaesdm.vs", 0b101001, 0b00000, &[Slot::Vd, Slot::Vs2]),
+    ("vaesdf.vs", 0b101001, 0b00001, &[Slot::Vd, Slot::Vs2]),
+    ("vaesem.vs", 0b101001, 0b00010, &[Slot::Vd, Slot::Vs2]),
+    ("vaesef.vs", 0b101001, 0b00011, &[Slot::Vd, Slot::Vs2]),
+    ("vaesz.vs", 0b101001, 0b00111, &[Slot::Vd, Slot::Vs2]),
+    ("vsm4r.vs", 0b101001, 0b10000, &[Slot::Vd, Slot::Vs2]),
+    ("vaeskf2.vi", 0b101010, 0, &[Slot::Vd, Slot::Vs2, Slot::Uimm]),
+    ("vsm3c.vi", 0b101011, 0, &[Slot::Vd, Slot::Vs2, Slot::Uimm]),
+    ("vghsh.vv", 0b101100, 0, &[Slot::Vd, Slot::Vs2, Slot::Vs1]),
+    ("vsha2ms.vv", 0b101101, 0, &[Slot::Vd, Slot::Vs2, Slot::Vs1]),
+    ("vsha2ch.vv", 0b101110, 0, &[Slot::Vd, Slot::Vs2, Slot::Vs1]),
+    ("vsha2cl.vv", 0b101111, 0, &[Slot::Vd, Slot::Vs2, Slot::Vs1]),
+];
+
+impl Layout {
+    fn new(slots: &[Slot]) -> Self {
+        let mut layout = Layout {
+            slots: [Slot::Vd; 4],
+            len: 0,
+        };
+        for &slot in slots {
+            layout.push(slot);
+        }
+        layout
+    }
+
+    fn push(&mut self, slot: Slot) {
+        self.slots[self.len as usize] = slot;
+        self.len += 1;
+    }
+
+    // whether the fields without an operand hold their fixed values: the function code
+    // in vs1, zero in vs2 and vm set unless the instruction is masked
+    fn fits(&self, code: u32, vs1: u32, vs2: u32, vm: bool) -> bool {
+        let vs1_free = self.iter().any(|slot| {
+            matches!(
+                slot,
+                Slot::Vs1 | Slot::Rs1 | Slot::Fs1 | Slot::Simm | Slot::Uimm | Slot::Uimm6
+            )
+        });
+        (vs1_free || vs1 == code)
+            && (self.contains(&Slot::Vs2) || vs2 == 0)
+            && (self.contains(&Slot::Mask) || vm != self.contains(&Slot::Carry))
+    }
+}
+
+impl Deref for Layout {
+    type Target = [Slot];
+
+    fn deref(&self) -> &[Slot] {
+        &self.slots[..self.len as usize]
+    }
+}
+
+// the multiply-adds name the addend vd and put vs1 or rs1 before vs2
+fn is_multiply_add(name: &str) -> bool {
+    ["macc", "msac", "madd", "msub"]
+        .iter()
+        .any(|op| name.contains(op))
+}
+
+// shifts, gathers and slides take an unsigned immediate
+fn has_unsigned_imm(name: &str) -> bool {
+    matches!(
+        name,
+        "vsll"
+            | "vsrl"
+            | "vsra"
+            | "vssrl"
+            | "vssra"
+            | "vnsrl"
+            | "vnsra"
+            | "vnclipu"
+            | "vnclip"
+            | "vrgather"
+            | "vslideup"
+            | "vslidedown"
+            | "vwsll"
+    )
+}
+
+fn binary_layout(name: &str, suffix: &str) -> Layout {
+    let src = match suffix.as_bytes()[1] {
+        b'x' => Slot::Rs1,
+        b'f' => Slot::Fs1,
+        b'i' if name == "vror" => Slot::Uimm6,
+        b'i' if has_unsigned_imm(name) => Slot::Uimm,
+        b'i' => Slot::Simm,
+        _ => Slot::Vs1,
+    };
+    let mut layout = if is_multiply_add(name) {
+        Layout::new(&[Slot::Vd, src, Slot::Vs2])
+    } else {
+        Layout::new(&[Slot::Vd, Slot::Vs2, src])
+    };
+    // `vvm`, `vxm`, `vim` and `vfm` take v0 as carry-in or merge mask, the mask-register
+    // logical ops, vcompress and the carry-out without carry-in are never masked
+    if suffix.len() == 3 {
+        layout.push(Slot::Carry);
+    } else if !matches!(suffix, "mm" | "vm") && !matches!(name, "vmadc" | "vmsbc") {
+        layout.push(Slot::Mask);
+    }
+    layout
+}
+
+fn suffix_funct3(suffix: &str, vector: u32, scalar: u32) -> u32 {
+    match suffix.as_bytes()[1] {
+        b'x' | b'f' => scalar,
+        b'i' => OPIVI,
+        _ => vector,
+    }
+}
+
+// the mnemonic and operands of an OP-V arithmetic or OP-VE crypto instruction
+pub fn vector_op(v: VectorType) -> Option<(Mnemonic, Layout)> {
+    let (funct3, funct6, vs1, vs2, vm) = (v.funct3(), v.funct6(), v.vs1(), v.vs2(), v.vm());
+    if v.opcode() == OP_VE {
+        return CRYPTO.iter().find_map(|&(name, f6, code, slots)| {
+            let layout = Layout::new(slots);
+            (funct3 == OPMVV && f6 == funct6 && layout.fits(code, vs1, vs2, vm))
+                .then(|| (Mnemonic::from(name), layout))
+        });
+    }
+    for &(name, f3, f6, code, slots) in UNARY {
+        let layout = Layout::new(slots);
+        if (f3, f6) == (funct3, funct6) && layout.fits(code, vs1, vs2, vm) {
+            return Some((Mnemonic::from(name), layout));
+        }
+    }
+    for (table, vector, scalar) in GROUPS {
+        for &(name, f6, suffixes) in table {
+            for suffix in suffixes {
+                let layout = binary_layout(name, suffix);
+                let funct6 = match layout.contains(&Slot::Uimm6) {
+                    true => funct6 & !1,
+                    false => funct6,
+                };
+                if f6 == funct6
+                    && suffix_funct3(suffix, vector, scalar) == funct3
+                    && layout.fits(0, vs1, vs2, vm)
+                {
+                    return Some((mnemonic!("{}.{}", name, suffix), layout));
+                }
+            }
+        }
+    }
+    None
+}
+
+// the fields an OP-V or OP-VE mnemonic fixes, with vm set and the function code in vs1,
+// and its operands
+#[cfg(feature = "std")]
+pub fn vector_op_code(mnemonic: &str) -> Option<(VectorType, Layout)> {
+    let (opcode, funct3, funct6, code, layout) =
+        if let Some(&(_, funct3, funct6, code, slots)) = UNARY.iter().find(|op| op.0 == mnemonic) {
+            (OP_V, funct3, funct6, code, Layout::new(slots))
+        } else if let Some(&(_, funct6, code, slots)) = CRYPTO.iter().find(|op| op.0 == mnemonic) {
+            (OP_VE, OPMVV, funct6, code, Layout::new(slots))
+        } else {
+            let (name, suffix) = mnemonic.rsplit_once('.')?;
+            GROUPS.iter().find_map(|&(table, vector, scalar)| {
+                let &(_, funct6, _) = table
+                    .iter()
+                    .find(|op| op.0 == name && op.2.contains(&suffix))?;
+                let funct3 = suffix_funct3(suffix, vector, scalar);
+                Some((OP_V, funct3, funct6, 0, binary_layout(name, suffix)))
+            })?
+        };
+    let mut v = VectorType(0);
+    v.set_funct6(funct6);
+    v.set_vm(true);
+    v.set_vs1(code);
+    v.set_funct3(funct3);
+    v.set_opcode(opcode);
+    Some((v, layout))
+}
+
+// vector loads and stores by mop and, for unit-stride accesses, the lumop/sumop in the rs2
+// field. nf is the segment count minus one, or the register count minus one of the
+// whole-register accesses
+pub fn vector_mem_op(
+    store: bool,
+    nf: u32,
+    mop: u32,
+    umop: u32,
+    width: u32,
+    vm: bool,
+) -> Option<(Mnemonic, Layout)> {
+    let eew = match width {
+        0b000 => 8,
+        0b101 => 16,
+        0b110 => 32,
+        0b111 => 64,
+        _ => return None,
+    };
+    let dir = if store { 's' } else { 'l' };
+    let fields = nf + 1;
+    let (mnemonic, layout) = match (mop, umop) {
+        (0b00, 0b00000 | 0b10000) => {
+            let ff = match umop {
+                0b10000 if store => return None,
+                0b10000 => "ff",
+                _ => "",
+            };
+            let mnemonic = match nf {
+                0 => mnemonic!("v{}e{}{}.v", dir, eew, ff),
+                _ => mnemonic!("v{}seg{}e{}{}.v", dir, fields, eew, ff),
+            };
+            (mnemonic, Layout::new(&[Slot::Vd, Slot::Base, Slot::Mask]))
+        }
+        (0b00, 0b01000) if matches!(fields, 1 | 2 | 4 | 8) => {
+            let mnemonic = match store {
+                false => mnemonic!("vl{}re{}.v", fields, eew),
+                true if eew == 8 => mnemonic!("vs{}r.v", fields),
+                true => return None,
+            };
+            (mnemonic, Layout::new(&[Slot::Vd, Slot::Base]))
+        }
+        (0b00, 0b01011) if nf == 0 && eew == 8 => {
+            let mnemonic = mnemonic!("v{}m.v", dir);
+            (mnemonic, Layout::new(&[Slot::Vd, Slot::Base]))
+        }
+        (0b10, _) => {
+            let mnemonic = match nf {
+                0 => mnemonic!("v{}se{}.v", dir, eew),
+                _ => mnemonic!("v{}sseg{}e{}.v", dir, fields, eew),
+            };
+            let layout = Layout::new(&[Slot::Vd, Slot::Base, Slot::Stride, Slot::Mask]);
+            (mnemonic, layout)
+        }
+        (0b01 | 0b11, _) => {
+            let order = if mop == 0b01 { 'u' } else { 'o' };
+            let mnemonic = match nf {
+                0 => mnemonic!("v{}{}xei{}.v", dir, order, eew),
+                _ => mnemonic!("v{}{}xseg{}ei{}.v", dir, order, fields, eew),
+            };
+            let layout = Layout::new(&[Slot::Vd, Slot::Base, Slot::Index, Slot::Mask]);
+            (mnemonic, layout)
+        }
+        _ => return None,
+    };
+    (layout.contains(&Slot::Mask) || vm).then_some((mnemonic, layout))
+}
+
+// store, nf, mop, lumop/sumop and width of a vector load or store mnemonic, checked by
+// spelling the fields back out
+#[cfg(feature = "std")]
+pub fn vector_mem_code(mnemonic: &str) -> Option<(bool, u32, u32, u32, u32, Layout)> {
+    let rest = mnemonic.strip_suffix(".v")?;
+    let (store, rest) = match rest.strip_prefix("vl") {
+        Some(rest) => (false, rest),
+        None => (true, rest.strip_prefix("vs")?),
+    };
+    let (nf, mop, umop, width) = if rest == "m" {
+        (0, 0b00, 0b01011, 0b000)
+    } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        // whole-register vl<n>re<eew>.v and vs<n>r.v
+        let (count, eew) = rest.split_once('r')?;
+        let width = match eew.strip_prefix('e') {
+            Some(eew) => eew_width(eew)?,
+            None if eew.is_empty() => 0b000,
+            None => return None,
+        };
+        (
+            count.parse::<u32>().ok()?.checked_sub(1)?,
+            0b00,
+            0b01000,
+            width,
+        )
+    } else {
+        let (mop, rest) = if let Some(rest) = rest.strip_prefix("ux") {
+            (0b01, rest)
+        } else if let Some(rest) = rest.strip_prefix("ox") {
+            (0b11, rest)
+        } else if rest.starts_with("seg") {
+            (0b00, rest)
+        } else if let Some(rest) = rest.strip_prefix('s') {
+            (0b10, rest)
+        } else {
+            (0b00, rest)
+        };
+        let (fields, rest) = match rest.strip_prefix("seg") {
+            Some(rest) => {
+                let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+                (rest[..digits].parse::<u32>().ok()?, &rest[digits..])
+            }
+            None => (1, rest),
+        };
+        let (umop, rest) = match rest.strip_suffix("ff") {
+            Some(rest) => (0b10000, rest),
+            None => (0b00000, rest),
+        };
+        let eew = match mop {
+            0b01 | 0b11 => rest.strip_prefix("ei")?,
+            _ => rest.strip_prefix('e')?,
+        };
+        (fields.checked_sub(1)?, mop, umop, eew_width(eew)?)
+    };
+    if nf > 7 {
+        return None;
+    }
+    let (name, layout) = vector_mem_op(store, nf, mop, umop, width, true)?;
+    (name == mnemonic).then_some((store, nf, mop, umop, width, layout))
+}
+
+#[cfg(feature = "std")]
+fn eew_width(eew: &str) -> Option<u32> {
+    match eew {
+        "8" => Some(0b000),
+        "16" => Some(0b101),
+        "32" => Some(0b110),
+        "64" => Some(0b111),
+        _ => None,
+    }
+}
+
+// the operand a slot decodes to, an unset mask has none. Loads, stores and OP-V share the
+// positions of vd, rs1/vs1, rs2/vs2 and vm
+pub fn slot_operand(slot: Slot, instr: u32) -> Option<Operand> {
+    let vd = (instr >> 7) & 0x1f;
+    let vs1 = (instr >> 15) & 0x1f;
+    let vs2 = (instr >> 20) & 0x1f;
+    let operand = match slot {
+        Slot::Vd => Operand::vreg(vd),
+        Slot::Rd => Operand::reg(vd),
+        Slot::Fd => Operand::freg(vd),
+        Slot::Vs2 | Slot::Index => Operand::vreg(vs2),
+        Slot::Stride => Operand::reg(vs2),
+        Slot::Vs1 => Operand::vreg(vs1),
+        Slot::Rs1 | Slot::Base => Operand::reg(vs1),
+        Slot::Fs1 => Operand::freg(vs1),
+        Slot::Simm => Operand::imm(sign_extend(vs1, 5)),
+        Slot::Uimm => Operand::imm(vs1 as i32),
+        Slot::Uimm6 => Operand::imm((vs1 | ((instr >> 26) & 1) << 5) as i32),
+        Slot::Carry => Operand::vreg(0u32),
+        Slot::Mask if (instr >> 25) & 1 == 0 => Operand::VMask,
+        Slot::Mask => return None,
+    };
+    Some(operand)
+}
+
+// e.g. `e32, m4, ta, ma`, reserved settings and vill are shown as the number
+pub fn write_vtype(vtype: u32, f: &mut impl fmt::Write) -> fmt::Result {
+    let sew = (vtype >> 3) & 0x7;
+    let lmul = LMULS[(vtype & 0x7) as usize];
+    if vtype >> 8 != 0 || sew > 3 || lmul.is_empty() {
+        return write!(f, "{}", vtype);
+    }
+    let tail = if vtype & 0x40 != 0 { "ta" } else { "tu" };
+    let mask = if vtype & 0x80 != 0 { "ma" } else { "mu" };
+    write!(f, "e{}, {}, {}, {}", 8 << sew, lmul, tail, mask)
+}
+
+// the vtype operands split at their commas: the element width, then optionally LMUL and the
+// tail and mask policies in that order, which default to m1, tu and mu. A plain number is
+// taken as the raw immediate
+#[cfg(feature = "std")]
+pub fn parse_vtype(operands: &[String]) -> Result<u32> {
+    if let [imm] = operands
+        && let Ok(imm) = parse_immediate(imm)
+    {
+        return u32::try_from(imm).map_err(|_| Error::ImmediateOutOfRange);
+    }
+    let [sew, rest @ ..] = operands else {
+        return Err(Error::InvalidFormat);
+    };
+    let sew = match sew.as_str() {
+        "e8" => 0,
+        "e16" => 1,
+        "e32" => 2,
+        "e64" => 3,
+        _ => return Err(Error::InvalidVectorType),
+    };
+    let mut rest = rest.iter().map(String::as_str).peekable();
+    let lmul = match rest
+        .peek()
+        .and_then(|op| LMULS.iter().position(|l| !l.is_empty() && l == op))
+    {
+        Some(lmul) => {
+            rest.next();
+            lmul as u32
+        }
+        None => 0,
+    };
+    let tail = match rest.next_if(|op| matches!(*op, "ta" | "tu")) {
+        Some("ta") => 1,
+        _ => 0,
+    };
+    let mask = match rest.next_if(|op| matches!(*op, "ma" | "mu")) {
+        Some("ma") => 1,
+        _ => 0,
+    };
+    if rest.next().is_some() {
+        return Err(Error::InvalidVectorType);
+    }
+    Ok(mask << 7 | tail << 6 | sew << 3 | lmul)
+}
+
+// the operands of a decoded vector instruction, vset{i}vl{i} have no layout
+#[cfg(feature = "std")]
+pub fn instruction_layout(instr: &Instruction) -> Option<Layout> {
+    let (_, layout) = match instr {
+        Instruction::Vector(v) if v.opcode() == OP_VE || v.funct3() != OPCFG => vector_op(*v)?,
+        Instruction::VectorMem(v) => {
+            let store = v.opcode() == 0b0100111;
+            vector_mem_op(store, v.nf(), v.mop(), v.rs2(), v.width(), v.vm())?
+        }
+        _ => return None,
+    };
+    Some(layout)
+}